@@ -1,5 +1,5 @@
 import { getDevtoolsApi, getManagementApi } from '../config'
-import { unwrapResponse } from '../utils'
+import { apiFetch, unwrapResponse } from '../utils'
 
 // ============================================================================
 // Alert Types (engine.alerts.*)
@@ -28,7 +28,7 @@ export interface AlertsResponse {
 
 export async function fetchAlerts(): Promise<AlertsResponse> {
   try {
-    const res = await fetch(`${getDevtoolsApi()}/alerts`)
+    const res = await apiFetch(`${getDevtoolsApi()}/alerts`)
     if (res.ok) {
       return unwrapResponse<AlertsResponse>(res)
     }
@@ -36,7 +36,7 @@ export async function fetchAlerts(): Promise<AlertsResponse> {
     // Fall through to management API
   }
 
-  const res = await fetch(`${getManagementApi()}/alerts`)
+  const res = await apiFetch(`${getManagementApi()}/alerts`)
   if (!res.ok) throw new Error('Failed to fetch alerts')
   return unwrapResponse<AlertsResponse>(res)
 }
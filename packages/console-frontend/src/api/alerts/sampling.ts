@@ -1,5 +1,5 @@
 import { getDevtoolsApi, getManagementApi } from '../config'
-import { unwrapResponse } from '../utils'
+import { apiFetch, unwrapResponse } from '../utils'
 
 // ============================================================================
 // Sampling Rules Types (engine.sampling.*)
@@ -30,7 +30,7 @@ export interface SamplingRulesResponse {
 
 export async function fetchSamplingRules(): Promise<SamplingRulesResponse> {
   try {
-    const res = await fetch(`${getDevtoolsApi()}/sampling/rules`, {
+    const res = await apiFetch(`${getDevtoolsApi()}/sampling/rules`, {
       method: 'GET',
       headers: { 'Content-Type': 'application/json' },
     })
@@ -41,7 +41,7 @@ export async function fetchSamplingRules(): Promise<SamplingRulesResponse> {
     // Fall through to management API
   }
 
-  const res = await fetch(`${getManagementApi()}/sampling/rules`, {
+  const res = await apiFetch(`${getManagementApi()}/sampling/rules`, {
     method: 'GET',
     headers: { 'Content-Type': 'application/json' },
   })
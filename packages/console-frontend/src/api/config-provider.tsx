@@ -1,7 +1,7 @@
 import { useQuery } from '@tanstack/react-query'
 import { createContext, useContext } from 'react'
 import type { ConsoleConfig } from './config'
-import { setConfig } from './config'
+import { consoleUrl, setConfig } from './config'
 import { getConnectionErrorMessage } from './utils'
 
 const ConfigContext = createContext<ConsoleConfig | null>(null)
@@ -23,10 +23,15 @@ async function fetchConsoleConfig(): Promise<ConsoleConfig> {
   const timeout = setTimeout(() => controller.abort(), 5000)
 
   try {
-    const res = await fetch('/api/config', {
+    const res = await fetch(consoleUrl('/api/config'), {
       signal: controller.signal,
     })
 
+    if (res.status === 401) {
+      window.location.assign(consoleUrl('/login'))
+      throw new Error('Session expired, redirecting to login')
+    }
+
     if (!res.ok) {
       throw new Error(`Config fetch failed: ${res.status}`)
     }
@@ -1,13 +1,41 @@
 // Console configuration types and getter-based module
 // Config is set once at startup by ConfigProvider, then available everywhere.
 
+export interface EngineInfo {
+  name: string
+  engineHost: string
+  enginePort: number
+  wsPort: number
+  bridgePort: number
+  status: {
+    connected: boolean
+    lastError?: string | null
+    lastChecked?: number | null
+  }
+}
+
 export interface ConsoleConfig {
   engineHost: string
   enginePort: number
   wsPort: number
+  /** Path of the console's engine REST proxy, null when connecting directly */
+  engineUrl?: string | null
+  /** Path of the console's engine WebSocket proxy, null when connecting directly */
+  engineWsUrl?: string | null
   consolePort: number
   version: string
   enableFlow?: boolean
+  authEnabled?: boolean
+  user?: string
+  role?: string
+  allowedFunctions?: string[]
+  engines?: EngineInfo[]
+}
+
+declare global {
+  interface Window {
+    __CONSOLE_CONFIG__?: { basePath?: string }
+  }
 }
 
 let _config: ConsoleConfig | null = null
@@ -16,6 +44,21 @@ export function setConfig(config: ConsoleConfig): void {
   _config = config
 }
 
+/**
+ * Path prefix the console is served under (`--base-path`), without a trailing
+ * slash. Read from the config injected into index.html, so it is available
+ * before `/api/config` is fetched.
+ */
+export function getBasePath(): string {
+  const basePath = typeof window !== 'undefined' ? window.__CONSOLE_CONFIG__?.basePath : undefined
+  return basePath && basePath !== '/' ? basePath.replace(/\/+$/, '') : ''
+}
+
+/** A console server path, such as `/api/config`, under the base path. */
+export function consoleUrl(path: string): string {
+  return `${getBasePath()}${path}`
+}
+
 export function getConfig(): ConsoleConfig {
   if (!_config) {
     throw new Error(
@@ -25,22 +68,46 @@ export function getConfig(): ConsoleConfig {
   return _config
 }
 
-export function getDevtoolsApi(): string {
+/**
+ * Whether the current user's role allows calling a console bridge function.
+ * Allowlist entries ending in `*` match by prefix.
+ */
+export function canCall(functionId: string): boolean {
+  const allowed = getConfig().allowedFunctions
+  if (!allowed) return true
+  return allowed.some((pattern) =>
+    pattern.endsWith('*') ? functionId.startsWith(pattern.slice(0, -1)) : pattern === functionId,
+  )
+}
+
+/**
+ * Base URL of the engine REST API: the console's proxy on the console origin,
+ * or the engine itself when the console runs with --direct-engine.
+ */
+export function getEngineApi(): string {
   const c = getConfig()
+  if (c.engineUrl && typeof window !== 'undefined') {
+    return `${window.location.origin}${c.engineUrl}`
+  }
   const protocol = typeof window !== 'undefined' ? window.location.protocol : 'http:'
-  return `${protocol}//${c.engineHost}:${c.enginePort}/_console`
+  return `${protocol}//${c.engineHost}:${c.enginePort}`
+}
+
+export function getDevtoolsApi(): string {
+  return `${getEngineApi()}/_console`
 }
 
 export function getManagementApi(): string {
-  const c = getConfig()
-  const protocol = typeof window !== 'undefined' ? window.location.protocol : 'http:'
-  return `${protocol}//${c.engineHost}:${c.enginePort}/_console`
+  return `${getEngineApi()}/_console`
 }
 
 export function getStreamsWs(): string {
   const c = getConfig()
   const wsProtocol =
     typeof window !== 'undefined' && window.location.protocol === 'https:' ? 'wss:' : 'ws:'
+  if (c.engineWsUrl && typeof window !== 'undefined') {
+    return `${wsProtocol}//${window.location.host}${c.engineWsUrl}`
+  }
   return `${wsProtocol}//${c.engineHost}:${c.wsPort}`
 }
 
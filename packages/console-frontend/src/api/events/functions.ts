@@ -1,5 +1,5 @@
 import { getDevtoolsApi, getManagementApi } from '../config'
-import { unwrapResponse } from '../utils'
+import { apiFetch, unwrapResponse } from '../utils'
 
 // ============================================================================
 // Types
@@ -43,7 +43,7 @@ export async function fetchFunctions(options?: {
   const params = new URLSearchParams()
   if (options?.include_internal) params.set('include_internal', 'true')
   const qs = params.toString()
-  const res = await fetch(`${getDevtoolsApi()}/functions${qs ? `?${qs}` : ''}`)
+  const res = await apiFetch(`${getDevtoolsApi()}/functions${qs ? `?${qs}` : ''}`)
   if (!res.ok) throw new Error('Failed to fetch functions')
   const data = await unwrapResponse<{ functions: FunctionInfo[] }>(res)
   return {
@@ -52,6 +52,16 @@ export async function fetchFunctions(options?: {
   }
 }
 
+export async function fetchFunctionExample(
+  functionId: string,
+): Promise<{ function_id: string; has_schema: boolean; example: unknown }> {
+  const res = await apiFetch(
+    `${getDevtoolsApi()}/functions/${encodeURIComponent(functionId)}/example`,
+  )
+  if (!res.ok) throw new Error('Failed to fetch function example')
+  return unwrapResponse(res)
+}
+
 export async function fetchTriggers(options?: {
   include_internal?: boolean
 }): Promise<{ triggers: TriggerInfo[]; count: number }> {
@@ -59,7 +69,7 @@ export async function fetchTriggers(options?: {
   if (options?.include_internal) params.set('include_internal', 'true')
   const qs = params.toString()
   try {
-    const res = await fetch(`${getDevtoolsApi()}/triggers${qs ? `?${qs}` : ''}`)
+    const res = await apiFetch(`${getDevtoolsApi()}/triggers${qs ? `?${qs}` : ''}`)
     if (res.ok) {
       const data = await unwrapResponse<{ triggers: TriggerInfo[] }>(res)
       return {
@@ -71,7 +81,7 @@ export async function fetchTriggers(options?: {
     // Fall through to management API
   }
 
-  const res = await fetch(`${getManagementApi()}/triggers${qs ? `?${qs}` : ''}`)
+  const res = await apiFetch(`${getManagementApi()}/triggers${qs ? `?${qs}` : ''}`)
   if (!res.ok) throw new Error('Failed to fetch triggers')
   const data = await res.json()
   return {
@@ -84,7 +94,7 @@ export async function fetchTriggerTypes(): Promise<{
   trigger_types: string[]
   count: number
 }> {
-  const res = await fetch(`${getDevtoolsApi()}/trigger-types`)
+  const res = await apiFetch(`${getDevtoolsApi()}/trigger-types`)
   if (!res.ok) throw new Error('Failed to fetch trigger types')
   const data = await unwrapResponse<{ trigger_types: string[] }>(res)
   return {
@@ -94,7 +104,7 @@ export async function fetchTriggerTypes(): Promise<{
 }
 
 export async function fetchEventsInfo(): Promise<EventsInfo> {
-  const res = await fetch(`${getDevtoolsApi()}/events`)
+  const res = await apiFetch(`${getDevtoolsApi()}/events`)
   if (!res.ok) throw new Error('Failed to fetch events info')
   return unwrapResponse(res)
 }
@@ -1,4 +1,5 @@
 import { getDevtoolsApi } from '../config'
+import { apiFetch } from '../utils'
 
 async function extractApiError(res: Response, fallback: string): Promise<string> {
   const contentType = res.headers.get('content-type') || ''
@@ -16,12 +17,13 @@ async function extractApiError(res: Response, fallback: string): Promise<string>
             ? (data.body as Record<string, unknown>)
             : null
 
-        if (wrappedBody?.error && typeof wrappedBody.error === 'string') {
-          return wrappedBody.error
+        const error = wrappedBody?.error ?? ('error' in data ? data.error : undefined)
+        if (typeof error === 'string') {
+          return error
         }
-
-        if ('error' in data && typeof data.error === 'string') {
-          return data.error
+        if (error && typeof error === 'object' && 'message' in error) {
+          const { code, message } = error as { code?: string; message: string }
+          return code ? `${message} (${code})` : message
         }
 
         return JSON.stringify(wrappedBody ?? data)
@@ -44,7 +46,7 @@ export async function invokeFunction(
   input?: unknown,
 ): Promise<{ success: boolean; data?: unknown; error?: string }> {
   try {
-    const res = await fetch(`${getDevtoolsApi()}/invoke`, {
+    const res = await apiFetch(`${getDevtoolsApi()}/invoke`, {
       method: 'POST',
       headers: { 'Content-Type': 'application/json' },
       body: JSON.stringify({ function_id: functionId, input: input || {} }),
@@ -67,7 +69,7 @@ export async function emitEvent(
   data: unknown,
 ): Promise<{ success: boolean; error?: string }> {
   try {
-    const res = await fetch(`${getDevtoolsApi()}/emit`, {
+    const res = await apiFetch(`${getDevtoolsApi()}/emit`, {
       method: 'POST',
       headers: { 'Content-Type': 'application/json' },
       body: JSON.stringify({ topic, data }),
@@ -95,7 +97,7 @@ export async function triggerCron(
   functionId?: string,
 ): Promise<{ success: boolean; data?: unknown; error?: string }> {
   try {
-    const res = await fetch(`${getDevtoolsApi()}/cron/trigger`, {
+    const res = await apiFetch(`${getDevtoolsApi()}/cron/trigger`, {
       method: 'POST',
       headers: { 'Content-Type': 'application/json' },
       body: JSON.stringify({ trigger_id: triggerId, function_id: functionId }),
@@ -7,11 +7,15 @@ export { fetchAlerts } from './alerts/alerts'
 export type { SamplingRule, SamplingRulesResponse } from './alerts/sampling'
 export { fetchSamplingRules } from './alerts/sampling'
 // Configuration
-export type { ConsoleConfig } from './config'
+export type { ConsoleConfig, EngineInfo } from './config'
 export {
+  canCall,
+  consoleUrl,
+  getBasePath,
   getConfig,
   getConnectionInfo,
   getDevtoolsApi,
+  getEngineApi,
   getManagementApi,
   getStreamsWs,
   setConfig,
@@ -26,6 +30,7 @@ export type {
 } from './events/functions'
 export {
   fetchEventsInfo,
+  fetchFunctionExample,
   fetchFunctions,
   fetchTriggers,
   fetchTriggerTypes,
@@ -51,9 +56,10 @@ export type {
   LogEntry,
   LogWriteInput,
   OtelLog,
+  OtelLogStreamFilters,
   OtelLogsResponse,
 } from './observability/logs'
-export { clearOtelLogs, fetchLogs, fetchOtelLogs } from './observability/logs'
+export { clearOtelLogs, fetchLogs, fetchOtelLogs, streamOtelLogs } from './observability/logs'
 // Observability - Metrics
 export type {
   DetailedMetricsResponse,
@@ -73,24 +79,41 @@ export {
 } from './observability/metrics'
 // Observability - Traces
 export type {
+  CompletedTrace,
   SpanEvent,
   SpanLink,
   SpanTreeNode,
   StoredSpan,
   TracesFilterParams,
   TracesResponse,
+  TraceStreamFilters,
   TraceTreeResponse,
 } from './observability/traces'
-export { clearTraces, fetchTraces, fetchTraceTree } from './observability/traces'
+export { clearTraces, fetchTraces, fetchTraceTree, streamTraces } from './observability/traces'
 // Queries (React Query)
 export * from './queries'
 // State
-export type { StateGroup, StateItem } from './state/state'
+export type {
+  StateBulkResult,
+  StateGroup,
+  StateImportMode,
+  StateImportReport,
+  StateItem,
+  StateItemsQuery,
+  StateMatch,
+} from './state/state'
 export {
+  clearStateGroup,
+  deleteMatchingStateItems,
   deleteStateItem,
+  deleteStateItems,
   fetchStateGroups,
   fetchStateItems,
+  importState,
   setStateItem,
+  setStateItems,
+  StateConflictError,
+  stateExportUrl,
 } from './state/state'
 // Streams
 export type { StreamInfo } from './state/streams'
@@ -123,9 +146,9 @@ export type {
   StreamUpdateOp,
   StreamUpdateResult,
 } from './types/shared'
-export type { WrappedResponse } from './utils'
+export type { ConsoleErrorBody, WrappedResponse } from './utils'
 // Utilities
-export { fetchWithFallback, unwrapResponse } from './utils'
+export { apiFetch, ConsoleApiError, fetchWithFallback, unwrapResponse } from './utils'
 
 // WebSocket
 export * from './websocket'
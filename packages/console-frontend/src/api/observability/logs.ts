@@ -1,5 +1,5 @@
-import { getDevtoolsApi, getManagementApi } from '../config'
-import { unwrapResponse } from '../utils'
+import { consoleUrl, getDevtoolsApi, getManagementApi } from '../config'
+import { apiFetch, unwrapResponse } from '../utils'
 
 // ============================================================================
 // Log Types (engine.otel.logs.*)
@@ -84,7 +84,7 @@ export async function fetchOtelLogs(options?: {
   }
 
   try {
-    const res = await fetch(`${getDevtoolsApi()}/otel/logs`, {
+    const res = await apiFetch(`${getDevtoolsApi()}/otel/logs`, {
       method: 'POST',
       headers: { 'Content-Type': 'application/json' },
       body: JSON.stringify(body),
@@ -96,7 +96,7 @@ export async function fetchOtelLogs(options?: {
     // Fall through to management API
   }
 
-  const res = await fetch(`${getManagementApi()}/otel/logs`, {
+  const res = await apiFetch(`${getManagementApi()}/otel/logs`, {
     method: 'POST',
     headers: { 'Content-Type': 'application/json' },
     body: JSON.stringify(body),
@@ -105,9 +105,48 @@ export async function fetchOtelLogs(options?: {
   return res.json()
 }
 
+export interface OtelLogStreamFilters {
+  /** Minimum severity, as a level name such as `warn` or an OTEL severity number */
+  severity?: string
+  service?: string
+  trace_id?: string
+  /** Substring of the log body */
+  q?: string
+  /** Unix timestamp in milliseconds to replay records from */
+  since?: number
+  engine?: string
+}
+
+/**
+ * Follow new OTEL log records through the console's Server-Sent Events stream.
+ * The browser reconnects on its own and resumes after the last record received.
+ * Returns a function that closes the stream.
+ */
+export function streamOtelLogs(
+  filters: OtelLogStreamFilters,
+  onLog: (log: OtelLog) => void,
+  onError?: (error: { code: string; message: string; retryable: boolean }) => void,
+): () => void {
+  const params = new URLSearchParams()
+  for (const [key, value] of Object.entries(filters)) {
+    if (value !== undefined && value !== '') params.set(key, String(value))
+  }
+  const query = params.toString()
+  const source = new EventSource(consoleUrl(`/api/logs/stream${query ? `?${query}` : ''}`))
+
+  source.addEventListener('log', (event) => {
+    onLog(JSON.parse((event as MessageEvent).data) as OtelLog)
+  })
+  source.addEventListener('engine_error', (event) => {
+    onError?.(JSON.parse((event as MessageEvent).data).error)
+  })
+
+  return () => source.close()
+}
+
 export async function clearOtelLogs(): Promise<{ success: boolean }> {
   try {
-    const res = await fetch(`${getDevtoolsApi()}/otel/logs/clear`, {
+    const res = await apiFetch(`${getDevtoolsApi()}/otel/logs/clear`, {
       method: 'POST',
     })
     if (res.ok) {
@@ -118,7 +157,7 @@ export async function clearOtelLogs(): Promise<{ success: boolean }> {
     // Fall through to management API
   }
 
-  const res = await fetch(`${getManagementApi()}/otel/logs/clear`, {
+  const res = await apiFetch(`${getManagementApi()}/otel/logs/clear`, {
     method: 'POST',
   })
   if (!res.ok) throw new Error('Failed to clear OTEL logs')
@@ -1,6 +1,6 @@
 import { getDevtoolsApi, getManagementApi } from '../config'
 import type { MetricsSnapshot } from '../types/shared'
-import { unwrapResponse } from '../utils'
+import { apiFetch, unwrapResponse } from '../utils'
 
 // ============================================================================
 // Metrics Types (engine.metrics.*)
@@ -78,7 +78,7 @@ export interface RollupsResponse {
 }
 
 export async function fetchMetrics(): Promise<MetricsSnapshot> {
-  const res = await fetch(`${getDevtoolsApi()}/metrics`)
+  const res = await apiFetch(`${getDevtoolsApi()}/metrics`)
   if (!res.ok) throw new Error('Failed to fetch metrics')
   return unwrapResponse(res)
 }
@@ -89,7 +89,7 @@ export async function fetchMetricsHistory(
   const url = limit
     ? `${getDevtoolsApi()}/metrics/history?limit=${limit}`
     : `${getDevtoolsApi()}/metrics/history`
-  const res = await fetch(url)
+  const res = await apiFetch(url)
   if (!res.ok) throw new Error('Failed to fetch metrics history')
   return unwrapResponse(res)
 }
@@ -108,7 +108,7 @@ export async function fetchDetailedMetrics(options?: {
   }
 
   try {
-    const res = await fetch(`${getDevtoolsApi()}/metrics/detailed`, {
+    const res = await apiFetch(`${getDevtoolsApi()}/metrics/detailed`, {
       method: 'POST',
       headers: { 'Content-Type': 'application/json' },
       body: JSON.stringify(body),
@@ -120,7 +120,7 @@ export async function fetchDetailedMetrics(options?: {
     // Fall through to management API
   }
 
-  const res = await fetch(`${getManagementApi()}/metrics/detailed`, {
+  const res = await apiFetch(`${getManagementApi()}/metrics/detailed`, {
     method: 'POST',
     headers: { 'Content-Type': 'application/json' },
     body: JSON.stringify(body),
@@ -143,7 +143,7 @@ export async function fetchRollups(options?: {
   }
 
   try {
-    const res = await fetch(`${getManagementApi()}/rollups`, {
+    const res = await apiFetch(`${getManagementApi()}/rollups`, {
       method: 'POST',
       headers: { 'Content-Type': 'application/json' },
       body: JSON.stringify(body),
@@ -155,7 +155,7 @@ export async function fetchRollups(options?: {
     // Fall through to management API
   }
 
-  const res = await fetch(`${getManagementApi()}/rollups`, {
+  const res = await apiFetch(`${getManagementApi()}/rollups`, {
     method: 'POST',
     headers: { 'Content-Type': 'application/json' },
     body: JSON.stringify(body),
@@ -1,5 +1,5 @@
-import { getDevtoolsApi, getManagementApi } from '../config'
-import { unwrapResponse } from '../utils'
+import { consoleUrl, getDevtoolsApi, getManagementApi } from '../config'
+import { apiFetch, unwrapResponse } from '../utils'
 
 // ============================================================================
 // Trace Types (engine.otel.traces.*)
@@ -107,7 +107,7 @@ export async function fetchTraces(options?: TracesFilterParams): Promise<TracesR
   if (options?.include_internal !== undefined) body.include_internal = options.include_internal
 
   try {
-    const res = await fetch(`${getDevtoolsApi()}/otel/traces`, {
+    const res = await apiFetch(`${getDevtoolsApi()}/otel/traces`, {
       method: 'POST',
       headers: { 'Content-Type': 'application/json' },
       body: JSON.stringify(body),
@@ -119,7 +119,7 @@ export async function fetchTraces(options?: TracesFilterParams): Promise<TracesR
     // Fall through to management API
   }
 
-  const res = await fetch(`${getManagementApi()}/otel/traces`, {
+  const res = await apiFetch(`${getManagementApi()}/otel/traces`, {
     method: 'POST',
     headers: { 'Content-Type': 'application/json' },
     body: JSON.stringify(body),
@@ -132,7 +132,7 @@ export async function fetchTraceTree(traceId: string): Promise<TraceTreeResponse
   const body = { trace_id: traceId }
 
   try {
-    const res = await fetch(`${getDevtoolsApi()}/otel/traces/tree`, {
+    const res = await apiFetch(`${getDevtoolsApi()}/otel/traces/tree`, {
       method: 'POST',
       headers: { 'Content-Type': 'application/json' },
       body: JSON.stringify(body),
@@ -144,7 +144,7 @@ export async function fetchTraceTree(traceId: string): Promise<TraceTreeResponse
     // Fall through to management API
   }
 
-  const res = await fetch(`${getManagementApi()}/otel/traces/tree`, {
+  const res = await apiFetch(`${getManagementApi()}/otel/traces/tree`, {
     method: 'POST',
     headers: { 'Content-Type': 'application/json' },
     body: JSON.stringify(body),
@@ -153,9 +153,67 @@ export async function fetchTraceTree(traceId: string): Promise<TraceTreeResponse
   return res.json()
 }
 
+export interface TraceStreamFilters {
+  service?: string
+  function_id?: string
+  min_duration_ms?: number
+  /** Unix timestamp in milliseconds to replay traces from */
+  since?: number
+  engine?: string
+}
+
+/** A trace that stopped receiving spans, as sent by the live trace stream. */
+export interface CompletedTrace {
+  trace_id: string
+  root_span_id: string
+  name?: string | null
+  service_name?: string | null
+  function_id?: string | null
+  status?: string | null
+  start_time_unix_nano: number
+  end_time_unix_nano: number
+  duration_ms: number
+  span_count: number
+  error_count: number
+  services: string[]
+}
+
+/**
+ * Follow an engine's traces through the console's Server-Sent Events stream:
+ * `onRootSpan` runs when a root span is recorded, `onTrace` once its trace completes.
+ * Returns a function that closes the stream.
+ */
+export function streamTraces(
+  filters: TraceStreamFilters,
+  handlers: {
+    onRootSpan?: (span: StoredSpan) => void
+    onTrace?: (trace: CompletedTrace) => void
+    onError?: (error: { code: string; message: string; retryable: boolean }) => void
+  },
+): () => void {
+  const params = new URLSearchParams()
+  for (const [key, value] of Object.entries(filters)) {
+    if (value !== undefined && value !== '') params.set(key, String(value))
+  }
+  const query = params.toString()
+  const source = new EventSource(consoleUrl(`/api/traces/stream${query ? `?${query}` : ''}`))
+
+  source.addEventListener('root_span', (event) => {
+    handlers.onRootSpan?.(JSON.parse((event as MessageEvent).data) as StoredSpan)
+  })
+  source.addEventListener('trace', (event) => {
+    handlers.onTrace?.(JSON.parse((event as MessageEvent).data) as CompletedTrace)
+  })
+  source.addEventListener('engine_error', (event) => {
+    handlers.onError?.(JSON.parse((event as MessageEvent).data).error)
+  })
+
+  return () => source.close()
+}
+
 export async function clearTraces(): Promise<{ success: boolean }> {
   try {
-    const res = await fetch(`${getDevtoolsApi()}/otel/traces/clear`, {
+    const res = await apiFetch(`${getDevtoolsApi()}/otel/traces/clear`, {
       method: 'POST',
     })
     if (res.ok) {
@@ -166,7 +224,7 @@ export async function clearTraces(): Promise<{ success: boolean }> {
     // Fall through to management API
   }
 
-  const res = await fetch(`${getManagementApi()}/otel/traces/clear`, {
+  const res = await apiFetch(`${getManagementApi()}/otel/traces/clear`, {
     method: 'POST',
   })
   if (!res.ok) throw new Error('Failed to clear traces')
@@ -16,7 +16,7 @@ import {
   fetchRollups,
 } from './observability/metrics'
 import { fetchTraces, fetchTraceTree } from './observability/traces'
-import { fetchStateGroups, fetchStateItems } from './state/state'
+import { fetchStateGroups, fetchStateItems, type StateItemsQuery } from './state/state'
 import { fetchStreams } from './state/streams'
 import { fetchAdapters } from './system/adapters'
 import { fetchConfig, fetchStatus, healthCheck } from './system/status'
@@ -119,10 +119,10 @@ export const stateGroupsQuery = () =>
   })
 
 // State items for a group
-export const stateItemsQuery = (groupId: string) =>
+export const stateItemsQuery = (groupId: string, query: StateItemsQuery = {}) =>
   queryOptions({
-    queryKey: ['state-items', groupId],
-    queryFn: () => fetchStateItems(groupId),
+    queryKey: ['state-items', groupId, query],
+    queryFn: () => fetchStateItems(groupId, query),
     enabled: !!groupId,
   })
 
@@ -1,5 +1,5 @@
-import { getDevtoolsApi } from '../config'
-import { unwrapResponse } from '../utils'
+import { consoleUrl, getDevtoolsApi } from '../config'
+import { apiFetch, unwrapResponse } from '../utils'
 
 // ============================================================================
 // State Types
@@ -15,7 +15,44 @@ export interface StateItem {
 
 export interface StateGroup {
   id: string
+  /** Null when counts are turned off or the group could not be listed */
+  count: number | null
+  size_bytes?: number
+  last_modified?: number | null
+  error?: string
+}
+
+export interface StateItemsQuery {
+  /** `next_cursor` of the previous page */
+  cursor?: string
+  /** Items per page, 1-1000 (default 100) */
+  limit?: number
+  prefix?: string
+  /** Glob on keys, with `*` and `?` */
+  pattern?: string
+  /** JSONPath predicate on values, e.g. `@.status == "active"` */
+  filter?: string
+  order?: 'asc' | 'desc'
+}
+
+/** Outcome of a bulk state call: a preview when `dry_run`, else one result per key */
+export interface StateBulkResult {
+  dry_run: boolean
   count: number
+  keys?: string[]
+  succeeded?: number
+  failed?: number
+  results?: { key: string; ok: boolean; error?: string }[]
+  /** Matched items without a key, which cannot be deleted */
+  skipped?: number
+  /** Dry runs of a group clear: the token the clear must be confirmed with */
+  confirm_token?: string
+}
+
+export interface StateMatch {
+  prefix?: string
+  pattern?: string
+  filter?: string
 }
 
 // ============================================================================
@@ -24,14 +61,19 @@ export interface StateGroup {
 
 export async function fetchStateItems(
   groupId: string,
-): Promise<{ items: StateItem[]; count: number }> {
-  const res = await fetch(`${getDevtoolsApi()}/states/group`, {
+  query: StateItemsQuery = {},
+): Promise<{ items: StateItem[]; count: number; total: number; nextCursor: string | null }> {
+  const res = await apiFetch(`${getDevtoolsApi()}/states/group`, {
     method: 'POST',
     headers: { 'Content-Type': 'application/json' },
-    body: JSON.stringify({ scope: groupId }),
+    body: JSON.stringify({ scope: groupId, ...query }),
   })
   if (!res.ok) throw new Error('Failed to fetch state items')
-  const data = await unwrapResponse<{ items: unknown[] }>(res)
+  const data = await unwrapResponse<{
+    items: unknown[]
+    total: number
+    next_cursor: string | null
+  }>(res)
   const items: StateItem[] = (data.items || []).map((item: unknown, index: number) => {
     const typedItem = item as Record<string, unknown>
     return {
@@ -42,14 +84,19 @@ export async function fetchStateItems(
       timestamp: Date.now(),
     }
   })
-  return { items, count: items.length }
+  return {
+    items,
+    count: items.length,
+    total: data.total ?? items.length,
+    nextCursor: data.next_cursor ?? null,
+  }
 }
 
 export async function fetchStateGroups(): Promise<{
   groups: StateGroup[]
   count: number
 }> {
-  const res = await fetch(`${getDevtoolsApi()}/states/groups`, {
+  const res = await apiFetch(`${getDevtoolsApi()}/states/groups`, {
     method: 'GET',
     headers: { 'Content-Type': 'application/json' },
   })
@@ -58,21 +105,132 @@ export async function fetchStateGroups(): Promise<{
   return { groups: data.groups || [], count: (data.groups || []).length }
 }
 
-export async function setStateItem(groupId: string, key: string, value: unknown): Promise<void> {
-  const res = await fetch(`${getDevtoolsApi()}/states/${encodeURIComponent(groupId)}/item`, {
+/** Thrown when an item changed since it was read; `current` is its value now */
+export class StateConflictError extends Error {
+  readonly current: unknown
+  readonly currentHash: string
+
+  constructor(current: unknown, currentHash: string) {
+    super('The item changed since it was read')
+    this.name = 'StateConflictError'
+    this.current = current
+    this.currentHash = currentHash
+  }
+}
+
+async function stateWriteError(res: Response, fallback: string): Promise<Error> {
+  if (res.status !== 409) return new Error(fallback)
+  const data = await res.json().catch(() => null)
+  const details = data?.error?.details ?? data?.body?.error?.details
+  return new StateConflictError(details?.current ?? null, details?.current_hash ?? '')
+}
+
+/**
+ * Set an item. With `expected`, the value last read, the write is refused
+ * with a StateConflictError if the item has changed since.
+ */
+export async function setStateItem(
+  groupId: string,
+  key: string,
+  value: unknown,
+  expected?: unknown,
+): Promise<void> {
+  const res = await apiFetch(`${getDevtoolsApi()}/states/${encodeURIComponent(groupId)}/item`, {
     method: 'POST',
     headers: { 'Content-Type': 'application/json' },
-    body: JSON.stringify({ key, value }),
+    body: JSON.stringify(expected === undefined ? { key, value } : { key, value, expected }),
   })
-  if (!res.ok) throw new Error('Failed to set state item')
+  if (!res.ok) throw await stateWriteError(res, 'Failed to set state item')
 }
 
-export async function deleteStateItem(groupId: string, key: string): Promise<void> {
-  const res = await fetch(
+/** Delete an item, only while it still holds `expected` when that is given */
+export async function deleteStateItem(
+  groupId: string,
+  key: string,
+  expected?: unknown,
+): Promise<void> {
+  const res = await apiFetch(
     `${getDevtoolsApi()}/states/${encodeURIComponent(groupId)}/item/${encodeURIComponent(key)}`,
-    {
-      method: 'DELETE',
-    },
+    expected === undefined
+      ? { method: 'DELETE' }
+      : {
+          method: 'DELETE',
+          headers: { 'Content-Type': 'application/json' },
+          body: JSON.stringify({ expected }),
+        },
   )
-  if (!res.ok) throw new Error('Failed to delete state item')
+  if (!res.ok) throw await stateWriteError(res, 'Failed to delete state item')
+}
+
+async function postStateBulk(
+  groupId: string,
+  action: string,
+  body: Record<string, unknown>,
+): Promise<StateBulkResult> {
+  const res = await apiFetch(`${getDevtoolsApi()}/states/${encodeURIComponent(groupId)}/${action}`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify(body),
+  })
+  if (!res.ok) throw new Error('Failed to update state items')
+  return unwrapResponse<StateBulkResult>(res)
+}
+
+export function setStateItems(
+  groupId: string,
+  items: { key: string; value: unknown }[],
+  dryRun = false,
+): Promise<StateBulkResult> {
+  return postStateBulk(groupId, 'items', { items, dry_run: dryRun })
+}
+
+export function deleteStateItems(
+  groupId: string,
+  keys: string[],
+  dryRun = false,
+): Promise<StateBulkResult> {
+  return postStateBulk(groupId, 'items/delete', { keys, dry_run: dryRun })
+}
+
+export function deleteMatchingStateItems(
+  groupId: string,
+  match: StateMatch,
+  dryRun = false,
+): Promise<StateBulkResult> {
+  return postStateBulk(groupId, 'items/delete-matching', { ...match, dry_run: dryRun })
+}
+
+/** Preview a clear without `confirm`, then pass the preview's `confirm_token` */
+export function clearStateGroup(groupId: string, confirm?: string): Promise<StateBulkResult> {
+  return postStateBulk(groupId, 'clear', confirm ? { confirm } : { dry_run: true })
+}
+
+/** Download URL streaming the groups as NDJSON */
+export function stateExportUrl(groupIds: string[]): string {
+  const query = new URLSearchParams({ groups: groupIds.join(',') })
+  return consoleUrl(`/api/states/export?${query}`)
+}
+
+export type StateImportMode = 'overwrite' | 'skip-existing' | 'fail'
+
+export interface StateImportReport {
+  groups: string[]
+  imported: number
+  skipped: number
+  failed: number
+  errors: { group: string; key: string; error: string }[]
+}
+
+/** Import a JSON or NDJSON export file's text */
+export async function importState(
+  text: string,
+  mode: StateImportMode = 'fail',
+): Promise<StateImportReport> {
+  const res = await apiFetch(`${getDevtoolsApi()}/states/import`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({ data: text, mode }),
+  })
+  if (!res.ok) throw new Error('Failed to import state')
+  return unwrapResponse<StateImportReport>(res)
 }
@@ -1,5 +1,5 @@
 import { getConfig, getDevtoolsApi } from '../config'
-import { unwrapResponse } from '../utils'
+import { apiFetch, unwrapResponse } from '../utils'
 
 // ============================================================================
 // Stream Types
@@ -25,7 +25,7 @@ export async function fetchStreams(): Promise<{
 }> {
   console.log('[Streams API] Starting fetch from:', `${getDevtoolsApi()}/streams/list`)
   try {
-    const res = await fetch(`${getDevtoolsApi()}/streams/list`, {
+    const res = await apiFetch(`${getDevtoolsApi()}/streams/list`, {
       method: 'GET',
     })
 
@@ -1,5 +1,5 @@
 import { getDevtoolsApi } from '../config'
-import { unwrapResponse } from '../utils'
+import { apiFetch, unwrapResponse } from '../utils'
 
 export interface AdapterInfo {
   id: string
@@ -13,7 +13,7 @@ export interface AdapterInfo {
 }
 
 export async function fetchAdapters(): Promise<{ adapters: AdapterInfo[]; count: number }> {
-  const res = await fetch(`${getDevtoolsApi()}/adapters`)
+  const res = await apiFetch(`${getDevtoolsApi()}/adapters`)
   if (!res.ok) throw new Error('Failed to fetch adapters')
   return unwrapResponse(res)
 }
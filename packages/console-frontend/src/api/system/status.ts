@@ -1,5 +1,5 @@
 import { getDevtoolsApi, getManagementApi } from '../config'
-import { unwrapResponse } from '../utils'
+import { apiFetch, unwrapResponse } from '../utils'
 
 export interface SystemStatus {
   status: string
@@ -40,7 +40,7 @@ export interface HealthStatus {
 
 export async function fetchStatus(): Promise<SystemStatus> {
   try {
-    const res = await fetch(`${getDevtoolsApi()}/status`)
+    const res = await apiFetch(`${getDevtoolsApi()}/status`)
     if (res.ok) {
       const data = await unwrapResponse<{
         status: string
@@ -64,7 +64,7 @@ export async function fetchStatus(): Promise<SystemStatus> {
     // Fall through to management API
   }
 
-  const res = await fetch(`${getManagementApi()}/status`)
+  const res = await apiFetch(`${getManagementApi()}/status`)
   if (!res.ok) throw new Error('Failed to fetch status')
   const data = await res.json()
 
@@ -82,7 +82,7 @@ export async function fetchStatus(): Promise<SystemStatus> {
 
 export async function fetchConfig(): Promise<DevToolsConfig> {
   try {
-    const res = await fetch(`${getDevtoolsApi()}/config`)
+    const res = await apiFetch(`${getDevtoolsApi()}/config`)
     if (res.ok) {
       return unwrapResponse(res)
     }
@@ -90,20 +90,20 @@ export async function fetchConfig(): Promise<DevToolsConfig> {
     // Fall through to management API
   }
 
-  const res = await fetch(`${getManagementApi()}/config`)
+  const res = await apiFetch(`${getManagementApi()}/config`)
   if (!res.ok) throw new Error('Failed to fetch config')
   return res.json()
 }
 
 export async function healthCheck(): Promise<HealthStatus> {
-  const res = await fetch(`${getDevtoolsApi()}/health`)
+  const res = await apiFetch(`${getDevtoolsApi()}/health`)
   if (!res.ok) throw new Error('Health check failed')
   return unwrapResponse(res)
 }
 
 export async function isDevToolsAvailable(): Promise<boolean> {
   try {
-    const res = await fetch(`${getDevtoolsApi()}/health`)
+    const res = await apiFetch(`${getDevtoolsApi()}/health`)
     return res.ok
   } catch {
     return false
@@ -112,7 +112,7 @@ export async function isDevToolsAvailable(): Promise<boolean> {
 
 export async function isManagementApiAvailable(): Promise<boolean> {
   try {
-    const res = await fetch(`${getManagementApi()}/status`)
+    const res = await apiFetch(`${getManagementApi()}/status`)
     return res.ok
   } catch {
     return false
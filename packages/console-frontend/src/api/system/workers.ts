@@ -1,5 +1,5 @@
 import { getDevtoolsApi } from '../config'
-import { unwrapResponse } from '../utils'
+import { apiFetch, unwrapResponse } from '../utils'
 
 export interface WorkerMetrics {
   cpu_percent: number
@@ -36,7 +36,7 @@ export async function fetchWorkers(): Promise<{
   count: number
   timestamp: number
 }> {
-  const res = await fetch(`${getDevtoolsApi()}/workers`)
+  const res = await apiFetch(`${getDevtoolsApi()}/workers`)
   if (!res.ok) throw new Error('Failed to fetch workers')
   const data = await unwrapResponse<{ workers: WorkerInfo[]; timestamp: number }>(res)
   return {
@@ -1,4 +1,4 @@
-import { getDevtoolsApi } from './config'
+import { getConfig, getDevtoolsApi } from './config'
 
 interface WrappedResponse<T> {
   status_code: number
@@ -6,6 +6,41 @@ interface WrappedResponse<T> {
   body: T
 }
 
+/** Error envelope returned by the console bridge functions */
+export interface ConsoleErrorBody {
+  code: string
+  message: string
+  details: unknown
+  retryable: boolean
+  trace_id: string | null
+}
+
+/** Error thrown for a console error response, keeping its machine-readable code */
+export class ConsoleApiError extends Error {
+  readonly code: string
+  readonly details: unknown
+  readonly retryable: boolean
+  readonly traceId: string | null
+
+  constructor(error: ConsoleErrorBody) {
+    super(error.message)
+    this.name = 'ConsoleApiError'
+    this.code = error.code
+    this.details = error.details
+    this.retryable = error.retryable
+    this.traceId = error.trace_id
+  }
+}
+
+function consoleErrorOf(body: unknown): ConsoleErrorBody | null {
+  if (!body || typeof body !== 'object' || !('error' in body)) return null
+  const error = (body as { error: unknown }).error
+  if (error && typeof error === 'object' && 'code' in error && 'message' in error) {
+    return error as ConsoleErrorBody
+  }
+  return null
+}
+
 const CORS_ERROR_MESSAGE =
   'Connection blocked by CORS. Check iii-engine CORS settings for this console origin.'
 
@@ -14,7 +49,9 @@ async function unwrapResponse<T>(res: Response): Promise<T> {
 
   if (data && typeof data === 'object' && 'status_code' in data && 'body' in data) {
     const wrapped = data as WrappedResponse<T>
-    if (wrapped.status_code !== 200) {
+    if (wrapped.status_code < 200 || wrapped.status_code >= 300) {
+      const error = consoleErrorOf(wrapped.body)
+      if (error) throw new ConsoleApiError(error)
       throw new Error(`API Error: ${JSON.stringify(wrapped.body)}`)
     }
     return wrapped.body
@@ -55,13 +92,22 @@ export function getConnectionErrorMessage(error: unknown, fallback = 'Network er
   return fallback
 }
 
+/**
+ * `fetch` for the console bridge API. When console auth is enabled, requests
+ * carry the session cookie so the bridge handlers can verify the caller.
+ */
+export function apiFetch(input: string, init?: RequestInit): Promise<Response> {
+  if (!getConfig().authEnabled) return fetch(input, init)
+  return fetch(input, { ...init, credentials: 'include' })
+}
+
 export async function fetchWithFallback<T>(
   devtoolsPath: string,
   _managementPath?: string,
   options?: RequestInit,
 ): Promise<T> {
   try {
-    const res = await fetch(`${getDevtoolsApi()}${devtoolsPath}`, options)
+    const res = await apiFetch(`${getDevtoolsApi()}${devtoolsPath}`, options)
     if (!res.ok) {
       throw new Error(`Failed to fetch from ${devtoolsPath}: ${res.status}`)
     }
@@ -3,6 +3,7 @@ import { ReactQueryDevtools } from '@tanstack/react-query-devtools'
 import { createRouter, RouterProvider } from '@tanstack/react-router'
 import { StrictMode } from 'react'
 import { createRoot } from 'react-dom/client'
+import { getBasePath } from './api/config'
 import { ConfigProvider } from './api/config-provider'
 import { routeTree } from './routeTree.gen'
 import './styles/globals.css'
@@ -21,6 +22,7 @@ const router = createRouter({
   routeTree,
   context: { queryClient },
   defaultPreload: 'intent',
+  basepath: getBasePath() || '/',
 })
 
 declare module '@tanstack/react-router' {
@@ -30,6 +30,7 @@ import { useMemo, useState } from 'react'
 import type { SystemStatus } from '@/api'
 import {
   adaptersQuery,
+  apiFetch,
   configQuery,
   functionsQuery,
   statusQuery,
@@ -120,7 +121,7 @@ function ConfigPage() {
         }
         const start = Date.now()
         try {
-          const response = await fetch(`${ep.url}/_console/health`, {
+          const response = await apiFetch(`${ep.url}/_console/health`, {
             signal: AbortSignal.timeout(3000),
           })
           return {
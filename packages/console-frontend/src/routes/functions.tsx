@@ -19,7 +19,13 @@ import {
 } from 'lucide-react'
 import { useReducer } from 'react'
 import type { FunctionInfo } from '@/api'
-import { functionsQuery, invokeFunction as invokeFunctionApi, workersQuery } from '@/api'
+import {
+  canCall,
+  fetchFunctionExample,
+  functionsQuery,
+  invokeFunction as invokeFunctionApi,
+  workersQuery,
+} from '@/api'
 import { Badge, Button, Input } from '@/components/ui/card'
 import { JsonViewer } from '@/components/ui/json-viewer'
 
@@ -36,6 +42,8 @@ interface InvocationState {
   invoking: boolean
   invocationResult: InvocationResult | null
   requestBody: string
+  /** Function whose example may still replace the untouched template */
+  pendingExample: string | null
 }
 
 type InvocationAction =
@@ -43,12 +51,16 @@ type InvocationAction =
   | { type: 'SET_RESULT'; result: InvocationResult }
   | { type: 'CLEAR_RESULT' }
   | { type: 'SET_REQUEST_BODY'; body: string }
+  | { type: 'SET_TEMPLATE'; functionId: string; body: string }
+  | { type: 'APPLY_EXAMPLE'; functionId: string; body: string }
+  | { type: 'CANCEL_EXAMPLE' }
   | { type: 'INVOKE_DONE' }
 
 const invocationInitial: InvocationState = {
   invoking: false,
   invocationResult: null,
   requestBody: '{}',
+  pendingExample: null,
 }
 
 function invocationReducer(state: InvocationState, action: InvocationAction): InvocationState {
@@ -60,7 +72,14 @@ function invocationReducer(state: InvocationState, action: InvocationAction): In
     case 'CLEAR_RESULT':
       return { ...state, invocationResult: null }
     case 'SET_REQUEST_BODY':
-      return { ...state, requestBody: action.body }
+      return { ...state, requestBody: action.body, pendingExample: null }
+    case 'SET_TEMPLATE':
+      return { ...state, requestBody: action.body, pendingExample: action.functionId }
+    case 'APPLY_EXAMPLE':
+      if (state.pendingExample !== action.functionId) return state
+      return { ...state, requestBody: action.body, pendingExample: null }
+    case 'CANCEL_EXAMPLE':
+      return { ...state, pendingExample: null }
     case 'INVOKE_DONE':
       return { ...state, invoking: false }
     default:
@@ -244,11 +263,28 @@ function FunctionsPage() {
   const handleSelectFunction = (fn: FunctionInfo) => {
     if (selectedFunction?.function_id === fn.function_id) {
       dispatchUi({ type: 'SET_SELECTED_FUNCTION', payload: null })
+      dispatchInvocation({ type: 'CANCEL_EXAMPLE' })
     } else {
       dispatchUi({ type: 'SET_SELECTED_FUNCTION', payload: fn })
       dispatchInvocation({ type: 'CLEAR_RESULT' })
       const template = fn.request_format ? generateTemplate(fn.request_format) : '{\n  \n}'
-      dispatchInvocation({ type: 'SET_REQUEST_BODY', body: template })
+      dispatchInvocation({ type: 'SET_TEMPLATE', functionId: fn.function_id, body: template })
+      if (fn.request_format) {
+        // Applied only while the function is selected and its template untouched
+        fetchFunctionExample(fn.function_id)
+          .then(({ has_schema, example }) => {
+            if (has_schema) {
+              dispatchInvocation({
+                type: 'APPLY_EXAMPLE',
+                functionId: fn.function_id,
+                body: JSON.stringify(example, null, 2),
+              })
+            }
+          })
+          .catch(() => {
+            // Keep the locally generated template
+          })
+      }
     }
   }
 
@@ -470,7 +506,7 @@ function FunctionsPage() {
 
                   <Button
                     onClick={() => invokeFunction(selectedFunction)}
-                    disabled={invoking}
+                    disabled={invoking || !canCall('engine::console::invoke')}
                     className="w-full h-9"
                   >
                     {invoking ? (
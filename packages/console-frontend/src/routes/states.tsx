@@ -19,11 +19,24 @@ import {
 } from 'lucide-react'
 import { useEffect, useMemo, useReducer, useState } from 'react'
 import type { StateItem } from '@/api'
-import { deleteStateItem, setStateItem, stateGroupsQuery, stateItemsQuery } from '@/api'
+import {
+  canCall,
+  deleteStateItem,
+  setStateItem,
+  StateConflictError,
+  stateGroupsQuery,
+  stateItemsQuery,
+} from '@/api'
 import { Badge, Button, Input } from '@/components/ui/card'
 import { JsonViewer } from '@/components/ui/json-viewer'
 import { Pagination } from '@/components/ui/pagination'
 
+/** Ask whether to go ahead with a change to an item that changed since it was loaded */
+function confirmDespiteConflict(key: string, error: StateConflictError, action: string): boolean {
+  const current = JSON.stringify(error.current, null, 2)
+  return window.confirm(`"${key}" changed since it was loaded:\n\n${current}\n\n${action}`)
+}
+
 // --- addModal reducer ---
 interface AddModalState {
   show: boolean
@@ -177,7 +190,7 @@ function StatesPage() {
 
   useEffect(() => {
     if (groups.length > 0 && !selectedGroupId) {
-      const firstWithItems = groups.find((g) => g.count > 0)
+      const firstWithItems = groups.find((g) => (g.count ?? 0) > 0)
       if (firstWithItems) {
         setSelectedGroupId(firstWithItems.id)
       } else {
@@ -218,7 +231,16 @@ function StatesPage() {
     if (!selectedGroupId) return
 
     try {
-      await deleteStateItem(selectedGroupId, item.key)
+      try {
+        await deleteStateItem(selectedGroupId, item.key, item.value)
+      } catch (error) {
+        if (!(error instanceof StateConflictError)) throw error
+        if (!confirmDespiteConflict(item.key, error, 'Delete it anyway?')) {
+          refetchItems()
+          return
+        }
+        await deleteStateItem(selectedGroupId, item.key)
+      }
       refetchItems()
       if (selectedItem?.key === item.key) {
         setSelectedItem(null)
@@ -240,7 +262,16 @@ function StatesPage() {
         // Keep as string if not valid JSON
       }
 
-      await setStateItem(selectedGroupId, item.key, value)
+      try {
+        await setStateItem(selectedGroupId, item.key, value, item.value)
+      } catch (error) {
+        if (!(error instanceof StateConflictError)) throw error
+        if (!confirmDespiteConflict(item.key, error, 'Overwrite it with your edit?')) {
+          refetchItems()
+          return
+        }
+        await setStateItem(selectedGroupId, item.key, value)
+      }
       dispatchEdit({ type: 'CLOSE_EDIT' })
       refetchItems()
     } catch {
@@ -423,12 +454,12 @@ function StatesPage() {
                       className={`text-[10px] px-1.5 py-0.5 rounded ${
                         selectedGroupId === group.id
                           ? 'bg-blue-500/20 text-blue-300'
-                          : group.count > 0
+                          : (group.count ?? 0) > 0
                             ? 'bg-dark-gray text-muted'
                             : 'bg-dark-gray/50 text-muted/50'
                       }`}
                     >
-                      {group.count}
+                      {group.count ?? '–'}
                     </span>
                   </button>
                 ))}
@@ -624,6 +655,7 @@ function StatesPage() {
                                 </button>
                                 <button
                                   type="button"
+                                  hidden={!canCall('engine::console::state_item_delete')}
                                   onClick={(e) => {
                                     e.stopPropagation()
                                     handleDeleteItem(item)
@@ -788,7 +820,10 @@ function StatesPage() {
                   )}
                 </div>
 
-                <div className="pt-4 border-t border-border">
+                <div
+                  className="pt-4 border-t border-border"
+                  hidden={!canCall('engine::console::state_item_delete')}
+                >
                   <Button
                     variant="outline"
                     size="sm"
@@ -4,6 +4,9 @@ import react from '@vitejs/plugin-react'
 import { defineConfig } from 'vite'
 
 export default defineConfig(({ mode }) => ({
+  // The binary serves the console under --base-path and rewrites index.html,
+  // so built assets refer to each other relatively
+  base: mode === 'binary' ? './' : '/',
   plugins: [
     tanstackRouter({
       target: 'react',
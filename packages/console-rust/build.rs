@@ -1,8 +1,28 @@
+use sha2::{Digest, Sha256};
 use std::env;
 use std::fs;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
+/// Extensions of assets that are also embedded pre-compressed
+const COMPRESSIBLE_EXTENSIONS: &[&str] = &[
+    "js",
+    "mjs",
+    "css",
+    "svg",
+    "json",
+    "webmanifest",
+    "map",
+    "txt",
+    "xml",
+    "wasm",
+    "ico",
+];
+
+/// Assets smaller than this are only embedded as they are
+const MIN_COMPRESS_SIZE: usize = 1024;
+
 fn main() {
     // Only rebuild frontend if REBUILD_FRONTEND is set or in release mode
     let skip_frontend = env::var("SKIP_FRONTEND_BUILD").is_ok();
@@ -26,7 +46,7 @@ fn main() {
 
         // Run vite build via pnpm from workspace root
         let build_result = Command::new("pnpm")
-            .current_dir(&workspace_root)
+            .current_dir(workspace_root)
             .args(["run", "build:binary"])
             .status();
 
@@ -69,6 +89,21 @@ fn main() {
         println!("cargo:warning=Using existing frontend assets");
     }
 
+    // Write Brotli and gzip variants to OUT_DIR and list them for assets.rs to embed
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let mut variants = Vec::new();
+    if assets_dir.exists() {
+        compress_dir(
+            &assets_dir,
+            &assets_dir,
+            &out_dir.join("compressed"),
+            &mut variants,
+        )
+        .expect("Failed to compress frontend assets");
+    }
+    fs::write(out_dir.join("compressed.rs"), variant_table(&variants))
+        .expect("Failed to write the compressed asset table");
+
     // Tell cargo to rerun if source files change
     println!("cargo:rerun-if-changed=../console-frontend/src/");
     println!("cargo:rerun-if-changed=../console-frontend/index.html");
@@ -96,3 +131,123 @@ fn copy_dir_recursive(src: &Path, dst: &Path) -> std::io::Result<()> {
 
     Ok(())
 }
+
+/// A compressed file written to OUT_DIR for an asset.
+struct Variant {
+    /// Asset path as rust-embed names it, like `assets/index-B4x9_kQz.js`
+    path: String,
+    suffix: &'static str,
+    /// SHA-256 of the asset the variant was made from
+    source_hash: [u8; 32],
+    file: PathBuf,
+}
+
+fn compress_dir(
+    root: &Path,
+    dir: &Path,
+    out: &Path,
+    variants: &mut Vec<Variant>,
+) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            compress_dir(root, &path, out, variants)?;
+            continue;
+        }
+
+        let compressible = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| COMPRESSIBLE_EXTENSIONS.contains(&ext));
+        if !compressible {
+            continue;
+        }
+        let data = fs::read(&path)?;
+        if data.len() < MIN_COMPRESS_SIZE {
+            continue;
+        }
+
+        let relative = path.strip_prefix(root).unwrap();
+        let asset: Vec<_> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect();
+        let asset = asset.join("/");
+        let source_hash: [u8; 32] = Sha256::digest(&data).into();
+        let target = out.join(relative);
+        fs::create_dir_all(target.parent().unwrap())?;
+
+        let brotli = write_variant(&path, &target, "br", &data, |data| {
+            let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, 11, 22);
+            encoder.write_all(data)?;
+            Ok(encoder.into_inner())
+        })?;
+        let gzip = write_variant(&path, &target, "gz", &data, |data| {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+            encoder.write_all(data)?;
+            encoder.finish()
+        })?;
+        for (suffix, file) in [("br", brotli), ("gz", gzip)] {
+            if let Some(file) = file {
+                variants.push(Variant {
+                    path: asset.clone(),
+                    suffix,
+                    source_hash,
+                    file,
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Write `<target>.<suffix>` unless it is already newer than the asset, and
+/// return its path. Variants that would not be smaller are removed instead.
+fn write_variant(
+    path: &Path,
+    target: &Path,
+    suffix: &str,
+    data: &[u8],
+    compress: impl Fn(&[u8]) -> std::io::Result<Vec<u8>>,
+) -> std::io::Result<Option<PathBuf>> {
+    let mut variant_name = target.as_os_str().to_owned();
+    variant_name.push(".");
+    variant_name.push(suffix);
+    let variant = PathBuf::from(variant_name);
+
+    let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+    if let (Some(source), Some(compressed)) = (modified(path), modified(&variant)) {
+        if compressed >= source {
+            return Ok(Some(variant));
+        }
+    }
+
+    let compressed = compress(data)?;
+    if compressed.len() < data.len() {
+        fs::write(&variant, compressed)?;
+        Ok(Some(variant))
+    } else {
+        if variant.exists() {
+            fs::remove_file(&variant)?;
+        }
+        Ok(None)
+    }
+}
+
+/// Rust source of the `Variant` slice that assets.rs includes.
+fn variant_table(variants: &[Variant]) -> String {
+    let mut table = String::from("&[\n");
+    for variant in variants {
+        table.push_str(&format!(
+            "    Variant {{ path: {:?}, suffix: {:?}, source_hash: {:?}, data: include_bytes!({:?}) }},\n",
+            variant.path,
+            variant.suffix,
+            variant.source_hash,
+            variant.file.to_string_lossy(),
+        ));
+    }
+    table.push(']');
+    table
+}
@@ -0,0 +1,188 @@
+use axum::body::Body;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::Response;
+use rust_embed::Embed;
+use std::borrow::Cow;
+
+#[derive(Embed)]
+#[folder = "assets/"]
+pub struct Assets;
+
+/// Pre-compressed variants written by build.rs, most preferred first:
+/// `(Content-Encoding, file suffix)`
+const ENCODINGS: &[(&str, &str)] = &[("br", "br"), ("gzip", "gz")];
+
+/// A pre-compressed copy of an embedded asset. build.rs writes them to
+/// `OUT_DIR`, leaving the source `assets/` directory as it is.
+struct Variant {
+    path: &'static str,
+    suffix: &'static str,
+    /// SHA-256 of the asset it was made from, so a stale variant is never sent
+    source_hash: [u8; 32],
+    data: &'static [u8],
+}
+
+static VARIANTS: &[Variant] = include!(concat!(env!("OUT_DIR"), "/compressed.rs"));
+
+/// Directory Vite writes its hashed build output to (`build.assetsDir`)
+const HASHED_DIRECTORY: &str = "assets/";
+
+/// Cache policy of assets whose file name carries a content hash
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// Cache policy of everything else, revalidated on every use
+pub const NO_CACHE: &str = "no-cache";
+
+/// Files browsers and crawlers ask for at the root on their own
+const ROOT_FILES: &[&str] = &[
+    "favicon.ico",
+    "robots.txt",
+    "apple-touch-icon.png",
+    "apple-touch-icon-precomposed.png",
+    "site.webmanifest",
+    "manifest.json",
+    "browserconfig.xml",
+];
+
+/// Whether a path asks for a static file rather than a client-side route: it
+/// lies in the build's asset directory or is one of [`ROOT_FILES`]. Other
+/// paths, dotted or not, are client routes.
+///
+/// Missing files at such paths get a 404 instead of the SPA's index.html.
+pub fn is_asset_path(path: &str) -> bool {
+    path.starts_with(HASHED_DIRECTORY) || ROOT_FILES.contains(&path)
+}
+
+/// Whether a file is build output with a hash in its name, like
+/// `assets/index-B4x9_kQz.js`, so its content never changes under that name.
+/// Files copied from `public/` keep their names and are never immutable.
+fn is_hashed(path: &str) -> bool {
+    let Some(name) = path.strip_prefix(HASHED_DIRECTORY) else {
+        return false;
+    };
+    let stem = name.split('.').next().unwrap_or(name);
+    stem.rsplit_once('-').is_some_and(|(_, hash)| {
+        hash.len() >= 8 && hash.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// Whether `Accept-Encoding` allows a content coding.
+fn accepts(headers: &HeaderMap, coding: &str) -> bool {
+    let mut wildcard = false;
+    let values = headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|v| v.to_str().ok());
+    for item in values.flat_map(|v| v.split(',')) {
+        let mut params = item.split(';');
+        let name = params.next().unwrap_or_default().trim();
+        let allowed = params
+            .filter_map(|p| p.trim().strip_prefix("q="))
+            .all(|q| q.trim().parse::<f32>().map_or(true, |q| q > 0.0));
+        if name.eq_ignore_ascii_case(coding) {
+            return allowed;
+        }
+        if name == "*" {
+            wildcard = allowed;
+        }
+    }
+    wildcard
+}
+
+/// Whether `If-None-Match` lists the ETag, so the cached copy is still current.
+fn not_modified(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| {
+            v.split(',').any(|tag| {
+                let tag = tag.trim();
+                tag == "*" || tag.trim_start_matches("W/") == etag
+            })
+        })
+}
+
+/// Serve an embedded asset, or `None` when there is no such file.
+///
+/// The Brotli or gzip variant is sent when the client accepts it. The ETag is
+/// rust-embed's content hash, so `If-None-Match` revalidations get a 304, and
+/// hashed build output is cached as immutable.
+pub fn serve(path: &str, headers: &HeaderMap) -> Option<Response> {
+    let file = Assets::get(path)?;
+    let source_hash = file.metadata.sha256_hash();
+    let hash: String = source_hash.iter().map(|b| format!("{:02x}", b)).collect();
+
+    let variants: Vec<(&str, &str, Cow<'static, [u8]>)> = ENCODINGS
+        .iter()
+        .filter_map(|(coding, suffix)| {
+            VARIANTS
+                .iter()
+                .find(|v| v.path == path && v.suffix == *suffix && v.source_hash == source_hash)
+                .map(|v| (*coding, *suffix, Cow::Borrowed(v.data)))
+        })
+        .collect();
+    let vary = !variants.is_empty();
+    let (encoding, etag, data) = match variants
+        .into_iter()
+        .find(|(coding, _, _)| accepts(headers, coding))
+    {
+        Some((coding, suffix, variant)) => {
+            (Some(coding), format!("\"{}.{}\"", hash, suffix), variant)
+        }
+        None => (None, format!("\"{}\"", hash), file.data),
+    };
+
+    let cache_control = if is_hashed(path) { IMMUTABLE } else { NO_CACHE };
+    let mut response = Response::builder()
+        .header(header::ETAG, &etag)
+        .header(header::CACHE_CONTROL, cache_control);
+    if vary {
+        response = response.header(header::VARY, header::ACCEPT_ENCODING.as_str());
+    }
+    if not_modified(headers, &etag) {
+        return response
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .ok();
+    }
+
+    let mime = mime_guess::from_path(path).first_or_octet_stream();
+    if let Some(encoding) = encoding {
+        response = response.header(header::CONTENT_ENCODING, encoding);
+    }
+    response
+        .status(StatusCode::OK)
+        .header(
+            header::CONTENT_TYPE,
+            HeaderValue::from_str(mime.as_ref()).ok()?,
+        )
+        .body(Body::from(data))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dotted_client_routes_are_not_assets() {
+        assert!(is_asset_path("assets/index-B4x9_kQz.js"));
+        assert!(is_asset_path("assets/missing.css"));
+        assert!(is_asset_path("favicon.ico"));
+        assert!(is_asset_path("robots.txt"));
+        assert!(!is_asset_path("states/app.config"));
+        assert!(!is_asset_path("functions/orders.create"));
+        assert!(!is_asset_path("traces/report.json"));
+        assert!(!is_asset_path("logs"));
+    }
+
+    #[test]
+    fn only_hashed_build_output_is_immutable() {
+        assert!(is_hashed("assets/index-B4x9_kQz.js"));
+        assert!(is_hashed("assets/inter-latin-Cz0qK1fA.woff2"));
+        assert!(!is_hashed("assets/logo.svg"));
+        assert!(!is_hashed("assets/short-abc.js"));
+        assert!(!is_hashed("apple-touch-icon-precomposed.png"));
+        assert!(!is_hashed("favicon/site-webmanifest.json"));
+    }
+}
@@ -0,0 +1,193 @@
+mod roles;
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub use roles::{Principal, Roles, ROLE_ADMIN};
+
+/// Name of the cookie carrying the console session ID.
+pub const SESSION_COOKIE: &str = "iii_console_session";
+
+/// How long a session stays valid after login.
+pub const SESSION_TTL: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// Shared authentication state for the HTTP server and the bridge handlers.
+///
+/// When no token is configured, authentication is disabled and every
+/// request is treated as coming from an anonymous admin.
+#[derive(Clone, Default)]
+pub struct AuthState {
+    inner: Arc<AuthInner>,
+}
+
+#[derive(Default)]
+struct AuthInner {
+    /// Accepted tokens, with the principal each one signs in as.
+    tokens: Vec<(String, Principal)>,
+    roles: Roles,
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+struct Session {
+    principal: Principal,
+    expires: Instant,
+}
+
+impl AuthState {
+    /// Build the auth state from the shared admin token and the roles file users.
+    pub fn new(token: Option<String>, roles: Roles) -> Self {
+        let mut tokens: Vec<(String, Principal)> = token
+            .filter(|t| !t.is_empty())
+            .map(|t| {
+                (
+                    t,
+                    Principal {
+                        name: "token".to_string(),
+                        role: ROLE_ADMIN.to_string(),
+                    },
+                )
+            })
+            .into_iter()
+            .collect();
+
+        tokens.extend(roles.users().iter().map(|user| {
+            (
+                user.token.clone(),
+                Principal {
+                    name: user.name.clone(),
+                    role: user.role.clone(),
+                },
+            )
+        }));
+
+        Self {
+            inner: Arc::new(AuthInner {
+                tokens,
+                roles,
+                sessions: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        !self.inner.tokens.is_empty()
+    }
+
+    pub fn roles(&self) -> &Roles {
+        &self.inner.roles
+    }
+
+    /// Whether the principal's role may call the given console function.
+    pub fn allows(&self, principal: &Principal, function_id: &str) -> bool {
+        self.inner.roles.allows(&principal.role, function_id)
+    }
+
+    /// Exchange a token for a new session ID.
+    pub fn login(&self, token: &str) -> Option<String> {
+        let principal = self.principal_for_token(token)?;
+
+        let session_id = uuid::Uuid::new_v4().simple().to_string();
+        let mut sessions = self.inner.sessions.lock().unwrap();
+        let now = Instant::now();
+        sessions.retain(|_, session| session.expires > now);
+        sessions.insert(
+            session_id.clone(),
+            Session {
+                principal,
+                expires: now + SESSION_TTL,
+            },
+        );
+        Some(session_id)
+    }
+
+    pub fn logout(&self, session_id: &str) {
+        self.inner.sessions.lock().unwrap().remove(session_id);
+    }
+
+    /// Resolve a bearer credential, which may be either a token or a session ID.
+    pub fn authenticate_credential(&self, credential: &str) -> Option<Principal> {
+        if !self.enabled() {
+            return Some(anonymous());
+        }
+        if let Some(principal) = self.principal_for_token(credential) {
+            return Some(principal);
+        }
+
+        let mut sessions = self.inner.sessions.lock().unwrap();
+        match sessions.get(credential) {
+            Some(session) if session.expires > Instant::now() => Some(session.principal.clone()),
+            Some(_) => {
+                sessions.remove(credential);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Resolve the principal behind a set of request headers.
+    ///
+    /// Accepts `Authorization: Bearer <token|session>` or the session cookie.
+    pub fn authenticate_headers<'a>(
+        &self,
+        mut header: impl FnMut(&str) -> Option<&'a str>,
+    ) -> Option<Principal> {
+        if !self.enabled() {
+            return Some(anonymous());
+        }
+
+        if let Some(bearer) = header("authorization").and_then(|v| v.strip_prefix("Bearer ")) {
+            if let Some(principal) = self.authenticate_credential(bearer.trim()) {
+                return Some(principal);
+            }
+        }
+
+        header("cookie")
+            .and_then(session_from_cookie)
+            .and_then(|session| self.authenticate_credential(session))
+    }
+
+    /// Resolve the principal behind a bridge handler input (an HTTP trigger request).
+    pub fn authenticate_input(&self, input: &Value) -> Option<Principal> {
+        let headers = input.get("headers").and_then(|h| h.as_object());
+        self.authenticate_headers(|name| {
+            headers.and_then(|h| {
+                h.iter()
+                    .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                    .and_then(|(_, v)| v.as_str())
+            })
+        })
+    }
+
+    fn principal_for_token(&self, token: &str) -> Option<Principal> {
+        self.inner
+            .tokens
+            .iter()
+            .find(|(expected, _)| constant_time_eq(expected.as_bytes(), token.as_bytes()))
+            .map(|(_, principal)| principal.clone())
+    }
+}
+
+/// The principal used for every request when authentication is disabled.
+fn anonymous() -> Principal {
+    Principal {
+        name: "anonymous".to_string(),
+        role: ROLE_ADMIN.to_string(),
+    }
+}
+
+/// Extract the session ID from a `Cookie` header value.
+pub fn session_from_cookie(cookie: &str) -> Option<&str> {
+    cookie.split(';').find_map(|pair| {
+        let (name, value) = pair.trim().split_once('=')?;
+        (name == SESSION_COOKIE).then_some(value)
+    })
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
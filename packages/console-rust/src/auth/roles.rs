@@ -0,0 +1,155 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+const ROLE_VIEWER: &str = "viewer";
+const ROLE_OPERATOR: &str = "operator";
+pub const ROLE_ADMIN: &str = "admin";
+
+/// Console functions that only read engine data.
+const VIEWER_FUNCTIONS: &[&str] = &[
+    "engine::console::status",
+    "engine::console::health",
+    "engine::console::functions",
+    "engine::console::triggers",
+    "engine::console::trigger_types",
+    "engine::console::workers",
+    "engine::console::alerts_list",
+    "engine::console::sampling_rules",
+    "engine::console::otel_logs_list",
+    "engine::console::otel_traces_list",
+    "engine::console::otel_traces_tree",
+    "engine::console::metrics_detailed",
+    "engine::console::rollups_list",
+    "engine::console::state_groups_list",
+    "engine::console::state_group_items",
+    "engine::console::state_export",
+    "engine::console::streams_list",
+    "engine::console::flow_config_get",
+    "engine::console::invoke_presets_list",
+    "engine::console::invoke_presets_export",
+    "engine::console::function_example",
+];
+
+/// Console functions that change state or run user code, on top of the viewer set.
+const OPERATOR_FUNCTIONS: &[&str] = &[
+    "engine::console::state_item_set",
+    "engine::console::state_item_delete",
+    "engine::console::state_items_set",
+    "engine::console::state_items_delete",
+    "engine::console::state_items_delete_matching",
+    "engine::console::state_group_clear",
+    "engine::console::state_import",
+    "engine::console::flow_config_save",
+    "engine::console::invoke",
+    "engine::console::invocation_status",
+    "engine::console::invocation_history_list",
+    "engine::console::invocation_cancel",
+    "engine::console::invocation_history_delete",
+    "engine::console::invocation_replay",
+    "engine::console::invoke_preset_save",
+    "engine::console::invoke_preset_delete",
+    "engine::console::invoke_presets_import",
+    "engine::console::cron_trigger",
+];
+
+/// A principal authenticated by the console.
+#[derive(Debug, Clone)]
+pub struct Principal {
+    pub name: String,
+    pub role: String,
+}
+
+/// A token holder declared in the roles file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct UserConfig {
+    pub name: String,
+    pub token: String,
+    pub role: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RolesFile {
+    #[serde(default)]
+    roles: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    users: Vec<UserConfig>,
+}
+
+/// Named roles, each mapped to an allowlist of `engine::console::*` function IDs.
+///
+/// Allowlist entries match a function ID exactly, or as a prefix when they
+/// end with `*`.
+#[derive(Debug, Clone)]
+pub struct Roles {
+    roles: HashMap<String, Vec<String>>,
+    users: Vec<UserConfig>,
+}
+
+impl Default for Roles {
+    fn default() -> Self {
+        let viewer: Vec<String> = VIEWER_FUNCTIONS.iter().map(|f| f.to_string()).collect();
+        let mut operator = viewer.clone();
+        operator.extend(OPERATOR_FUNCTIONS.iter().map(|f| f.to_string()));
+
+        let roles = HashMap::from([
+            (ROLE_VIEWER.to_string(), viewer),
+            (ROLE_OPERATOR.to_string(), operator),
+            (ROLE_ADMIN.to_string(), vec!["*".to_string()]),
+        ]);
+
+        Self {
+            roles,
+            users: Vec::new(),
+        }
+    }
+}
+
+impl Roles {
+    /// Load roles and users from a TOML file, on top of the built-in roles.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read roles file {}", path.display()))?;
+        let file: RolesFile = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse roles file {}", path.display()))?;
+
+        let mut roles = Self::default();
+        roles.roles.extend(file.roles);
+
+        for user in &file.users {
+            if user.token.is_empty() {
+                bail!("User '{}' has an empty token", user.name);
+            }
+            if !roles.roles.contains_key(&user.role) {
+                bail!("User '{}' has unknown role '{}'", user.name, user.role);
+            }
+        }
+        roles.users = file.users;
+
+        Ok(roles)
+    }
+
+    pub fn users(&self) -> &[UserConfig] {
+        &self.users
+    }
+
+    pub fn allows(&self, role: &str, function_id: &str) -> bool {
+        self.roles
+            .get(role)
+            .map(|patterns| patterns.iter().any(|p| pattern_matches(p, function_id)))
+            .unwrap_or(false)
+    }
+
+    /// The allowlist patterns of a role, as configured.
+    pub fn allowlist(&self, role: &str) -> &[String] {
+        self.roles.get(role).map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
+fn pattern_matches(pattern: &str, function_id: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => function_id.starts_with(prefix),
+        None => pattern == function_id,
+    }
+}
@@ -0,0 +1,365 @@
+use iii_sdk::{IIIError, III};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+
+use crate::auth::Principal;
+use crate::bridge::state;
+use crate::util::now_ms;
+
+/// State group ID used to persist audit records when no audit file is configured.
+pub const AUDIT_GROUP: &str = "__console.audit";
+
+/// Records kept in the audit group; the oldest are deleted as new ones arrive.
+const MAX_STATE_RECORDS: usize = 10_000;
+
+/// Records written between two trims of the audit group.
+const TRIM_INTERVAL: usize = 100;
+
+/// Console function whose callers may list the audit log.
+pub const AUDIT_READ_FUNCTION: &str = "engine::console::audit_list";
+
+/// Console functions that change engine data or run user code.
+const AUDITED_FUNCTIONS: &[&str] = &[
+    "engine::console::state_item_set",
+    "engine::console::state_item_delete",
+    "engine::console::state_items_set",
+    "engine::console::state_items_delete",
+    "engine::console::state_items_delete_matching",
+    "engine::console::state_group_clear",
+    "engine::console::state_import",
+    "engine::console::flow_config_save",
+    "engine::console::otel_logs_clear",
+    "engine::console::otel_traces_clear",
+    "engine::console::cron_trigger",
+    "engine::console::invoke",
+    "engine::console::invocation_cancel",
+    "engine::console::invocation_history_delete",
+    "engine::console::invocation_replay",
+    "engine::console::invoke_preset_save",
+    "engine::console::invoke_preset_delete",
+    "engine::console::invoke_presets_import",
+];
+
+/// Request fields that identify what a mutating call acts on.
+const TARGET_FIELDS: &[&str] = &[
+    "group",
+    "key",
+    "flow_id",
+    "function_id",
+    "trigger_id",
+    "job_id",
+    "invocation_id",
+    "preset",
+];
+
+pub fn is_audited(function_id: &str) -> bool {
+    AUDITED_FUNCTIONS.contains(&function_id)
+}
+
+/// A structured record of one mutating console call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub id: String,
+    /// Unix timestamp in milliseconds
+    pub timestamp: u64,
+    pub action: String,
+    /// Name of the engine profile the call acted on
+    #[serde(default)]
+    pub engine: String,
+    pub target: Option<String>,
+    /// SHA-256 of the request input, excluding headers
+    pub input_hash: String,
+    pub user: String,
+    pub role: String,
+    /// `success`, `error` or `forbidden`
+    pub outcome: String,
+    pub status_code: u64,
+    pub error: Option<String>,
+    pub latency_ms: u64,
+}
+
+/// Filters and pagination accepted by `engine::console::audit_list`.
+#[derive(Debug, Default)]
+pub struct AuditFilter {
+    pub action: Option<String>,
+    pub engine: Option<String>,
+    pub user: Option<String>,
+    pub outcome: Option<String>,
+    pub target: Option<String>,
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl AuditFilter {
+    fn matches(&self, record: &AuditRecord) -> bool {
+        self.action.as_ref().is_none_or(|a| &record.action == a)
+            && self.engine.as_ref().is_none_or(|e| &record.engine == e)
+            && self.user.as_ref().is_none_or(|u| &record.user == u)
+            && self.outcome.as_ref().is_none_or(|o| &record.outcome == o)
+            && self.target.as_ref().is_none_or(|t| {
+                record
+                    .target
+                    .as_ref()
+                    .is_some_and(|target| target.contains(t.as_str()))
+            })
+            && self.since.is_none_or(|s| record.timestamp >= s)
+            && self.until.is_none_or(|u| record.timestamp <= u)
+    }
+}
+
+enum AuditSink {
+    /// Records stored in the console-owned state group
+    State { bridge: III, writes: AtomicUsize },
+    /// Records appended to a JSONL file
+    File {
+        path: PathBuf,
+        lock: tokio::sync::Mutex<()>,
+    },
+}
+
+/// Audit trail of mutating console calls.
+#[derive(Clone)]
+pub struct AuditLog {
+    sink: Arc<AuditSink>,
+}
+
+impl AuditLog {
+    /// Store records in the engine state, under the console audit group.
+    pub fn state(bridge: &III) -> Self {
+        Self {
+            sink: Arc::new(AuditSink::State {
+                bridge: bridge.clone(),
+                writes: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// Append records to a JSONL file.
+    pub fn file(path: PathBuf) -> Self {
+        Self {
+            sink: Arc::new(AuditSink::File {
+                path,
+                lock: tokio::sync::Mutex::new(()),
+            }),
+        }
+    }
+
+    /// Capture the request side of a mutating call before its handler runs.
+    pub fn begin(
+        &self,
+        action: &str,
+        engine: &str,
+        principal: &Principal,
+        input: &Value,
+    ) -> PendingAudit {
+        PendingAudit {
+            log: self.clone(),
+            action: action.to_string(),
+            engine: engine.to_string(),
+            target: extract_target(input),
+            input_hash: hash_input(input),
+            user: principal.name.clone(),
+            role: principal.role.clone(),
+            started: Instant::now(),
+        }
+    }
+
+    async fn write(&self, record: &AuditRecord) -> Result<(), IIIError> {
+        match self.sink.as_ref() {
+            AuditSink::State { bridge, writes } => {
+                // Keys sort chronologically so the group reads back in order
+                let key = format!("{:013}-{}", record.timestamp, record.id);
+                let state_input = json!({
+                    "scope": AUDIT_GROUP,
+                    "key": key,
+                    "value": record
+                });
+                bridge
+                    .call_with_timeout("state::set", state_input, Duration::from_secs(5))
+                    .await?;
+
+                // The first write trims what earlier runs left behind
+                if writes.fetch_add(1, Ordering::Relaxed) % TRIM_INTERVAL == 0 {
+                    let timeout = Duration::from_secs(30);
+                    match state::trim(bridge, AUDIT_GROUP, "timestamp", MAX_STATE_RECORDS, timeout)
+                        .await
+                    {
+                        Ok(0) => {}
+                        Ok(deleted) => tracing::debug!(deleted, "Trimmed audit records"),
+                        Err(e) => tracing::warn!("Failed to trim audit records: {}", e),
+                    }
+                }
+                Ok(())
+            }
+            AuditSink::File { path, lock } => {
+                let mut line = serde_json::to_string(record)?;
+                line.push('\n');
+
+                let _guard = lock.lock().await;
+                let mut file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await
+                    .map_err(|e| IIIError::Handler(format!("Failed to open audit log: {}", e)))?;
+                file.write_all(line.as_bytes())
+                    .await
+                    .map_err(|e| IIIError::Handler(format!("Failed to write audit log: {}", e)))
+            }
+        }
+    }
+
+    async fn read_all(&self) -> Result<Vec<AuditRecord>, IIIError> {
+        match self.sink.as_ref() {
+            AuditSink::State { bridge, .. } => {
+                let data = bridge
+                    .call_with_timeout(
+                        "state::list",
+                        json!({ "scope": AUDIT_GROUP }),
+                        Duration::from_secs(10),
+                    )
+                    .await?;
+                Ok(data
+                    .as_array()
+                    .map(|items| {
+                        items
+                            .iter()
+                            .filter_map(|item| {
+                                let record = item.get("value").unwrap_or(item);
+                                serde_json::from_value(record.clone()).ok()
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default())
+            }
+            AuditSink::File { path, lock } => {
+                let _guard = lock.lock().await;
+                let content = match tokio::fs::read_to_string(path).await {
+                    Ok(content) => content,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+                    Err(e) => {
+                        return Err(IIIError::Handler(format!(
+                            "Failed to read audit log: {}",
+                            e
+                        )))
+                    }
+                };
+                Ok(content
+                    .lines()
+                    .filter_map(|line| serde_json::from_str(line).ok())
+                    .collect())
+            }
+        }
+    }
+
+    /// List records, newest first, returning the page and the total number of matches.
+    pub async fn list(&self, filter: &AuditFilter) -> Result<(Vec<AuditRecord>, usize), IIIError> {
+        let mut records: Vec<AuditRecord> = self
+            .read_all()
+            .await?
+            .into_iter()
+            .filter(|r| filter.matches(r))
+            .collect();
+        records.sort_by_key(|r| std::cmp::Reverse(r.timestamp));
+
+        let total = records.len();
+        let page = records
+            .into_iter()
+            .skip(filter.offset)
+            .take(filter.limit)
+            .collect();
+        Ok((page, total))
+    }
+}
+
+/// The request side of an audit record, completed once the handler responds.
+pub struct PendingAudit {
+    log: AuditLog,
+    action: String,
+    engine: String,
+    target: Option<String>,
+    input_hash: String,
+    user: String,
+    role: String,
+    started: Instant,
+}
+
+impl PendingAudit {
+    /// Complete the record from the handler response and write it in the background.
+    pub fn finish(self, response: &Value) {
+        let status_code = response
+            .get("status_code")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(200);
+        let outcome = match status_code {
+            200..=299 => "success",
+            403 => "forbidden",
+            _ => "error",
+        };
+        let error = response
+            .get("body")
+            .and_then(|b| b.get("error"))
+            .and_then(|e| e.get("message").unwrap_or(e).as_str())
+            .map(String::from);
+
+        let record = AuditRecord {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            timestamp: now_ms(),
+            action: self.action,
+            engine: self.engine,
+            target: self.target,
+            input_hash: self.input_hash,
+            user: self.user,
+            role: self.role,
+            outcome: outcome.to_string(),
+            status_code,
+            error,
+            latency_ms: self.started.elapsed().as_millis() as u64,
+        };
+
+        let log = self.log;
+        tokio::spawn(async move {
+            if let Err(e) = log.write(&record).await {
+                tracing::warn!(action = %record.action, "Failed to write audit record: {}", e);
+            }
+        });
+    }
+}
+
+/// Hash the request input without its headers, which carry credentials.
+fn hash_input(input: &Value) -> String {
+    let mut input = input.clone();
+    if let Some(obj) = input.as_object_mut() {
+        obj.remove("headers");
+    }
+    let bytes = serde_json::to_vec(&input).unwrap_or_default();
+    Sha256::digest(&bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Describe the call target from its path parameters and body, e.g. `group=users key=42`.
+fn extract_target(input: &Value) -> Option<String> {
+    let sources = [input.get("path_params"), input.get("body"), Some(input)];
+    let parts: Vec<String> = TARGET_FIELDS
+        .iter()
+        .filter_map(|field| {
+            sources
+                .iter()
+                .flatten()
+                .find_map(|source| source.get(field).and_then(|v| v.as_str()))
+                .map(|value| format!("{}={}", field, value))
+        })
+        .collect();
+
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
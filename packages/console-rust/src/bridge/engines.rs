@@ -0,0 +1,222 @@
+use iii_sdk::III;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Name of the engine profile built from the `--engine-*` options.
+pub const DEFAULT_ENGINE: &str = "default";
+
+/// Request header selecting which engine a console call should act on.
+pub const ENGINE_HEADER: &str = "x-iii-engine";
+
+/// How often each engine connection is probed for its status.
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Connection settings for one iii engine.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EngineProfile {
+    pub name: String,
+    #[serde(default = "default_host")]
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default = "default_ws_port")]
+    pub ws_port: u16,
+    #[serde(default = "default_bridge_port")]
+    pub bridge_port: u16,
+}
+
+fn default_host() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_port() -> u16 {
+    3111
+}
+
+fn default_ws_port() -> u16 {
+    3112
+}
+
+fn default_bridge_port() -> u16 {
+    49134
+}
+
+impl EngineProfile {
+    pub fn bridge_url(&self) -> String {
+        format!("ws://{}:{}", self.host, self.bridge_port)
+    }
+}
+
+/// Last known state of an engine bridge connection.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EngineStatus {
+    pub connected: bool,
+    pub last_error: Option<String>,
+    /// Unix timestamp in milliseconds of the last probe
+    pub last_checked: Option<u64>,
+}
+
+struct Engine {
+    profile: EngineProfile,
+    bridge: III,
+    status: RwLock<EngineStatus>,
+}
+
+/// The engines this console is connected to, one bridge per profile.
+///
+/// The first engine is the default one.
+#[derive(Clone)]
+pub struct Engines {
+    engines: Arc<Vec<Engine>>,
+}
+
+impl Engines {
+    pub fn new(engines: Vec<(EngineProfile, III)>) -> Self {
+        Self {
+            engines: Arc::new(
+                engines
+                    .into_iter()
+                    .map(|(profile, bridge)| Engine {
+                        profile,
+                        bridge,
+                        status: RwLock::new(EngineStatus::default()),
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn bridges(&self) -> impl Iterator<Item = (&EngineProfile, &III)> {
+        self.engines.iter().map(|e| (&e.profile, &e.bridge))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&III> {
+        self.engines
+            .iter()
+            .find(|e| e.profile.name == name)
+            .map(|e| &e.bridge)
+    }
+
+    /// The engine a request asked for through the `x-iii-engine` header or the
+    /// `engine` query parameter. `Ok(None)` means the request did not choose one.
+    pub fn select(&self, input: &Value) -> Result<Option<(String, III)>, String> {
+        let header = input
+            .get("headers")
+            .and_then(|h| h.as_object())
+            .and_then(|h| {
+                h.iter()
+                    .find(|(k, _)| k.eq_ignore_ascii_case(ENGINE_HEADER))
+                    .and_then(|(_, v)| v.as_str())
+            });
+        let query = input
+            .get("query_params")
+            .and_then(|q| q.get("engine"))
+            .and_then(|v| v.as_str());
+
+        match header.or(query).filter(|name| !name.is_empty()) {
+            Some(name) => self
+                .get(name)
+                .map(|bridge| Some((name.to_string(), bridge.clone())))
+                .ok_or_else(|| format!("Unknown engine '{}'", name)),
+            None => Ok(None),
+        }
+    }
+
+    /// The engine named `name`, or the default engine when no name is given.
+    pub fn resolve(&self, name: Option<&str>) -> Result<(String, III), String> {
+        match name.filter(|name| !name.is_empty()) {
+            Some(name) => self
+                .get(name)
+                .map(|bridge| (name.to_string(), bridge.clone()))
+                .ok_or_else(|| format!("Unknown engine '{}'", name)),
+            None => self
+                .engines
+                .first()
+                .map(|e| (e.profile.name.clone(), e.bridge.clone()))
+                .ok_or_else(|| "No engine configured".to_string()),
+        }
+    }
+
+    /// The connection settings of the engine named `name`, or of the default
+    /// engine when no name is given.
+    pub fn profile(&self, name: Option<&str>) -> Result<&EngineProfile, String> {
+        match name.filter(|name| !name.is_empty()) {
+            Some(name) => self
+                .engines
+                .iter()
+                .find(|e| e.profile.name == name)
+                .map(|e| &e.profile)
+                .ok_or_else(|| format!("Unknown engine '{}'", name)),
+            None => self
+                .engines
+                .first()
+                .map(|e| &e.profile)
+                .ok_or_else(|| "No engine configured".to_string()),
+        }
+    }
+
+    /// Describe every engine and its connection status, for `/api/config`.
+    pub fn describe(&self) -> Vec<Value> {
+        self.engines
+            .iter()
+            .map(|e| {
+                let status = e.status.read().unwrap().clone();
+                json!({
+                    "name": e.profile.name,
+                    "engineHost": e.profile.host,
+                    "enginePort": e.profile.port,
+                    "wsPort": e.profile.ws_port,
+                    "bridgePort": e.profile.bridge_port,
+                    "status": status
+                })
+            })
+            .collect()
+    }
+
+    /// Probe each engine periodically and record whether its bridge answers.
+    pub fn spawn_health_checks(&self) {
+        for index in 0..self.engines.len() {
+            let engines = self.engines.clone();
+            tokio::spawn(async move {
+                let engine = &engines[index];
+                let mut interval = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    let result = engine
+                        .bridge
+                        .call_with_timeout(
+                            "engine::health::check",
+                            json!({}),
+                            Duration::from_secs(2),
+                        )
+                        .await;
+
+                    let checked = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map(|d| d.as_millis() as u64)
+                        .ok();
+                    let mut status = engine.status.write().unwrap();
+                    if status.connected != result.is_ok() {
+                        match &result {
+                            Ok(_) => {
+                                tracing::info!(engine = %engine.profile.name, "Engine connected")
+                            }
+                            Err(e) => {
+                                tracing::warn!(engine = %engine.profile.name, "Engine unreachable: {}", e)
+                            }
+                        }
+                    }
+                    *status = EngineStatus {
+                        connected: result.is_ok(),
+                        last_error: result.err().map(|e| e.to_string()),
+                        last_checked: checked,
+                    };
+                }
+            });
+        }
+    }
+}
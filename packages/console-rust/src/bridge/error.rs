@@ -1,26 +1,127 @@
 use iii_sdk::IIIError;
+use serde::Serialize;
 use serde_json::{json, Value};
 
-/// Maps a IIIError to an HTTP response format
-pub fn error_response(error: IIIError) -> Value {
-    let (status_code, message) = match error {
-        IIIError::NotConnected => (503, "Bridge is not connected".to_string()),
-        IIIError::Timeout => (504, "Invocation timed out".to_string()),
-        IIIError::Remote { code, message } => {
-            (502, format!("Remote error ({}): {}", code, message))
+use crate::bridge::params::ParamError;
+
+/// A console error as returned to clients.
+///
+/// The response body is `{ "error": { code, message, details, retryable, trace_id } }`,
+/// where `code` is a stable, machine-readable identifier such as
+/// `function_not_found`, `missing_parameter` or `timeout`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConsoleError {
+    #[serde(skip)]
+    pub status_code: u16,
+    pub code: String,
+    pub message: String,
+    pub details: Option<Value>,
+    /// Whether the same request may succeed if sent again later
+    pub retryable: bool,
+    pub trace_id: Option<String>,
+}
+
+impl ConsoleError {
+    pub fn new(status_code: u16, code: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            status_code,
+            code: code.into(),
+            message: message.into(),
+            details: None,
+            retryable: false,
+            trace_id: None,
         }
-        IIIError::Handler(msg) => (500, format!("Handler error: {}", msg)),
-        IIIError::Serde(msg) => (500, format!("Serialization error: {}", msg)),
-        IIIError::WebSocket(msg) => (503, format!("WebSocket error: {}", msg)),
-    };
+    }
 
-    json!({
-        "status_code": status_code,
-        "headers": [],
-        "body": {
-            "error": message
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    pub fn retryable(mut self) -> Self {
+        self.retryable = true;
+        self
+    }
+
+    /// A request parameter is present but unusable.
+    pub fn invalid_parameter(name: &str, reason: impl Into<String>) -> Self {
+        Self::invalid_parameters(vec![ParamError::invalid(name, reason)])
+    }
+
+    /// One or more request parameters are absent or unusable. The code is
+    /// `missing_parameter` when every one of them is missing.
+    pub fn invalid_parameters(errors: Vec<ParamError>) -> Self {
+        let code = if errors.iter().all(|e| e.missing) {
+            "missing_parameter"
+        } else {
+            "invalid_parameter"
+        };
+        let message = match errors.as_slice() {
+            [e] if e.missing => format!("Missing {} parameter", e.name),
+            [e] => format!("Invalid {}: {}", e.name, e.reason),
+            _ => format!(
+                "Invalid parameters: {}",
+                errors
+                    .iter()
+                    .map(|e| format!("{} {}", e.name, e.reason))
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            ),
+        };
+        Self::new(400, code, message).with_details(json!({ "parameters": errors }))
+    }
+
+    pub fn not_found(code: &str, message: impl Into<String>) -> Self {
+        Self::new(404, code, message)
+    }
+
+    /// Build the handler response, tagged with the active trace.
+    pub fn into_response(mut self) -> Value {
+        if self.trace_id.is_none() {
+            self.trace_id = iii_sdk::current_trace_id();
         }
-    })
+        json!({
+            "status_code": self.status_code,
+            "headers": [],
+            "body": { "error": self }
+        })
+    }
+}
+
+impl From<IIIError> for ConsoleError {
+    fn from(error: IIIError) -> Self {
+        match error {
+            IIIError::NotConnected => {
+                Self::new(503, "not_connected", "Bridge is not connected").retryable()
+            }
+            IIIError::Timeout => Self::new(504, "timeout", "Invocation timed out").retryable(),
+            IIIError::Remote { code, message } => {
+                // Keep the engine's code so clients can tell a missing function
+                // apart from one whose handler failed.
+                let status_code = if code.ends_with("_not_found") {
+                    404
+                } else {
+                    502
+                };
+                Self::new(status_code, code.clone(), message)
+                    .with_details(json!({ "source": "engine", "remote_code": code }))
+            }
+            IIIError::Handler(msg) => Self::new(500, "internal_error", msg),
+            IIIError::Serde(msg) => Self::new(
+                500,
+                "serialization_error",
+                format!("Serialization error: {}", msg),
+            ),
+            IIIError::WebSocket(msg) => {
+                Self::new(503, "connection_error", format!("WebSocket error: {}", msg)).retryable()
+            }
+        }
+    }
+}
+
+/// Maps a IIIError to an HTTP response format
+pub fn error_response(error: IIIError) -> Value {
+    ConsoleError::from(error).into_response()
 }
 
 /// Wraps a successful response in the standard HTTP response format
@@ -31,3 +132,42 @@ pub fn success_response(body: Value) -> Value {
         "body": body
     })
 }
+
+/// Response for work that was started and continues in the background
+pub fn accepted_response(body: Value) -> Value {
+    json!({
+        "status_code": 202,
+        "headers": [],
+        "body": body
+    })
+}
+
+/// Response returned when a request carries no valid console credentials
+pub fn unauthorized_response() -> Value {
+    ConsoleError::new(401, "unauthorized", "Authentication required").into_response()
+}
+
+/// Response for a request the console rejects before calling the engine, with
+/// a generic code derived from the status
+pub fn client_error_response(status_code: u16, message: impl Into<String>) -> Value {
+    let code = match status_code {
+        400 => "invalid_request",
+        404 => "not_found",
+        409 => "conflict",
+        422 => "validation_failed",
+        429 => "too_many_requests",
+        _ => "client_error",
+    };
+    ConsoleError::new(status_code, code, message).into_response()
+}
+
+/// Response returned when the caller's role may not call a console function
+pub fn forbidden_response(function_id: &str) -> Value {
+    ConsoleError::new(
+        403,
+        "forbidden",
+        format!("Not allowed to call {}", function_id),
+    )
+    .with_details(json!({ "function_id": function_id }))
+    .into_response()
+}
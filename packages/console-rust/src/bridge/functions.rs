@@ -1,41 +1,53 @@
 use iii_sdk::III;
 use serde_json::{json, Value};
 use std::collections::HashSet;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::future::Future;
+use std::sync::Arc;
 use std::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::bridge::error::{error_response, success_response};
+use crate::auth::Principal;
+use crate::bridge::audit::{self, AuditFilter, AuditLog};
+use crate::bridge::error::{
+    accepted_response, client_error_response, error_response, forbidden_response, success_response,
+    unauthorized_response, ConsoleError,
+};
+use crate::bridge::history::{self, HistoryFilter, InvocationRecord};
+use crate::bridge::jobs::{Cancel, Jobs};
+use crate::bridge::params::{Param, Params, Source};
+use crate::bridge::presets::{self, Preset};
+use crate::bridge::schema;
+use crate::bridge::state::{self, ConflictMode, ImportError, KeyResult};
+use crate::bridge::Services;
+use crate::util::now_ms;
 
 /// State group ID used to persist console flow configurations.
-const FLOW_CONFIG_GROUP: &str = "__console.flowConfigs";
+pub const FLOW_CONFIG_GROUP: &str = "__console.flowConfigs";
 
-fn validate_flow_id(id: &str) -> Result<String, Value> {
-    if id.is_empty()
-        || !id
-            .chars()
-            .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '.')
+fn validate_flow_id(id: &str) -> Result<(), String> {
+    if !id
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '.')
     {
-        return Err(error_response(iii_sdk::IIIError::Handler(format!(
-            "Invalid flow_id: {}",
-            id
-        ))));
+        return Err("only letters, digits, '-', '_' and '.' are allowed".to_string());
     }
-    Ok(id.to_string())
+    Ok(())
 }
 
-/// Parse a boolean parameter from query_params, handling string "true"/"false" coercion.
-fn parse_bool_param(input: &Value, key: &str) -> bool {
-    let params = input.get("query_params").unwrap_or(input);
-    match params.get(key) {
-        Some(Value::Bool(b)) => *b,
-        Some(Value::String(s)) => s.eq_ignore_ascii_case("true"),
-        _ => false,
-    }
+/// Read a handler's declared parameters, or the 400 response listing every
+/// missing and invalid one.
+fn extract(input: &Value, params: &[Param]) -> Result<Params, Value> {
+    Params::extract(input, params).map_err(ConsoleError::into_response)
 }
 
-async fn handle_health(bridge: &III) -> Value {
+/// JSON values are only read from the body or a direct call, never the query string.
+const JSON_SOURCES: &[Source] = &[Source::Body, Source::Top];
+
+const INCLUDE_INTERNAL_PARAMS: &[Param] = &[Param::bool("include_internal")];
+
+async fn handle_health(bridge: &III, timeout: Duration) -> Value {
     match bridge
-        .call_with_timeout("engine::health::check", json!({}), Duration::from_secs(5))
+        .call_with_timeout("engine::health::check", json!({}), timeout)
         .await
     {
         Ok(health_data) => success_response(health_data),
@@ -43,9 +55,9 @@ async fn handle_health(bridge: &III) -> Value {
     }
 }
 
-async fn handle_workers(bridge: &III) -> Value {
+async fn handle_workers(bridge: &III, timeout: Duration) -> Value {
     match bridge
-        .call_with_timeout("engine::workers::list", json!({}), Duration::from_secs(5))
+        .call_with_timeout("engine::workers::list", json!({}), timeout)
         .await
     {
         Ok(workers_data) => success_response(workers_data),
@@ -53,15 +65,14 @@ async fn handle_workers(bridge: &III) -> Value {
     }
 }
 
-async fn handle_triggers_list(bridge: &III, input: Value) -> Value {
-    let include_internal = parse_bool_param(&input, "include_internal");
-    let effective_input = json!({ "include_internal": include_internal });
+async fn handle_triggers_list(bridge: &III, input: Value, timeout: Duration) -> Value {
+    let params = match extract(&input, INCLUDE_INTERNAL_PARAMS) {
+        Ok(params) => params,
+        Err(response) => return response,
+    };
+    let effective_input = json!({ "include_internal": params.bool("include_internal") });
     match bridge
-        .call_with_timeout(
-            "engine::triggers::list",
-            effective_input,
-            Duration::from_secs(5),
-        )
+        .call_with_timeout("engine::triggers::list", effective_input, timeout)
         .await
     {
         Ok(triggers_data) => success_response(triggers_data),
@@ -69,15 +80,14 @@ async fn handle_triggers_list(bridge: &III, input: Value) -> Value {
     }
 }
 
-async fn handle_functions_list(bridge: &III, input: Value) -> Value {
-    let include_internal = parse_bool_param(&input, "include_internal");
-    let effective_input = json!({ "include_internal": include_internal });
+async fn handle_functions_list(bridge: &III, input: Value, timeout: Duration) -> Value {
+    let params = match extract(&input, INCLUDE_INTERNAL_PARAMS) {
+        Ok(params) => params,
+        Err(response) => return response,
+    };
+    let effective_input = json!({ "include_internal": params.bool("include_internal") });
     match bridge
-        .call_with_timeout(
-            "engine::functions::list",
-            effective_input,
-            Duration::from_secs(5),
-        )
+        .call_with_timeout("engine::functions::list", effective_input, timeout)
         .await
     {
         Ok(functions_data) => success_response(functions_data),
@@ -85,15 +95,15 @@ async fn handle_functions_list(bridge: &III, input: Value) -> Value {
     }
 }
 
-async fn handle_status(bridge: &III) -> Value {
+async fn handle_status(bridge: &III, timeout: Duration) -> Value {
     let (workers_result, functions_result, metrics_result) = tokio::join!(
-        bridge.call_with_timeout("engine::workers::list", json!({}), Duration::from_secs(5)),
+        bridge.call_with_timeout("engine::workers::list", json!({}), timeout),
         bridge.call_with_timeout(
             "engine::functions::list",
             json!({ "include_internal": true }),
-            Duration::from_secs(5)
+            timeout
         ),
-        bridge.call_with_timeout("engine::metrics::list", json!({}), Duration::from_secs(5))
+        bridge.call_with_timeout("engine::metrics::list", json!({}), timeout)
     );
 
     let workers_count = workers_result
@@ -124,7 +134,7 @@ async fn handle_status(bridge: &III) -> Value {
     }))
 }
 
-async fn handle_trigger_types(bridge: &III) -> Value {
+async fn handle_trigger_types(bridge: &III, timeout: Duration) -> Value {
     let static_types = vec![
         "api",
         "event",
@@ -141,7 +151,7 @@ async fn handle_trigger_types(bridge: &III) -> Value {
         .call_with_timeout(
             "engine::triggers::list",
             json!({ "include_internal": true }),
-            Duration::from_secs(5),
+            timeout,
         )
         .await
     {
@@ -174,9 +184,9 @@ async fn handle_trigger_types(bridge: &III) -> Value {
     }
 }
 
-async fn handle_alerts_list(bridge: &III) -> Value {
+async fn handle_alerts_list(bridge: &III, timeout: Duration) -> Value {
     match bridge
-        .call_with_timeout("engine::alerts::list", json!({}), Duration::from_secs(5))
+        .call_with_timeout("engine::alerts::list", json!({}), timeout)
         .await
     {
         Ok(data) => success_response(data),
@@ -184,9 +194,9 @@ async fn handle_alerts_list(bridge: &III) -> Value {
     }
 }
 
-async fn handle_sampling_rules(bridge: &III) -> Value {
+async fn handle_sampling_rules(bridge: &III, timeout: Duration) -> Value {
     match bridge
-        .call_with_timeout("engine::sampling::rules", json!({}), Duration::from_secs(5))
+        .call_with_timeout("engine::sampling::rules", json!({}), timeout)
         .await
     {
         Ok(data) => success_response(data),
@@ -194,14 +204,10 @@ async fn handle_sampling_rules(bridge: &III) -> Value {
     }
 }
 
-async fn handle_otel_logs_list(bridge: &III, input: Value) -> Value {
+async fn handle_otel_logs_list(bridge: &III, input: Value, timeout: Duration) -> Value {
     let effective_input = input.get("body").cloned().unwrap_or(input);
     match bridge
-        .call_with_timeout(
-            "engine::logs::list",
-            effective_input,
-            Duration::from_secs(5),
-        )
+        .call_with_timeout("engine::logs::list", effective_input, timeout)
         .await
     {
         Ok(data) => success_response(data),
@@ -209,9 +215,9 @@ async fn handle_otel_logs_list(bridge: &III, input: Value) -> Value {
     }
 }
 
-async fn handle_otel_logs_clear(bridge: &III) -> Value {
+async fn handle_otel_logs_clear(bridge: &III, timeout: Duration) -> Value {
     match bridge
-        .call_with_timeout("engine::logs::clear", json!({}), Duration::from_secs(5))
+        .call_with_timeout("engine::logs::clear", json!({}), timeout)
         .await
     {
         Ok(data) => success_response(data),
@@ -219,14 +225,10 @@ async fn handle_otel_logs_clear(bridge: &III) -> Value {
     }
 }
 
-async fn handle_otel_traces_list(bridge: &III, input: Value) -> Value {
+async fn handle_otel_traces_list(bridge: &III, input: Value, timeout: Duration) -> Value {
     let effective_input = input.get("body").cloned().unwrap_or(input);
     match bridge
-        .call_with_timeout(
-            "engine::traces::list",
-            effective_input,
-            Duration::from_secs(5),
-        )
+        .call_with_timeout("engine::traces::list", effective_input, timeout)
         .await
     {
         Ok(data) => success_response(data),
@@ -234,9 +236,9 @@ async fn handle_otel_traces_list(bridge: &III, input: Value) -> Value {
     }
 }
 
-async fn handle_otel_traces_clear(bridge: &III) -> Value {
+async fn handle_otel_traces_clear(bridge: &III, timeout: Duration) -> Value {
     match bridge
-        .call_with_timeout("engine::traces::clear", json!({}), Duration::from_secs(5))
+        .call_with_timeout("engine::traces::clear", json!({}), timeout)
         .await
     {
         Ok(data) => success_response(data),
@@ -244,28 +246,15 @@ async fn handle_otel_traces_clear(bridge: &III) -> Value {
     }
 }
 
-async fn handle_otel_traces_tree(bridge: &III, input: Value) -> Value {
-    // Extract trace_id from body wrapper or top-level input
-    // API triggers wrap POST body inside a "body" field
-    let trace_id = input
-        .get("body")
-        .and_then(|b| b.get("trace_id"))
-        .and_then(|v| v.as_str())
-        .or_else(|| input.get("trace_id").and_then(|v| v.as_str()));
-
-    let trace_id = match trace_id {
-        Some(id) => id.to_string(),
-        None => {
-            return error_response(iii_sdk::IIIError::Handler(
-                "Missing trace_id in request".to_string(),
-            ))
-        }
+async fn handle_otel_traces_tree(bridge: &III, input: Value, timeout: Duration) -> Value {
+    let params = match extract(&input, &[Param::string("trace_id").required()]) {
+        Ok(params) => params,
+        Err(response) => return response,
     };
-
-    let tree_input = json!({ "trace_id": trace_id });
+    let tree_input = json!({ "trace_id": params.str("trace_id") });
 
     match bridge
-        .call_with_timeout("engine::traces::tree", tree_input, Duration::from_secs(10))
+        .call_with_timeout("engine::traces::tree", tree_input, timeout)
         .await
     {
         Ok(data) => success_response(data),
@@ -273,14 +262,10 @@ async fn handle_otel_traces_tree(bridge: &III, input: Value) -> Value {
     }
 }
 
-async fn handle_metrics_detailed(bridge: &III, input: Value) -> Value {
+async fn handle_metrics_detailed(bridge: &III, input: Value, timeout: Duration) -> Value {
     let effective_input = input.get("body").cloned().unwrap_or(input);
     match bridge
-        .call_with_timeout(
-            "engine::metrics::list",
-            effective_input,
-            Duration::from_secs(5),
-        )
+        .call_with_timeout("engine::metrics::list", effective_input, timeout)
         .await
     {
         Ok(data) => success_response(data),
@@ -288,14 +273,10 @@ async fn handle_metrics_detailed(bridge: &III, input: Value) -> Value {
     }
 }
 
-async fn handle_rollups_list(bridge: &III, input: Value) -> Value {
+async fn handle_rollups_list(bridge: &III, input: Value, timeout: Duration) -> Value {
     let effective_input = input.get("body").cloned().unwrap_or(input);
     match bridge
-        .call_with_timeout(
-            "engine::rollups::list",
-            effective_input,
-            Duration::from_secs(5),
-        )
+        .call_with_timeout("engine::rollups::list", effective_input, timeout)
         .await
     {
         Ok(data) => success_response(data),
@@ -303,183 +284,683 @@ async fn handle_rollups_list(bridge: &III, input: Value) -> Value {
     }
 }
 
-async fn handle_state_groups_list(bridge: &III, _input: Value) -> Value {
+/// Item counts list every group, so very large deployments can turn them off.
+const STATE_GROUPS_LIST_PARAMS: &[Param] = &[Param::bool("counts").default_bool(true)];
+
+async fn handle_state_groups_list(call: &Call, input: Value, timeout: Duration) -> Value {
+    let (bridge, stats, engine) = (&call.bridge, &call.services.state_stats, &call.engine);
+    let params = match extract(&input, STATE_GROUPS_LIST_PARAMS) {
+        Ok(params) => params,
+        Err(response) => return response,
+    };
+
     // Always use state::list_groups - no filtering by stream_name needed
-    match bridge
-        .call_with_timeout("state::list_groups", json!({}), Duration::from_secs(5))
+    let mut groups: Vec<String> = match bridge
+        .call_with_timeout("state::list_groups", json!({}), timeout)
         .await
     {
-        Ok(data) => {
-            if let Some(groups) = data.get("groups").and_then(|g| g.as_array()) {
-                let group_objects: Vec<_> = groups
+        Ok(data) => data
+            .get("groups")
+            .and_then(|g| g.as_array())
+            .map(|groups| {
+                groups
                     .iter()
-                    .filter_map(|g| g.as_str())
-                    .map(|id| json!({ "id": id, "count": 0 }))
-                    .collect();
+                    .filter_map(|g| g.as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        Err(err) => return error_response(err),
+    };
+    groups.retain(|group| state::group_visible(&call.services.auth, &call.principal, group));
+
+    let counts = params.bool("counts");
+    let group_objects: Vec<Value> = if counts {
+        let group_stats = stats.groups(bridge, engine, &groups, timeout).await;
+        groups
+            .iter()
+            .zip(group_stats)
+            .map(|(id, result)| match result {
+                Ok(stats) => json!({
+                    "id": id,
+                    "count": stats.count,
+                    "size_bytes": stats.size_bytes,
+                    "last_modified": stats.last_modified
+                }),
+                Err(err) => json!({
+                    "id": id,
+                    "count": null,
+                    "error": ConsoleError::from(err).message
+                }),
+            })
+            .collect()
+    } else {
+        groups
+            .iter()
+            .map(|id| json!({ "id": id, "count": null }))
+            .collect()
+    };
 
-                success_response(json!({
-                    "groups": group_objects,
-                    "count": group_objects.len()
-                }))
-            } else {
-                success_response(json!({ "groups": [], "count": 0 }))
-            }
-        }
-        Err(err) => error_response(err),
+    success_response(json!({
+        "groups": group_objects,
+        "count": group_objects.len(),
+        "counts": counts
+    }))
+}
+
+fn validate_cursor(cursor: &str) -> Result<(), String> {
+    cursor
+        .parse::<usize>()
+        .map(|_| ())
+        .map_err(|_| "is not a cursor returned by this function".to_string())
+}
+
+fn validate_filter(filter: &str) -> Result<(), String> {
+    state::parse_filter(filter).map(|_| ())
+}
+
+fn validate_order(order: &str) -> Result<(), String> {
+    match order {
+        "asc" | "desc" => Ok(()),
+        _ => Err("must be asc or desc".to_string()),
     }
 }
 
-async fn handle_state_group_items(bridge: &III, input: Value) -> Value {
-    // Extract scope from body or top-level input
-    let scope = input
-        .get("body")
-        .and_then(|b| b.get("scope"))
-        .and_then(|v| v.as_str())
-        .or_else(|| input.get("scope").and_then(|v| v.as_str()));
+const STATE_GROUP_ITEMS_PARAMS: &[Param] = &[
+    Param::string("scope").required(),
+    Param::string("cursor").check(validate_cursor),
+    Param::integer("limit").range(1, 1000).default_integer(100),
+    Param::string("prefix"),
+    Param::string("pattern"),
+    Param::string("filter").check(validate_filter),
+    Param::string("order").check(validate_order),
+];
+
+async fn handle_state_group_items(call: &Call, input: Value, timeout: Duration) -> Value {
+    let params = match extract(&input, STATE_GROUP_ITEMS_PARAMS) {
+        Ok(params) => params,
+        Err(response) => return response,
+    };
+    let scope = params.str("scope").unwrap_or_default();
+    if let Err(err) = state::check_readable(&call.services.auth, &call.principal, [scope]) {
+        return err.into_response();
+    }
+    let query = state::ItemQuery {
+        prefix: params.opt_string("prefix"),
+        pattern: params.opt_string("pattern"),
+        filter: params.opt_string("filter"),
+        descending: params.str("order") == Some("desc"),
+        offset: params
+            .str("cursor")
+            .and_then(|c| c.parse().ok())
+            .unwrap_or(0),
+        limit: params.usize("limit"),
+    };
 
-    match scope {
-        Some(scope) => {
-            let state_input = json!({ "scope": scope });
+    let items = match state::list(&call.bridge, scope, timeout).await {
+        Ok(items) => items,
+        Err(err) => return error_response(err),
+    };
+    match query.page(items) {
+        Ok(page) => success_response(json!({
+            "count": page.items.len(),
+            "total": page.total,
+            "next_cursor": page.next_cursor,
+            "items": page.items
+        })),
+        Err(reason) => ConsoleError::invalid_parameter("filter", reason).into_response(),
+    }
+}
 
-            match bridge
-                .call_with_timeout("state::list", state_input, Duration::from_secs(5))
-                .await
-            {
-                Ok(data) => {
-                    // state::list returns an array of items directly
-                    if let Some(items) = data.as_array() {
-                        success_response(json!({
-                            "items": items,
-                            "count": items.len()
-                        }))
-                    } else {
-                        success_response(json!({
-                            "items": [],
-                            "count": 0
-                        }))
-                    }
-                }
-                Err(err) => error_response(err),
-            }
-        }
-        None => error_response(iii_sdk::IIIError::Handler(
-            "Missing scope in request".to_string(),
-        )),
+fn validate_writable_group(group: &str) -> Result<(), String> {
+    match state::is_console_group(group) {
+        true => Err("is reserved for console records".to_string()),
+        false => Ok(()),
     }
 }
 
-async fn handle_state_item_set(bridge: &III, input: Value) -> Value {
-    // Extract path parameters (from URL: /states/:group/item)
-    let path_params = input.get("path_params");
-    let body = input.get("body");
+/// The group a state function changes, which must not hold console records.
+const WRITABLE_GROUP_PARAM: Param = Param::string("group")
+    .required()
+    .check(validate_writable_group);
 
-    let group_id = path_params
-        .and_then(|p| p.get("group"))
-        .and_then(|v| v.as_str())
-        .or_else(|| input.get("group").and_then(|v| v.as_str()));
+fn validate_hash(hash: &str) -> Result<(), String> {
+    match hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        true => Ok(()),
+        false => Err("must be a SHA-256 hex digest".to_string()),
+    }
+}
 
-    let group_id = match group_id {
-        Some(id) => id.to_string(),
-        None => {
-            return error_response(iii_sdk::IIIError::Handler(
-                "Missing group in path parameters".to_string(),
-            ))
-        }
-    };
+/// Optimistic concurrency: the hash of what the caller last read of the item.
+/// The value itself, `expected`, is read from the raw input, since `null`
+/// is a value to expect.
+const EXPECTED_HASH_PARAM: Param = Param::string("expected_hash").check(validate_hash);
+
+/// What the caller expects the item to hold, if the write is conditional.
+fn parse_expected(input: &Value, params: &Params) -> Result<Option<state::Expected>, Value> {
+    let value = [input.get("body"), Some(input)]
+        .into_iter()
+        .flatten()
+        .find_map(|source| source.get("expected"));
+    match (value, params.opt_string("expected_hash")) {
+        (Some(_), Some(_)) => Err(ConsoleError::invalid_parameter(
+            "expected_hash",
+            "cannot be combined with expected",
+        )
+        .into_response()),
+        (Some(value), None) => Ok(Some(state::Expected::Value(value.clone()))),
+        (None, Some(hash)) => Ok(Some(state::Expected::Hash(hash))),
+        (None, None) => Ok(None),
+    }
+}
 
-    // Extract key and value from body
-    let item_id = body
-        .and_then(|b| b.get("key"))
-        .and_then(|v| v.as_str())
-        .or_else(|| input.get("key").and_then(|v| v.as_str()));
+/// The 409 response for an item that no longer holds what the caller expects.
+fn check_current(
+    expected: &state::Expected,
+    group: &str,
+    key: &str,
+    current: Value,
+) -> Result<(), Value> {
+    if expected.matches(&current) {
+        return Ok(());
+    }
+    Err(ConsoleError::new(
+        409,
+        "conflict",
+        format!("'{}' changed since it was read", key),
+    )
+    .with_details(json!({
+        "group": group,
+        "key": key,
+        "current_hash": state::value_hash(&current),
+        "current": current
+    }))
+    .into_response())
+}
 
-    let item_id = match item_id {
-        Some(id) => id.to_string(),
-        None => {
-            return error_response(iii_sdk::IIIError::Handler(
-                "Missing key in request body".to_string(),
-            ))
-        }
+/// Check an item still holds what the caller expects before it is changed,
+/// or the 409 response carrying its current value. Without `expected` or
+/// `expected_hash` the write is unconditional.
+///
+/// The check and the write are separate engine calls, so this catches edits
+/// made while a user had the item open, not ones landing in between.
+async fn check_expected(
+    bridge: &III,
+    input: &Value,
+    params: &Params,
+    group: &str,
+    key: &str,
+    timeout: Duration,
+) -> Result<(), Value> {
+    let Some(expected) = parse_expected(input, params)? else {
+        return Ok(());
+    };
+    let current = state::get(bridge, group, key, timeout)
+        .await
+        .map_err(error_response)?;
+    check_current(&expected, group, key, current)
+}
+
+const STATE_ITEM_SET_PARAMS: &[Param] = &[
+    WRITABLE_GROUP_PARAM,
+    Param::string("key").required(),
+    Param::json("value").required().sources(JSON_SOURCES),
+    EXPECTED_HASH_PARAM,
+];
+
+async fn handle_state_item_set(call: &Call, input: Value, timeout: Duration) -> Value {
+    let (bridge, stats, engine) = (&call.bridge, &call.services.state_stats, &call.engine);
+    // The group comes from the URL (/states/:group/item), the key and value from the body
+    let mut params = match extract(&input, STATE_ITEM_SET_PARAMS) {
+        Ok(params) => params,
+        Err(response) => return response,
     };
 
-    let data = body
-        .and_then(|b| b.get("value"))
-        .or_else(|| input.get("value"));
+    let (group, key) = (params.string("group"), params.string("key"));
+    if let Err(response) = check_expected(bridge, &input, &params, &group, &key, timeout).await {
+        return response;
+    }
 
-    let data = match data {
-        Some(value) => value.clone(),
-        None => {
-            return error_response(iii_sdk::IIIError::Handler(
-                "Missing value in request body".to_string(),
-            ))
+    let state_input = json!({
+        "scope": group,
+        "key": key,
+        "value": params.take("value")
+    });
+
+    match bridge
+        .call_with_timeout("state::set", state_input, timeout)
+        .await
+    {
+        Ok(data) => {
+            stats.invalidate(engine, &group);
+            success_response(data)
         }
+        Err(err) => error_response(err),
+    }
+}
+
+const STATE_ITEM_DELETE_PARAMS: &[Param] = &[
+    WRITABLE_GROUP_PARAM,
+    Param::string("key").required(),
+    EXPECTED_HASH_PARAM,
+];
+
+async fn handle_state_item_delete(call: &Call, input: Value, timeout: Duration) -> Value {
+    let (bridge, stats, engine) = (&call.bridge, &call.services.state_stats, &call.engine);
+    // Path parameters from the URL: /states/:group/item/:key
+    tracing::debug!(path_params = ?input.get("path_params"), "Received state item delete input");
+    let params = match extract(&input, STATE_ITEM_DELETE_PARAMS) {
+        Ok(params) => params,
+        Err(response) => return response,
     };
+    let (group, key) = (params.string("group"), params.string("key"));
+    if let Err(response) = check_expected(bridge, &input, &params, &group, &key, timeout).await {
+        return response;
+    }
 
     let state_input = json!({
-        "scope": group_id,
-        "key": item_id,
-        "value": data
+        "scope": group,
+        "key": key
     });
 
     match bridge
-        .call_with_timeout("state::set", state_input, Duration::from_secs(5))
+        .call_with_timeout("state::delete", state_input, timeout)
         .await
     {
-        Ok(data) => success_response(data),
+        Ok(data) => {
+            stats.invalidate(engine, &group);
+            success_response(data)
+        }
         Err(err) => error_response(err),
     }
 }
 
-async fn handle_state_item_delete(bridge: &III, input: Value) -> Value {
-    // Extract path parameters (from URL: /states/:group/item/:key)
-    let path_params = input.get("path_params");
+/// Keys or items accepted by one bulk state call.
+const MAX_BULK_ITEMS: usize = 1000;
 
-    tracing::debug!(path_params = ?path_params, "Received state item delete input");
-    let group_id = path_params
-        .and_then(|p| p.get("group"))
-        .and_then(|v| v.as_str())
-        .or_else(|| input.get("group").and_then(|v| v.as_str()));
+/// A state key given as a non-empty string or a number.
+fn key_of(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
 
-    let group_id = match group_id {
-        Some(id) => id.to_string(),
-        None => {
-            return error_response(iii_sdk::IIIError::Handler(
-                "Missing group in path parameters".to_string(),
-            ))
+/// The entries of a bulk call's JSON array, bounded by [`MAX_BULK_ITEMS`].
+fn bulk_entries(value: Option<Value>) -> Result<Vec<Value>, String> {
+    match value {
+        Some(Value::Array(entries)) if entries.is_empty() => Err("must not be empty".to_string()),
+        Some(Value::Array(entries)) if entries.len() > MAX_BULK_ITEMS => {
+            Err(format!("must hold at most {} entries", MAX_BULK_ITEMS))
         }
+        Some(Value::Array(entries)) => Ok(entries),
+        _ => Err("must be an array".to_string()),
+    }
+}
+
+/// Keys to delete, without repeats.
+fn parse_keys(value: Option<Value>) -> Result<Vec<String>, String> {
+    let mut seen = HashSet::new();
+    let mut keys = Vec::new();
+    for (i, entry) in bulk_entries(value)?.iter().enumerate() {
+        let key = key_of(entry).ok_or_else(|| format!("entry {} is not a string or number", i))?;
+        if seen.insert(key.clone()) {
+            keys.push(key);
+        }
+    }
+    Ok(keys)
+}
+
+/// `{ key, value }` entries to set. A key given twice is rejected, since the
+/// writes run concurrently and either could win.
+fn parse_items(value: Option<Value>) -> Result<Vec<(String, Value)>, String> {
+    let mut seen = HashSet::new();
+    let mut items = Vec::new();
+    for (i, mut entry) in bulk_entries(value)?.into_iter().enumerate() {
+        let key = entry
+            .get("key")
+            .and_then(key_of)
+            .ok_or_else(|| format!("entry {} needs a key", i))?;
+        let value = entry
+            .get_mut("value")
+            .map(Value::take)
+            .ok_or_else(|| format!("entry {} needs a value", i))?;
+        if !seen.insert(key.clone()) {
+            return Err(format!("key '{}' appears more than once", key));
+        }
+        items.push((key, value));
+    }
+    Ok(items)
+}
+
+/// What a dry run of a bulk call would touch.
+fn bulk_preview(keys: Vec<String>) -> Value {
+    json!({
+        "dry_run": true,
+        "count": keys.len(),
+        "keys": keys
+    })
+}
+
+/// How each key of a bulk call fared.
+fn bulk_report(results: Vec<KeyResult>) -> Value {
+    let succeeded = results.iter().filter(|r| r.ok).count();
+    json!({
+        "dry_run": false,
+        "count": results.len(),
+        "succeeded": succeeded,
+        "failed": results.len() - succeeded,
+        "results": results
+    })
+}
+
+/// Keys of the listed items, and how many items had no key to act on.
+fn listed_keys(items: &[Value]) -> (Vec<String>, usize) {
+    let keys: Vec<String> = items.iter().filter_map(state::item_key).collect();
+    let skipped = items.len() - keys.len();
+    (keys, skipped)
+}
+
+const STATE_ITEMS_SET_PARAMS: &[Param] = &[
+    WRITABLE_GROUP_PARAM,
+    Param::json("items").required().sources(JSON_SOURCES),
+    Param::bool("dry_run").default_bool(false),
+];
+
+async fn handle_state_items_set(call: &Call, input: Value, timeout: Duration) -> Value {
+    let (bridge, stats, engine) = (&call.bridge, &call.services.state_stats, &call.engine);
+    let mut params = match extract(&input, STATE_ITEMS_SET_PARAMS) {
+        Ok(params) => params,
+        Err(response) => return response,
+    };
+    let items = match parse_items(params.take("items")) {
+        Ok(items) => items,
+        Err(reason) => return ConsoleError::invalid_parameter("items", reason).into_response(),
+    };
+    if params.bool("dry_run") {
+        return success_response(bulk_preview(
+            items.into_iter().map(|(key, _)| key).collect(),
+        ));
+    }
+
+    let group = params.string("group");
+    let results = state::set_items(bridge, &group, items, timeout).await;
+    stats.invalidate(engine, &group);
+    success_response(bulk_report(results))
+}
+
+const STATE_ITEMS_DELETE_PARAMS: &[Param] = &[
+    WRITABLE_GROUP_PARAM,
+    Param::json("keys").required().sources(JSON_SOURCES),
+    Param::bool("dry_run").default_bool(false),
+];
+
+async fn handle_state_items_delete(call: &Call, input: Value, timeout: Duration) -> Value {
+    let (bridge, stats, engine) = (&call.bridge, &call.services.state_stats, &call.engine);
+    let mut params = match extract(&input, STATE_ITEMS_DELETE_PARAMS) {
+        Ok(params) => params,
+        Err(response) => return response,
+    };
+    let keys = match parse_keys(params.take("keys")) {
+        Ok(keys) => keys,
+        Err(reason) => return ConsoleError::invalid_parameter("keys", reason).into_response(),
+    };
+    if params.bool("dry_run") {
+        return success_response(bulk_preview(keys));
+    }
+
+    let group = params.string("group");
+    let results = state::delete_keys(bridge, &group, keys, timeout).await;
+    stats.invalidate(engine, &group);
+    success_response(bulk_report(results))
+}
+
+const STATE_ITEMS_DELETE_MATCHING_PARAMS: &[Param] = &[
+    WRITABLE_GROUP_PARAM,
+    Param::string("prefix"),
+    Param::string("pattern"),
+    Param::string("filter").check(validate_filter),
+    Param::bool("dry_run").default_bool(false),
+];
+
+async fn handle_state_items_delete_matching(call: &Call, input: Value, timeout: Duration) -> Value {
+    let (bridge, stats, engine) = (&call.bridge, &call.services.state_stats, &call.engine);
+    let params = match extract(&input, STATE_ITEMS_DELETE_MATCHING_PARAMS) {
+        Ok(params) => params,
+        Err(response) => return response,
+    };
+    let query = state::ItemQuery {
+        prefix: params.opt_string("prefix"),
+        pattern: params.opt_string("pattern"),
+        filter: params.opt_string("filter"),
+        descending: false,
+        offset: 0,
+        limit: usize::MAX,
     };
+    // Matching nothing in particular would clear the group without its confirmation
+    if query.prefix.is_none() && query.pattern.is_none() && query.filter.is_none() {
+        return ConsoleError::invalid_parameter(
+            "pattern",
+            "a prefix, pattern or filter is required; use state_group_clear to delete every item",
+        )
+        .into_response();
+    }
 
-    let item_id = path_params
-        .and_then(|p| p.get("key"))
-        .and_then(|v| v.as_str())
-        .or_else(|| input.get("key").and_then(|v| v.as_str()));
+    let group = params.string("group");
+    let items = match state::list(bridge, &group, timeout).await {
+        Ok(items) => items,
+        Err(err) => return error_response(err),
+    };
+    let matching = match query.page(items) {
+        Ok(page) => page.items,
+        Err(reason) => return ConsoleError::invalid_parameter("filter", reason).into_response(),
+    };
+    let (keys, skipped) = listed_keys(&matching);
 
-    let item_id = match item_id {
-        Some(id) => id.to_string(),
+    let mut body = if params.bool("dry_run") {
+        bulk_preview(keys)
+    } else {
+        let results = state::delete_keys(bridge, &group, keys, timeout).await;
+        stats.invalidate(engine, &group);
+        bulk_report(results)
+    };
+    body["skipped"] = json!(skipped);
+    success_response(body)
+}
+
+const STATE_GROUP_CLEAR_PARAMS: &[Param] = &[
+    WRITABLE_GROUP_PARAM,
+    Param::string("confirm"),
+    Param::bool("dry_run").default_bool(false),
+];
+
+/// Delete every item of a group. A dry run returns a `confirm_token` that the
+/// clear itself must pass as `confirm`; it stops matching once the group's
+/// keys change.
+async fn handle_state_group_clear(call: &Call, input: Value, timeout: Duration) -> Value {
+    let (bridge, stats, engine) = (&call.bridge, &call.services.state_stats, &call.engine);
+    let params = match extract(&input, STATE_GROUP_CLEAR_PARAMS) {
+        Ok(params) => params,
+        Err(response) => return response,
+    };
+    let group = params.string("group");
+    let items = match state::list(bridge, &group, timeout).await {
+        Ok(items) => items,
+        Err(err) => return error_response(err),
+    };
+    let (keys, skipped) = listed_keys(&items);
+    let token = state::clear_token(&group, &keys);
+
+    if params.bool("dry_run") {
+        let mut body = bulk_preview(keys);
+        body["skipped"] = json!(skipped);
+        body["confirm_token"] = json!(token);
+        return success_response(body);
+    }
+    match params.str("confirm") {
         None => {
-            return error_response(iii_sdk::IIIError::Handler(
-                "Missing key in path parameters".to_string(),
-            ))
+            return ConsoleError::new(
+                400,
+                "confirmation_required",
+                "Clearing a group needs the confirm_token of a dry run",
+            )
+            .into_response()
+        }
+        Some(confirm) if confirm != token => {
+            return ConsoleError::new(
+                409,
+                "confirmation_mismatch",
+                "The group changed since the dry run; preview the clear again",
+            )
+            .into_response()
         }
+        Some(_) => {}
+    }
+
+    let results = state::delete_keys(bridge, &group, keys, timeout).await;
+    stats.invalidate(engine, &group);
+    let mut body = bulk_report(results);
+    body["skipped"] = json!(skipped);
+    success_response(body)
+}
+
+/// Conflicts listed in a failed import's error details.
+const MAX_REPORTED_CONFLICTS: usize = 100;
+
+/// Groups given as an array, or as a comma-separated list in the query string.
+fn parse_groups(value: Option<Value>) -> Result<Vec<String>, String> {
+    let groups: Vec<String> = match value {
+        Some(Value::String(list)) => list
+            .split(',')
+            .map(str::trim)
+            .filter(|g| !g.is_empty())
+            .map(String::from)
+            .collect(),
+        Some(Value::Array(groups)) => groups
+            .iter()
+            .map(|g| g.as_str().filter(|g| !g.is_empty()).map(String::from))
+            .collect::<Option<_>>()
+            .ok_or("must only hold group IDs")?,
+        _ => return Err("must be an array or a comma-separated list".to_string()),
     };
+    match groups.is_empty() {
+        true => Err("must name at least one group".to_string()),
+        false => Ok(groups),
+    }
+}
 
-    let state_input = json!({
-        "scope": group_id,
-        "key": item_id
-    });
+fn validate_format(format: &str) -> Result<(), String> {
+    match format {
+        "json" | "ndjson" => Ok(()),
+        _ => Err("must be json or ndjson".to_string()),
+    }
+}
 
-    match bridge
-        .call_with_timeout("state::delete", state_input, Duration::from_secs(5))
-        .await
-    {
-        Ok(data) => success_response(data),
-        Err(err) => error_response(err),
+fn validate_mode(mode: &str) -> Result<(), String> {
+    ConflictMode::parse(mode)
+        .map(|_| ())
+        .ok_or_else(|| "must be overwrite, skip-existing or fail".to_string())
+}
+
+const STATE_EXPORT_PARAMS: &[Param] = &[
+    Param::json("groups").required().alias("group"),
+    Param::string("format").check(validate_format),
+];
+
+/// Export groups as a JSON document, or as NDJSON text with `format=ndjson`.
+async fn handle_state_export(call: &Call, input: Value, timeout: Duration) -> Value {
+    let mut params = match extract(&input, STATE_EXPORT_PARAMS) {
+        Ok(params) => params,
+        Err(response) => return response,
+    };
+    let groups = match parse_groups(params.take("groups")) {
+        Ok(groups) => groups,
+        Err(reason) => return ConsoleError::invalid_parameter("groups", reason).into_response(),
+    };
+    let readable = state::check_readable(
+        &call.services.auth,
+        &call.principal,
+        groups.iter().map(String::as_str),
+    );
+    if let Err(err) = readable {
+        return err.into_response();
+    }
+
+    let mut exported = Vec::new();
+    for group in groups {
+        match state::export_group(&call.bridge, &group, timeout).await {
+            Ok(records) => exported.push((group, records)),
+            Err(err) => return error_response(err),
+        }
+    }
+    if params.str("format") == Some("ndjson") {
+        let records: Vec<_> = exported.into_iter().flat_map(|(_, r)| r).collect();
+        success_response(Value::String(state::to_ndjson(&records)))
+    } else {
+        success_response(state::to_document(exported))
+    }
+}
+
+const STATE_IMPORT_PARAMS: &[Param] = &[
+    Param::string("mode").check(validate_mode),
+    Param::json("data").sources(JSON_SOURCES),
+];
+
+/// Import an export: the body itself, or NDJSON text in `data`.
+async fn handle_state_import(call: &Call, input: Value, timeout: Duration) -> Value {
+    let (bridge, stats, engine) = (&call.bridge, &call.services.state_stats, &call.engine);
+    let mut params = match extract(&input, STATE_IMPORT_PARAMS) {
+        Ok(params) => params,
+        Err(response) => return response,
+    };
+    let data = params
+        .take("data")
+        .unwrap_or_else(|| input.get("body").unwrap_or(&input).clone());
+    let records = match state::parse_export(data) {
+        Ok(records) => records,
+        Err(reason) => return ConsoleError::invalid_parameter("data", reason).into_response(),
+    };
+    let mode = params
+        .str("mode")
+        .and_then(ConflictMode::parse)
+        .unwrap_or(ConflictMode::Fail);
+
+    match state::import(bridge, records, mode, timeout).await {
+        Ok(report) => {
+            for group in &report.groups {
+                stats.invalidate(engine, group);
+            }
+            success_response(json!(report))
+        }
+        Err(ImportError::Engine(err)) => error_response(err),
+        Err(ImportError::Protected(groups)) => ConsoleError::invalid_parameter(
+            "data",
+            format!("writes to console groups: {}", groups.join(", ")),
+        )
+        .into_response(),
+        Err(ImportError::Conflict(conflicts)) => ConsoleError::new(
+            409,
+            "import_conflict",
+            format!(
+                "{} imported keys already exist; nothing was imported",
+                conflicts.len()
+            ),
+        )
+        .with_details(json!({
+            "count": conflicts.len(),
+            "conflicts": conflicts
+                .iter()
+                .take(MAX_REPORTED_CONFLICTS)
+                .map(|(group, key)| json!({ "group": group, "key": key }))
+                .collect::<Vec<_>>()
+        }))
+        .into_response(),
     }
 }
 
-async fn handle_streams_list(bridge: &III) -> Value {
+async fn handle_streams_list(bridge: &III, timeout: Duration) -> Value {
     match bridge
-        .call_with_timeout("stream::list_all", json!({}), Duration::from_secs(10))
+        .call_with_timeout("stream::list_all", json!({}), timeout)
         .await
     {
         Ok(data) => {
@@ -525,33 +1006,17 @@ async fn handle_streams_list(bridge: &III) -> Value {
     }
 }
 
-async fn handle_flow_config_get(bridge: &III, input: Value) -> Value {
-    // Get flow_id from path_params or query_params
-    let flow_id = input
-        .get("path_params")
-        .and_then(|p| p.get("flow_id"))
-        .and_then(|v| v.as_str())
-        .or_else(|| {
-            input
-                .get("query_params")
-                .and_then(|p| p.get("flow_id"))
-                .and_then(|v| v.as_str())
-        })
-        .or_else(|| input.get("flow_id").and_then(|v| v.as_str()));
-
-    let flow_id = match flow_id {
-        Some(id) => id.to_string(),
-        None => {
-            return error_response(iii_sdk::IIIError::Handler(
-                "Missing flow_id parameter".to_string(),
-            ))
-        }
-    };
+const FLOW_ID_PARAM: Param = Param::string("flow_id")
+    .required()
+    .alias("id")
+    .check(validate_flow_id);
 
-    let flow_id = match validate_flow_id(&flow_id) {
-        Ok(id) => id,
-        Err(err) => return err,
+async fn handle_flow_config_get(bridge: &III, input: Value, timeout: Duration) -> Value {
+    let params = match extract(&input, &[FLOW_ID_PARAM]) {
+        Ok(params) => params,
+        Err(response) => return response,
     };
+    let flow_id = params.string("flow_id");
 
     // Try to get config from the engine's state
     let state_input = json!({
@@ -560,7 +1025,7 @@ async fn handle_flow_config_get(bridge: &III, input: Value) -> Value {
     });
 
     match bridge
-        .call_with_timeout("state::get", state_input, Duration::from_secs(5))
+        .call_with_timeout("state::get", state_input, timeout)
         .await
     {
         Ok(data) => {
@@ -577,69 +1042,312 @@ async fn handle_flow_config_get(bridge: &III, input: Value) -> Value {
     }
 }
 
-async fn handle_invoke(bridge: &III, input: Value) -> Value {
-    let body = input.get("body").unwrap_or(&input);
+/// A function call made on behalf of a console user.
+struct Invocation<'a> {
+    bridge: &'a III,
+    services: &'a Services,
+    engine: String,
+    function_id: String,
+    data: Value,
+    /// Run as a background job instead of waiting for the result
+    background: bool,
+    /// Check the input against the function's request schema first
+    validate: bool,
+    timeout: Duration,
+    replay_of: Option<String>,
+}
 
-    let function_id = body
-        .get("function_id")
-        .and_then(|v| v.as_str())
-        .or_else(|| input.get("function_id").and_then(|v| v.as_str()));
+/// The timeout for an invocation with the request's `timeout_ms`. Background
+/// jobs run up to the server maximum unless the request asks for less.
+fn invocation_timeout(
+    services: &Services,
+    params: &Params,
+    background: bool,
+    timeout: Duration,
+) -> Duration {
+    let timeout_ms = params.u64(TIMEOUT_MS_PARAM_NAME);
+    if background {
+        services
+            .timeouts
+            .requested(timeout_ms)
+            .unwrap_or_else(|| services.timeouts.max())
+    } else {
+        services.timeouts.for_request(timeout_ms, timeout)
+    }
+}
 
-    let function_id = match function_id {
-        Some(id) => id.to_string(),
-        None => {
-            return error_response(iii_sdk::IIIError::Handler(
-                "Missing function_id in request".to_string(),
-            ))
+/// Check an invocation's input against the function's request schema.
+///
+/// Returns the response to send instead of calling the function: a 422 when
+/// the input does not match, or the error of a failed schema lookup. Functions
+/// without a schema, and schemas that cannot be compiled, are let through.
+async fn validate_invocation(call: &Invocation<'_>) -> Option<Value> {
+    let lookup_timeout = call
+        .services
+        .timeouts
+        .for_function("engine::console::functions");
+    let schema = match call
+        .services
+        .schemas
+        .request_schema(call.bridge, &call.engine, &call.function_id, lookup_timeout)
+        .await
+    {
+        Ok(Some(schema)) => schema,
+        Ok(None) => return None,
+        Err(err) => {
+            let mut error = ConsoleError::from(err);
+            error.message = format!(
+                "Could not load the request schema of {}: {}; pass skip_validation to call it anyway",
+                call.function_id, error.message
+            );
+            return Some(error.into_response());
         }
     };
 
-    let data = body
-        .get("input")
-        .or_else(|| input.get("input"))
-        .cloned()
-        .unwrap_or(json!({}));
+    match schema::validate(&schema, &call.data) {
+        Ok(violations) if violations.is_empty() => None,
+        Ok(violations) => Some(
+            ConsoleError::new(
+                422,
+                "validation_failed",
+                format!(
+                    "Input does not match the request schema of {}",
+                    call.function_id
+                ),
+            )
+            .with_details(json!({ "violations": violations }))
+            .into_response(),
+        ),
+        Err(e) => {
+            tracing::warn!(function_id = %call.function_id, "Skipping input validation, invalid request schema: {}", e);
+            None
+        }
+    }
+}
 
-    match bridge
-        .call_with_timeout(&function_id, data, Duration::from_secs(30))
-        .await
-    {
+/// Call the function, or start it as a job, and record it in the invocation history.
+async fn run_invocation(call: Invocation<'_>) -> Value {
+    if call.validate {
+        if let Some(response) = validate_invocation(&call).await {
+            return response;
+        }
+    }
+
+    if call.background {
+        return match call.services.jobs.start(
+            &call.engine,
+            call.bridge.clone(),
+            call.function_id,
+            call.data,
+            call.timeout,
+            call.replay_of,
+        ) {
+            Ok(job) => accepted_response(json!({ "job_id": job.id, "job": job })),
+            Err(message) => ConsoleError::new(429, "too_many_jobs", message)
+                .retryable()
+                .into_response(),
+        };
+    }
+
+    let started_at = now_ms();
+    let started = std::time::Instant::now();
+    let result = call
+        .bridge
+        .call_with_timeout(&call.function_id, call.data.clone(), call.timeout)
+        .await;
+
+    let mut record = InvocationRecord::new(
+        &call.function_id,
+        &call.engine,
+        call.data,
+        &result,
+        started_at,
+        started.elapsed(),
+    );
+    record.replay_of = call.replay_of;
+    history::record(call.bridge, record);
+
+    match result {
         Ok(result) => success_response(result),
         Err(err) => error_response(err),
     }
 }
 
-async fn handle_cron_trigger(bridge: &III, input: Value) -> Value {
-    let body = input.get("body").unwrap_or(&input);
+const TIMEOUT_MS_PARAM_NAME: &str = "timeout_ms";
+
+/// Per-request timeout override, declared by the handlers that accept one and
+/// applied with `Timeouts::for_request`.
+const TIMEOUT_MS_PARAM: Param = Param::integer(TIMEOUT_MS_PARAM_NAME).min(1);
+
+const INVOKE_PARAMS: &[Param] = &[
+    Param::string("function_id").required(),
+    Param::json("input").sources(JSON_SOURCES),
+    Param::string("preset").check(presets::validate_name),
+    Param::bool("async"),
+    Param::bool("skip_validation"),
+    TIMEOUT_MS_PARAM,
+];
+
+async fn handle_invoke(
+    bridge: &III,
+    services: &Services,
+    engine: &str,
+    input: Value,
+    timeout: Duration,
+) -> Value {
+    let mut params = match extract(&input, INVOKE_PARAMS) {
+        Ok(params) => params,
+        Err(response) => return response,
+    };
+    let function_id = params.string("function_id");
 
-    let trigger_id = body
-        .get("trigger_id")
-        .and_then(|v| v.as_str())
-        .or_else(|| input.get("trigger_id").and_then(|v| v.as_str()));
-
-    let trigger_id = match trigger_id {
-        Some(id) if !id.is_empty() => id.to_string(),
-        _ => {
-            return error_response(iii_sdk::IIIError::Handler(
-                "Missing trigger_id in request".to_string(),
-            ))
+    let data = match (params.take("input"), params.opt_string("preset")) {
+        (Some(_), Some(_)) => {
+            return ConsoleError::invalid_parameter("preset", "cannot be combined with input")
+                .into_response()
         }
+        (Some(data), None) => data,
+        (None, Some(name)) => match presets::get(bridge, &function_id, &name, timeout).await {
+            Ok(Some(preset)) => preset.input,
+            Ok(None) => {
+                return ConsoleError::not_found(
+                    "preset_not_found",
+                    format!("Preset '{}' not found for {}", name, function_id),
+                )
+                .into_response()
+            }
+            Err(err) => return error_response(err),
+        },
+        (None, None) => json!({}),
+    };
+
+    let background = params.bool("async");
+    run_invocation(Invocation {
+        bridge,
+        services,
+        engine: engine.to_string(),
+        function_id,
+        data,
+        background,
+        validate: !params.bool("skip_validation"),
+        timeout: invocation_timeout(services, &params, background, timeout),
+        replay_of: None,
+    })
+    .await
+}
+
+const FUNCTION_EXAMPLE_PARAMS: &[Param] = &[
+    Param::string("function_id").required(),
+    Param::bool("required_only"),
+];
+
+/// Build an example input for a function from its request schema.
+async fn handle_function_example(call: &Call, input: Value, timeout: Duration) -> Value {
+    let params = match extract(&input, FUNCTION_EXAMPLE_PARAMS) {
+        Ok(params) => params,
+        Err(response) => return response,
+    };
+    let function_id = params.string("function_id");
+    let required_only = params.bool("required_only");
+
+    match call
+        .services
+        .schemas
+        .request_schema(&call.bridge, &call.engine, &function_id, timeout)
+        .await
+    {
+        Ok(Some(request_schema)) => match schema::example(&request_schema, required_only) {
+            Ok(example) => success_response(json!({
+                "function_id": function_id,
+                "has_schema": true,
+                "example": example
+            })),
+            Err(reason) => ConsoleError::new(
+                422,
+                "example_unavailable",
+                format!("Cannot build an example for {}: {}", function_id, reason),
+            )
+            .into_response(),
+        },
+        Ok(None) => success_response(json!({
+            "function_id": function_id,
+            "has_schema": false,
+            "example": {}
+        })),
+        Err(err) => error_response(err),
+    }
+}
+
+const JOB_ID_PARAMS: &[Param] = &[Param::string("job_id").required()];
+
+fn handle_invocation_status(jobs: &Jobs, input: Value) -> Value {
+    let job_id = match extract(&input, JOB_ID_PARAMS) {
+        Ok(params) => params.string("job_id"),
+        Err(response) => return response,
     };
 
-    let provided_function_id = body
-        .get("function_id")
-        .and_then(|v| v.as_str())
-        .or_else(|| input.get("function_id").and_then(|v| v.as_str()))
-        .map(|v| v.to_string());
+    match jobs.get(&job_id) {
+        Some(job) => success_response(json!(job)),
+        None => ConsoleError::not_found(
+            "job_not_found",
+            format!("Invocation '{}' not found", job_id),
+        )
+        .into_response(),
+    }
+}
+
+fn handle_invocation_cancel(jobs: &Jobs, input: Value) -> Value {
+    let job_id = match extract(&input, JOB_ID_PARAMS) {
+        Ok(params) => params.string("job_id"),
+        Err(response) => return response,
+    };
 
-    let function_id = if let Some(function_id) = provided_function_id {
+    match jobs.cancel(&job_id) {
+        Cancel::Cancelled(job) => success_response(json!(job)),
+        Cancel::AlreadyFinished(job) => ConsoleError::new(
+            409,
+            "job_finished",
+            format!("Invocation '{}' already finished", job_id),
+        )
+        .with_details(json!({ "job": job }))
+        .into_response(),
+        Cancel::NotFound => ConsoleError::not_found(
+            "job_not_found",
+            format!("Invocation '{}' not found", job_id),
+        )
+        .into_response(),
+    }
+}
+
+const CRON_TRIGGER_PARAMS: &[Param] = &[
+    Param::string("trigger_id").required(),
+    Param::string("function_id"),
+    TIMEOUT_MS_PARAM,
+];
+
+async fn handle_cron_trigger(
+    bridge: &III,
+    services: &Services,
+    input: Value,
+    timeout: Duration,
+) -> Value {
+    let params = match extract(&input, CRON_TRIGGER_PARAMS) {
+        Ok(params) => params,
+        Err(response) => return response,
+    };
+    let trigger_id = params.string("trigger_id");
+    let timeout = services
+        .timeouts
+        .for_request(params.u64(TIMEOUT_MS_PARAM_NAME), timeout);
+
+    let function_id = if let Some(function_id) = params.opt_string("function_id") {
         function_id
     } else {
         let triggers_data = match bridge
             .call_with_timeout(
                 "engine::triggers::list",
                 json!({ "include_internal": true }),
-                Duration::from_secs(5),
+                timeout,
             )
             .await
         {
@@ -663,10 +1371,11 @@ async fn handle_cron_trigger(bridge: &III, input: Value) -> Value {
         let trigger = match trigger_match {
             Some(trigger) => trigger,
             None => {
-                return error_response(iii_sdk::IIIError::Handler(format!(
-                    "Cron trigger '{}' not found",
-                    trigger_id
-                )))
+                return ConsoleError::not_found(
+                    "trigger_not_found",
+                    format!("Cron trigger '{}' not found", trigger_id),
+                )
+                .into_response()
             }
         };
 
@@ -676,19 +1385,22 @@ async fn handle_cron_trigger(bridge: &III, input: Value) -> Value {
             .unwrap_or_default();
 
         if trigger_type != "cron" {
-            return error_response(iii_sdk::IIIError::Handler(format!(
-                "Trigger '{}' is not a cron trigger",
-                trigger_id
-            )));
+            return ConsoleError::invalid_parameter(
+                "trigger_id",
+                format!("trigger '{}' is not a cron trigger", trigger_id),
+            )
+            .into_response();
         }
 
         match trigger.get("function_id").and_then(|v| v.as_str()) {
             Some(id) if !id.is_empty() => id.to_string(),
             _ => {
-                return error_response(iii_sdk::IIIError::Handler(format!(
-                    "Cron trigger '{}' has no function_id",
-                    trigger_id
-                )))
+                return ConsoleError::new(
+                    422,
+                    "trigger_without_function",
+                    format!("Cron trigger '{}' has no function_id", trigger_id),
+                )
+                .into_response()
             }
         }
     };
@@ -707,7 +1419,7 @@ async fn handle_cron_trigger(bridge: &III, input: Value) -> Value {
     });
 
     match bridge
-        .call_with_timeout(&function_id, payload, Duration::from_secs(30))
+        .call_with_timeout(&function_id, payload, timeout)
         .await
     {
         Ok(result) => success_response(json!({
@@ -719,31 +1431,18 @@ async fn handle_cron_trigger(bridge: &III, input: Value) -> Value {
     }
 }
 
-async fn handle_flow_config_save(bridge: &III, input: Value) -> Value {
-    let body = input.get("body").cloned().unwrap_or(input.clone());
-
-    let flow_id = input
-        .get("path_params")
-        .and_then(|p| p.get("flow_id"))
-        .and_then(|v| v.as_str())
-        .or_else(|| body.get("id").and_then(|v| v.as_str()));
-
-    let flow_id = match flow_id {
-        Some(id) => id.to_string(),
-        None => {
-            return error_response(iii_sdk::IIIError::Handler(
-                "Missing flow_id parameter".to_string(),
-            ))
-        }
-    };
+const FLOW_CONFIG_SAVE_PARAMS: &[Param] = &[
+    FLOW_ID_PARAM,
+    Param::json("config").default_object().sources(JSON_SOURCES),
+];
 
-    let flow_id = match validate_flow_id(&flow_id) {
-        Ok(id) => id,
-        Err(err) => return err,
+async fn handle_flow_config_save(bridge: &III, input: Value, timeout: Duration) -> Value {
+    let mut params = match extract(&input, FLOW_CONFIG_SAVE_PARAMS) {
+        Ok(params) => params,
+        Err(response) => return response,
     };
-
-    let config = body.get("config").cloned().unwrap_or(json!({}));
-    let data = json!({ "id": flow_id, "config": config });
+    let flow_id = params.string("flow_id");
+    let data = json!({ "id": flow_id, "config": params.take("config") });
 
     let state_input = json!({
         "scope": FLOW_CONFIG_GROUP,
@@ -752,7 +1451,7 @@ async fn handle_flow_config_save(bridge: &III, input: Value) -> Value {
     });
 
     match bridge
-        .call_with_timeout("state::set", state_input, Duration::from_secs(5))
+        .call_with_timeout("state::set", state_input, timeout)
         .await
     {
         Ok(_) => success_response(json!({ "message": "Flow config saved successfully" })),
@@ -760,148 +1459,665 @@ async fn handle_flow_config_save(bridge: &III, input: Value) -> Value {
     }
 }
 
-pub fn register_functions(bridge: &III) {
-    let b = bridge.clone();
-    bridge.register_function("engine::console::health", move |_input| {
-        let bridge = b.clone();
-        async move { Ok(handle_health(&bridge).await) }
-    });
+/// Pagination shared by the audit log and invocation history lists.
+const PAGE_PARAMS: [Param; 2] = [
+    Param::integer("offset").default_integer(0).min(0),
+    Param::integer("limit").default_integer(50).range(1, 500),
+];
+
+const AUDIT_LIST_PARAMS: &[Param] = &[
+    Param::string("action"),
+    Param::string("engine"),
+    Param::string("user"),
+    Param::string("outcome"),
+    Param::string("target"),
+    Param::integer("since").min(0),
+    Param::integer("until").min(0),
+    PAGE_PARAMS[0],
+    PAGE_PARAMS[1],
+];
+
+async fn handle_audit_list(audit: &AuditLog, input: Value) -> Value {
+    let params = match extract(&input, AUDIT_LIST_PARAMS) {
+        Ok(params) => params,
+        Err(response) => return response,
+    };
+    let filter = AuditFilter {
+        action: params.opt_string("action"),
+        engine: params.opt_string("engine"),
+        user: params.opt_string("user"),
+        outcome: params.opt_string("outcome"),
+        target: params.opt_string("target"),
+        since: params.u64("since"),
+        until: params.u64("until"),
+        offset: params.usize("offset"),
+        limit: params.usize("limit"),
+    };
 
-    let b = bridge.clone();
-    bridge.register_function("engine::console::workers", move |_input| {
-        let bridge = b.clone();
-        async move { Ok(handle_workers(&bridge).await) }
-    });
+    match audit.list(&filter).await {
+        Ok((records, total)) => success_response(json!({
+            "records": records,
+            "total": total,
+            "offset": filter.offset,
+            "limit": filter.limit
+        })),
+        Err(err) => error_response(err),
+    }
+}
 
-    let b = bridge.clone();
-    bridge.register_function("engine::console::functions", move |input| {
-        let bridge = b.clone();
-        async move { Ok(handle_functions_list(&bridge, input).await) }
-    });
+const HISTORY_LIST_PARAMS: &[Param] = &[
+    Param::string("function_id"),
+    Param::string("outcome"),
+    Param::integer("since").min(0),
+    Param::integer("until").min(0),
+    PAGE_PARAMS[0],
+    PAGE_PARAMS[1],
+];
+
+async fn handle_invocation_history_list(bridge: &III, input: Value) -> Value {
+    let params = match extract(&input, HISTORY_LIST_PARAMS) {
+        Ok(params) => params,
+        Err(response) => return response,
+    };
+    let filter = HistoryFilter {
+        function_id: params.opt_string("function_id"),
+        outcome: params.opt_string("outcome"),
+        since: params.u64("since"),
+        until: params.u64("until"),
+        offset: params.usize("offset"),
+        limit: params.usize("limit"),
+    };
 
-    let b = bridge.clone();
-    bridge.register_function("engine::console::triggers", move |input| {
-        let bridge = b.clone();
-        async move { Ok(handle_triggers_list(&bridge, input).await) }
-    });
+    match history::list(bridge, &filter).await {
+        Ok((records, total)) => success_response(json!({
+            "invocations": records,
+            "total": total,
+            "offset": filter.offset,
+            "limit": filter.limit
+        })),
+        Err(err) => error_response(err),
+    }
+}
 
-    let b = bridge.clone();
-    bridge.register_function("engine::console::status", move |_input| {
-        let bridge = b.clone();
-        async move { Ok(handle_status(&bridge).await) }
-    });
+async fn handle_invocation_history_delete(bridge: &III, input: Value) -> Value {
+    let invocation_id = match extract(&input, &[Param::string("invocation_id").required()]) {
+        Ok(params) => params.string("invocation_id"),
+        Err(response) => return response,
+    };
 
-    let b = bridge.clone();
-    bridge.register_function("engine::console::trigger_types", move |_input| {
-        let bridge = b.clone();
-        async move { Ok(handle_trigger_types(&bridge).await) }
-    });
+    match history::delete(bridge, &invocation_id).await {
+        Ok(true) => success_response(json!({ "message": "Invocation deleted successfully" })),
+        Ok(false) => ConsoleError::not_found(
+            "invocation_not_found",
+            format!("Invocation '{}' not found", invocation_id),
+        )
+        .into_response(),
+        Err(err) => error_response(err),
+    }
+}
 
-    let b = bridge.clone();
-    bridge.register_function("engine::console::alerts_list", move |_input| {
-        let bridge = b.clone();
-        async move { Ok(handle_alerts_list(&bridge).await) }
-    });
+const REPLAY_PARAMS: &[Param] = &[
+    Param::string("invocation_id").required(),
+    Param::json("input").sources(JSON_SOURCES),
+    Param::bool("async"),
+    Param::bool("skip_validation"),
+    TIMEOUT_MS_PARAM,
+];
+
+/// Call a recorded invocation's function again, with its original input
+/// unless the request carries an edited `input`.
+async fn handle_invocation_replay(
+    bridge: &III,
+    services: &Services,
+    engine: &str,
+    input: Value,
+    timeout: Duration,
+) -> Value {
+    let mut params = match extract(&input, REPLAY_PARAMS) {
+        Ok(params) => params,
+        Err(response) => return response,
+    };
+    let invocation_id = params.string("invocation_id");
+
+    let record = match history::get(bridge, &invocation_id).await {
+        Ok(Some(record)) => record,
+        Ok(None) => {
+            return ConsoleError::not_found(
+                "invocation_not_found",
+                format!("Invocation '{}' not found", invocation_id),
+            )
+            .into_response()
+        }
+        Err(err) => return error_response(err),
+    };
 
-    let b = bridge.clone();
-    bridge.register_function("engine::console::sampling_rules", move |_input| {
-        let bridge = b.clone();
-        async move { Ok(handle_sampling_rules(&bridge).await) }
-    });
+    let data = params.take("input").unwrap_or(record.input);
+
+    let background = params.bool("async");
+    run_invocation(Invocation {
+        bridge,
+        services,
+        engine: engine.to_string(),
+        function_id: record.function_id,
+        data,
+        background,
+        validate: !params.bool("skip_validation"),
+        timeout: invocation_timeout(services, &params, background, timeout),
+        replay_of: Some(record.id),
+    })
+    .await
+}
 
-    let b = bridge.clone();
-    bridge.register_function("engine::console::otel_logs_list", move |input| {
-        let bridge = b.clone();
-        async move { Ok(handle_otel_logs_list(&bridge, input).await) }
-    });
+const PRESET_FUNCTION_PARAM: Param = Param::string("function_id").required();
+const PRESET_NAME_PARAM: Param = Param::string("preset")
+    .required()
+    .check(presets::validate_name);
 
-    let b = bridge.clone();
-    bridge.register_function("engine::console::otel_logs_clear", move |_input| {
-        let bridge = b.clone();
-        async move { Ok(handle_otel_logs_clear(&bridge).await) }
-    });
+async fn handle_invoke_presets_list(bridge: &III, input: Value, timeout: Duration) -> Value {
+    let function_id = match extract(&input, &[PRESET_FUNCTION_PARAM]) {
+        Ok(params) => params.string("function_id"),
+        Err(response) => return response,
+    };
 
-    let b = bridge.clone();
-    bridge.register_function("engine::console::otel_traces_list", move |input| {
-        let bridge = b.clone();
-        async move { Ok(handle_otel_traces_list(&bridge, input).await) }
-    });
+    match presets::list(bridge, &function_id, timeout).await {
+        Ok(presets) => success_response(json!({
+            "function_id": function_id,
+            "presets": presets
+        })),
+        Err(err) => error_response(err),
+    }
+}
 
-    let b = bridge.clone();
-    bridge.register_function("engine::console::otel_traces_clear", move |_input| {
-        let bridge = b.clone();
-        async move { Ok(handle_otel_traces_clear(&bridge).await) }
-    });
+const PRESET_SAVE_PARAMS: &[Param] = &[
+    PRESET_FUNCTION_PARAM,
+    PRESET_NAME_PARAM,
+    Param::json("input").default_object().sources(JSON_SOURCES),
+    Param::string("description"),
+];
+
+async fn handle_invoke_preset_save(bridge: &III, input: Value, timeout: Duration) -> Value {
+    let mut params = match extract(&input, PRESET_SAVE_PARAMS) {
+        Ok(params) => params,
+        Err(response) => return response,
+    };
 
-    let b = bridge.clone();
-    bridge.register_function("engine::console::otel_traces_tree", move |input| {
-        let bridge = b.clone();
-        async move { Ok(handle_otel_traces_tree(&bridge, input).await) }
-    });
+    let preset = Preset {
+        function_id: params.string("function_id"),
+        name: params.string("preset"),
+        input: params.take("input").unwrap_or_default(),
+        description: params.opt_string("description"),
+        updated_at: 0,
+    };
 
-    let b = bridge.clone();
-    bridge.register_function("engine::console::metrics_detailed", move |input| {
-        let bridge = b.clone();
-        async move { Ok(handle_metrics_detailed(&bridge, input).await) }
-    });
+    match presets::save(bridge, preset, timeout).await {
+        Ok(preset) => success_response(json!(preset)),
+        Err(err) => error_response(err),
+    }
+}
 
-    let b = bridge.clone();
-    bridge.register_function("engine::console::rollups_list", move |input| {
-        let bridge = b.clone();
-        async move { Ok(handle_rollups_list(&bridge, input).await) }
-    });
+async fn handle_invoke_preset_delete(bridge: &III, input: Value, timeout: Duration) -> Value {
+    let params = match extract(&input, &[PRESET_FUNCTION_PARAM, PRESET_NAME_PARAM]) {
+        Ok(params) => params,
+        Err(response) => return response,
+    };
+    let (function_id, name) = (params.string("function_id"), params.string("preset"));
 
-    let b = bridge.clone();
-    bridge.register_function("engine::console::state_groups_list", move |input| {
-        let bridge = b.clone();
-        async move { Ok(handle_state_groups_list(&bridge, input).await) }
-    });
+    match presets::delete(bridge, &function_id, &name, timeout).await {
+        Ok(true) => success_response(json!({ "message": "Preset deleted successfully" })),
+        Ok(false) => ConsoleError::not_found(
+            "preset_not_found",
+            format!("Preset '{}' not found for {}", name, function_id),
+        )
+        .into_response(),
+        Err(err) => error_response(err),
+    }
+}
 
-    let b = bridge.clone();
-    bridge.register_function("engine::console::state_group_items", move |input| {
-        let bridge = b.clone();
-        async move { Ok(handle_state_group_items(&bridge, input).await) }
-    });
+async fn handle_invoke_presets_export(bridge: &III, input: Value, timeout: Duration) -> Value {
+    let function_id = match extract(&input, &[Param::string("function_id")]) {
+        Ok(params) => params.opt_string("function_id"),
+        Err(response) => return response,
+    };
 
-    let b = bridge.clone();
-    bridge.register_function("engine::console::state_item_set", move |input| {
-        let bridge = b.clone();
-        async move { Ok(handle_state_item_set(&bridge, input).await) }
-    });
+    match presets::export(bridge, function_id.as_deref(), timeout).await {
+        Ok(presets) => success_response(json!({
+            "version": presets::EXPORT_VERSION,
+            "exported_at": now_ms(),
+            "presets": presets
+        })),
+        Err(err) => error_response(err),
+    }
+}
 
-    let b = bridge.clone();
-    bridge.register_function("engine::console::state_item_delete", move |input| {
-        let bridge = b.clone();
-        async move { Ok(handle_state_item_delete(&bridge, input).await) }
-    });
+/// Import presets from an export file, or a bare array of presets.
+async fn handle_invoke_presets_import(bridge: &III, input: Value, timeout: Duration) -> Value {
+    let body = input.get("body").unwrap_or(&input);
+    let entries = match body {
+        Value::Array(_) => body.clone(),
+        _ => body.get("presets").cloned().unwrap_or(Value::Null),
+    };
+    let imported: Vec<Preset> = match serde_json::from_value(entries) {
+        Ok(presets) => presets,
+        Err(e) => return client_error_response(400, format!("Invalid preset file: {}", e)),
+    };
 
-    let b = bridge.clone();
-    bridge.register_function("engine::console::streams_list", move |_input| {
-        let bridge = b.clone();
-        async move { Ok(handle_streams_list(&bridge).await) }
-    });
+    let mut seen = HashSet::new();
+    for preset in &imported {
+        if preset.function_id.is_empty() {
+            return client_error_response(400, "Every preset needs a function_id");
+        }
+        if let Err(reason) = presets::validate_name(&preset.name) {
+            return ConsoleError::invalid_parameter("preset", reason).into_response();
+        }
+        if !seen.insert((&preset.function_id, &preset.name)) {
+            return ConsoleError::invalid_parameter(
+                "preset",
+                format!(
+                    "'{}' appears more than once for {}",
+                    preset.name, preset.function_id
+                ),
+            )
+            .into_response();
+        }
+    }
 
-    let b = bridge.clone();
-    bridge.register_function("engine::console::flow_config_get", move |input| {
-        let bridge = b.clone();
-        async move { Ok(handle_flow_config_get(&bridge, input).await) }
-    });
+    let overwrite = match extract(&input, &[Param::bool("overwrite").default_bool(true)]) {
+        Ok(params) => params.bool("overwrite"),
+        Err(response) => return response,
+    };
 
-    let b = bridge.clone();
-    bridge.register_function("engine::console::flow_config_save", move |input| {
-        let bridge = b.clone();
-        async move { Ok(handle_flow_config_save(&bridge, input).await) }
-    });
+    match presets::import(bridge, imported, overwrite, timeout).await {
+        Ok(summary) => success_response(json!(summary)),
+        Err(err) => error_response(err),
+    }
+}
 
-    let b = bridge.clone();
-    bridge.register_function("engine::console::invoke", move |input| {
-        let bridge = b.clone();
-        async move { Ok(handle_invoke(&bridge, input).await) }
-    });
+/// The engine a console call acts on, as selected by the request, and the
+/// services its handler may use.
+struct Call {
+    principal: Principal,
+    /// Name of the selected engine profile
+    engine: String,
+    bridge: III,
+    services: Services,
+}
 
-    let b = bridge.clone();
-    bridge.register_function("engine::console::cron_trigger", move |input| {
-        let bridge = b.clone();
-        async move { Ok(handle_cron_trigger(&bridge, input).await) }
-    });
+/// Registers console functions on one engine's bridge, behind the shared auth
+/// and audit checks.
+struct Registrar<'a> {
+    engine: &'a str,
+    bridge: &'a III,
+    services: &'a Services,
+}
+
+impl Registrar<'_> {
+    /// Register a console function whose handler only runs for authenticated callers
+    /// whose role allows the function.
+    ///
+    /// The handler receives the engine selected by the request, or the engine
+    /// the request arrived on, and the timeout for its engine calls.
+    /// Mutating functions are recorded in the audit log, including calls the
+    /// role forbids.
+    fn add<F, Fut>(&self, function_id: &'static str, handler: F)
+    where
+        F: Fn(Call, Value, Duration) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Value> + Send + 'static,
+    {
+        let engine = self.engine.to_string();
+        let b = self.bridge.clone();
+        let services = self.services.clone();
+        let handler = Arc::new(handler);
+        self.bridge.register_function(function_id, move |input| {
+            let (engine, bridge) = (engine.clone(), b.clone());
+            let services = services.clone();
+            let handler = handler.clone();
+            async move {
+                let Some(principal) = services.auth.authenticate_input(&input) else {
+                    return Ok(unauthorized_response());
+                };
+                let (engine, bridge) = match services.engines.select(&input) {
+                    Ok(Some(selected)) => selected,
+                    Ok(None) => (engine, bridge),
+                    Err(message) => {
+                        return Ok(
+                            ConsoleError::not_found("engine_not_found", message).into_response()
+                        )
+                    }
+                };
+                let pending = audit::is_audited(function_id).then(|| {
+                    services
+                        .audit
+                        .begin(function_id, &engine, &principal, &input)
+                });
+
+                let response = if services.auth.allows(&principal, function_id) {
+                    let timeout = services.timeouts.for_function(function_id);
+                    let call = Call {
+                        principal,
+                        engine,
+                        bridge,
+                        services: services.clone(),
+                    };
+                    handler(call, input, timeout).await
+                } else {
+                    tracing::warn!(
+                        user = %principal.name,
+                        role = %principal.role,
+                        function_id,
+                        "Rejected console call not allowed for role"
+                    );
+                    forbidden_response(function_id)
+                };
+
+                if let Some(pending) = pending {
+                    pending.finish(&response);
+                }
+                Ok(response)
+            }
+        });
+    }
+}
+
+/// Register the console functions on the bridge of the named engine.
+pub fn register_functions(engine: &str, bridge: &III, services: &Services) {
+    let r = Registrar {
+        engine,
+        bridge,
+        services,
+    };
+
+    r.add(
+        "engine::console::health",
+        |call, _input, timeout| async move { handle_health(&call.bridge, timeout).await },
+    );
+    r.add(
+        "engine::console::workers",
+        |call, _input, timeout| async move { handle_workers(&call.bridge, timeout).await },
+    );
+    r.add(
+        "engine::console::functions",
+        |call, input, timeout| async move {
+            handle_functions_list(&call.bridge, input, timeout).await
+        },
+    );
+    r.add(
+        "engine::console::triggers",
+        |call, input, timeout| async move { handle_triggers_list(&call.bridge, input, timeout).await },
+    );
+    r.add(
+        "engine::console::status",
+        |call, _input, timeout| async move { handle_status(&call.bridge, timeout).await },
+    );
+    r.add(
+        "engine::console::trigger_types",
+        |call, _input, timeout| async move { handle_trigger_types(&call.bridge, timeout).await },
+    );
+    r.add(
+        "engine::console::alerts_list",
+        |call, _input, timeout| async move { handle_alerts_list(&call.bridge, timeout).await },
+    );
+    r.add(
+        "engine::console::sampling_rules",
+        |call, _input, timeout| async move { handle_sampling_rules(&call.bridge, timeout).await },
+    );
+    r.add(
+        "engine::console::otel_logs_list",
+        |call, input, timeout| async move { handle_otel_logs_list(&call.bridge, input, timeout).await },
+    );
+    r.add(
+        "engine::console::otel_logs_clear",
+        |call, _input, timeout| async move { handle_otel_logs_clear(&call.bridge, timeout).await },
+    );
+    r.add(
+        "engine::console::otel_traces_list",
+        |call, input, timeout| async move {
+            handle_otel_traces_list(&call.bridge, input, timeout).await
+        },
+    );
+    r.add(
+        "engine::console::otel_traces_clear",
+        |call, _input, timeout| async move { handle_otel_traces_clear(&call.bridge, timeout).await },
+    );
+    r.add(
+        "engine::console::otel_traces_tree",
+        |call, input, timeout| async move {
+            handle_otel_traces_tree(&call.bridge, input, timeout).await
+        },
+    );
+    r.add(
+        "engine::console::metrics_detailed",
+        |call, input, timeout| async move {
+            handle_metrics_detailed(&call.bridge, input, timeout).await
+        },
+    );
+    r.add(
+        "engine::console::rollups_list",
+        |call, input, timeout| async move { handle_rollups_list(&call.bridge, input, timeout).await },
+    );
+    r.add(
+        "engine::console::state_groups_list",
+        |call, input, timeout| async move { handle_state_groups_list(&call, input, timeout).await },
+    );
+    r.add(
+        "engine::console::state_group_items",
+        |call, input, timeout| async move { handle_state_group_items(&call, input, timeout).await },
+    );
+    r.add(
+        "engine::console::state_item_set",
+        |call, input, timeout| async move { handle_state_item_set(&call, input, timeout).await },
+    );
+    r.add(
+        "engine::console::state_item_delete",
+        |call, input, timeout| async move { handle_state_item_delete(&call, input, timeout).await },
+    );
+    r.add(
+        "engine::console::state_items_set",
+        |call, input, timeout| async move { handle_state_items_set(&call, input, timeout).await },
+    );
+    r.add(
+        "engine::console::state_items_delete",
+        |call, input, timeout| async move { handle_state_items_delete(&call, input, timeout).await },
+    );
+    r.add(
+        "engine::console::state_items_delete_matching",
+        |call, input, timeout| async move {
+            handle_state_items_delete_matching(&call, input, timeout).await
+        },
+    );
+    r.add(
+        "engine::console::state_group_clear",
+        |call, input, timeout| async move { handle_state_group_clear(&call, input, timeout).await },
+    );
+    r.add(
+        "engine::console::state_export",
+        |call, input, timeout| async move { handle_state_export(&call, input, timeout).await },
+    );
+    r.add(
+        "engine::console::state_import",
+        |call, input, timeout| async move { handle_state_import(&call, input, timeout).await },
+    );
+    r.add(
+        "engine::console::streams_list",
+        |call, _input, timeout| async move { handle_streams_list(&call.bridge, timeout).await },
+    );
+    r.add(
+        "engine::console::flow_config_get",
+        |call, input, timeout| async move { handle_flow_config_get(&call.bridge, input, timeout).await },
+    );
+    r.add(
+        "engine::console::flow_config_save",
+        |call, input, timeout| async move {
+            handle_flow_config_save(&call.bridge, input, timeout).await
+        },
+    );
+    r.add(
+        "engine::console::invoke",
+        |call, input, timeout| async move {
+            handle_invoke(&call.bridge, &call.services, &call.engine, input, timeout).await
+        },
+    );
+    r.add(
+        "engine::console::cron_trigger",
+        |call, input, timeout| async move {
+            handle_cron_trigger(&call.bridge, &call.services, input, timeout).await
+        },
+    );
+
+    r.add(
+        "engine::console::audit_list",
+        |call, input, _timeout| async move { handle_audit_list(&call.services.audit, input).await },
+    );
+
+    r.add(
+        "engine::console::invocation_history_list",
+        |call, input, _timeout| async move {
+            handle_invocation_history_list(&call.bridge, input).await
+        },
+    );
+    r.add(
+        "engine::console::invocation_history_delete",
+        |call, input, _timeout| async move {
+            handle_invocation_history_delete(&call.bridge, input).await
+        },
+    );
+    r.add(
+        "engine::console::invocation_replay",
+        |call, input, timeout| async move {
+            handle_invocation_replay(&call.bridge, &call.services, &call.engine, input, timeout)
+                .await
+        },
+    );
+
+    r.add(
+        "engine::console::function_example",
+        |call, input, timeout| async move { handle_function_example(&call, input, timeout).await },
+    );
+    r.add(
+        "engine::console::invoke_presets_list",
+        |call, input, timeout| async move {
+            handle_invoke_presets_list(&call.bridge, input, timeout).await
+        },
+    );
+    r.add(
+        "engine::console::invoke_preset_save",
+        |call, input, timeout| async move {
+            handle_invoke_preset_save(&call.bridge, input, timeout).await
+        },
+    );
+    r.add(
+        "engine::console::invoke_preset_delete",
+        |call, input, timeout| async move {
+            handle_invoke_preset_delete(&call.bridge, input, timeout).await
+        },
+    );
+    r.add(
+        "engine::console::invoke_presets_export",
+        |call, input, timeout| async move {
+            handle_invoke_presets_export(&call.bridge, input, timeout).await
+        },
+    );
+    r.add(
+        "engine::console::invoke_presets_import",
+        |call, input, timeout| async move {
+            handle_invoke_presets_import(&call.bridge, input, timeout).await
+        },
+    );
+
+    r.add(
+        "engine::console::invocation_status",
+        |call, input, _timeout| async move { handle_invocation_status(&call.services.jobs, input) },
+    );
+    r.add(
+        "engine::console::invocation_cancel",
+        |call, input, _timeout| async move { handle_invocation_cancel(&call.services.jobs, input) },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expected(input: Value) -> Result<Option<state::Expected>, Value> {
+        let params = Params::extract(&input, &[EXPECTED_HASH_PARAM]).unwrap();
+        parse_expected(&input, &params)
+    }
+
+    #[test]
+    fn expected_null_is_a_value() {
+        let expected = expected(json!({ "body": { "value": 1, "expected": null } }))
+            .unwrap()
+            .unwrap();
+        let conflict = check_current(&expected, "users", "42", json!({ "name": "Ada" }));
+        assert_eq!(conflict.unwrap_err()["status_code"], 409);
+        assert!(check_current(&expected, "users", "42", Value::Null).is_ok());
+    }
+
+    #[test]
+    fn expected_empty_string_is_a_value() {
+        let expected = expected(json!({ "expected": "" })).unwrap().unwrap();
+        assert!(check_current(&expected, "g", "k", json!("")).is_ok());
+        let response = check_current(&expected, "g", "k", json!("x")).unwrap_err();
+        assert_eq!(response["body"]["error"]["details"]["current"], "x");
+    }
+
+    #[test]
+    fn expected_is_optional_and_exclusive() {
+        assert!(expected(json!({ "body": { "value": 1 } }))
+            .unwrap()
+            .is_none());
+
+        let hash = state::value_hash(&json!(1));
+        let both = expected(json!({ "expected": 1, "expected_hash": hash }));
+        assert_eq!(both.unwrap_err()["status_code"], 400);
+
+        let by_hash = expected(json!({ "expected_hash": hash })).unwrap().unwrap();
+        assert!(check_current(&by_hash, "g", "k", json!(1)).is_ok());
+    }
+
+    #[test]
+    fn bulk_items_need_unique_keys_and_values() {
+        let items = parse_items(Some(json!([
+            { "key": "a", "value": 1 },
+            { "key": 2, "value": null }
+        ])))
+        .unwrap();
+        assert_eq!(
+            items,
+            [("a".to_string(), json!(1)), ("2".to_string(), Value::Null)]
+        );
+
+        let error = |value: Value| parse_items(Some(value)).unwrap_err();
+        assert_eq!(error(json!([])), "must not be empty");
+        assert_eq!(error(json!({ "a": 1 })), "must be an array");
+        assert_eq!(error(json!([{ "value": 1 }])), "entry 0 needs a key");
+        assert_eq!(
+            error(json!([{ "key": "" , "value": 1 }])),
+            "entry 0 needs a key"
+        );
+        assert_eq!(
+            error(json!([{ "key": "a", "value": 1 }, { "key": "b" }])),
+            "entry 1 needs a value"
+        );
+        assert_eq!(
+            error(json!([{ "key": "a", "value": 1 }, { "key": "a", "value": 2 }])),
+            "key 'a' appears more than once"
+        );
+        let too_many: Vec<Value> = (0..=MAX_BULK_ITEMS)
+            .map(|i| json!({ "key": i, "value": i }))
+            .collect();
+        assert_eq!(error(json!(too_many)), "must hold at most 1000 entries");
+    }
+
+    #[test]
+    fn bulk_keys_drop_repeats() {
+        assert_eq!(
+            parse_keys(Some(json!(["a", 1, "a"]))).unwrap(),
+            ["a".to_string(), "1".to_string()]
+        );
+        assert_eq!(
+            parse_keys(Some(json!(["a", true]))).unwrap_err(),
+            "entry 1 is not a string or number"
+        );
+        assert_eq!(parse_keys(None).unwrap_err(), "must be an array");
+    }
 }
@@ -0,0 +1,207 @@
+use iii_sdk::{IIIError, III};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use crate::bridge::state;
+
+/// State group ID used to persist console invocation history.
+pub const HISTORY_GROUP: &str = "__console.invocations";
+
+/// Timeout for history reads and writes, which only touch the state module.
+const HISTORY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Records kept per engine; the oldest are deleted as new ones arrive.
+const MAX_RECORDS: usize = 1000;
+
+/// Records written to an engine between two trims of its history.
+const TRIM_INTERVAL: usize = 50;
+
+/// Records written per engine since the console started.
+static WRITES: LazyLock<Mutex<HashMap<String, usize>>> = LazyLock::new(Default::default);
+
+/// One call made through `engine::console::invoke`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvocationRecord {
+    pub id: String,
+    /// Unix timestamp in milliseconds of when the call started
+    pub timestamp: u64,
+    pub function_id: String,
+    #[serde(default)]
+    pub engine: String,
+    pub input: Value,
+    pub result: Option<Value>,
+    pub error: Option<String>,
+    /// `success` or `error`
+    pub outcome: String,
+    pub duration_ms: u64,
+    /// Async job the call ran as, if any
+    pub job_id: Option<String>,
+    /// History record this call replayed, if any
+    pub replay_of: Option<String>,
+}
+
+impl InvocationRecord {
+    /// Describe a finished call. `started_at` is a Unix timestamp in milliseconds.
+    pub fn new(
+        function_id: &str,
+        engine: &str,
+        input: Value,
+        result: &Result<Value, IIIError>,
+        started_at: u64,
+        duration: Duration,
+    ) -> Self {
+        let (result, error) = match result {
+            Ok(value) => (Some(value.clone()), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+        Self {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            timestamp: started_at,
+            function_id: function_id.to_string(),
+            engine: engine.to_string(),
+            input,
+            outcome: if error.is_none() { "success" } else { "error" }.to_string(),
+            result,
+            error,
+            duration_ms: duration.as_millis() as u64,
+            job_id: None,
+            replay_of: None,
+        }
+    }
+}
+
+/// Filters and pagination accepted by `engine::console::invocation_history_list`.
+#[derive(Debug, Default)]
+pub struct HistoryFilter {
+    pub function_id: Option<String>,
+    pub outcome: Option<String>,
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl HistoryFilter {
+    fn matches(&self, record: &InvocationRecord) -> bool {
+        self.function_id
+            .as_ref()
+            .is_none_or(|f| record.function_id.contains(f.as_str()))
+            && self.outcome.as_ref().is_none_or(|o| &record.outcome == o)
+            && self.since.is_none_or(|s| record.timestamp >= s)
+            && self.until.is_none_or(|u| record.timestamp <= u)
+    }
+}
+
+/// Store a record in the engine's state under its ID, logging instead of
+/// failing the call. Every few writes the oldest records beyond
+/// [`MAX_RECORDS`] are deleted.
+pub fn record(bridge: &III, record: InvocationRecord) {
+    let bridge = bridge.clone();
+    tokio::spawn(async move {
+        let state_input = json!({
+            "scope": HISTORY_GROUP,
+            "key": record.id,
+            "value": record
+        });
+        if let Err(e) = bridge
+            .call_with_timeout("state::set", state_input, HISTORY_TIMEOUT)
+            .await
+        {
+            tracing::warn!(function_id = %record.function_id, "Failed to record invocation: {}", e);
+            return;
+        }
+
+        let writes = {
+            let mut writes = WRITES.lock().unwrap();
+            let count = writes.entry(record.engine.clone()).or_default();
+            *count += 1;
+            *count
+        };
+        // The first write trims what earlier runs left behind
+        if writes % TRIM_INTERVAL == 1 {
+            match state::trim(
+                &bridge,
+                HISTORY_GROUP,
+                "timestamp",
+                MAX_RECORDS,
+                HISTORY_TIMEOUT,
+            )
+            .await
+            {
+                Ok(0) => {}
+                Ok(deleted) => tracing::debug!(deleted, "Trimmed invocation history"),
+                Err(e) => tracing::warn!("Failed to trim invocation history: {}", e),
+            }
+        }
+    });
+}
+
+async fn read_all(bridge: &III) -> Result<Vec<InvocationRecord>, IIIError> {
+    let data = bridge
+        .call_with_timeout(
+            "state::list",
+            json!({ "scope": HISTORY_GROUP }),
+            HISTORY_TIMEOUT,
+        )
+        .await?;
+    Ok(data
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| {
+                    let record = item.get("value").unwrap_or(item);
+                    serde_json::from_value(record.clone()).ok()
+                })
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// List records, newest first, returning the page and the total number of matches.
+pub async fn list(
+    bridge: &III,
+    filter: &HistoryFilter,
+) -> Result<(Vec<InvocationRecord>, usize), IIIError> {
+    let mut records: Vec<InvocationRecord> = read_all(bridge)
+        .await?
+        .into_iter()
+        .filter(|r| filter.matches(r))
+        .collect();
+    records.sort_by_key(|r| std::cmp::Reverse(r.timestamp));
+
+    let total = records.len();
+    let page = records
+        .into_iter()
+        .skip(filter.offset)
+        .take(filter.limit)
+        .collect();
+    Ok((page, total))
+}
+
+/// Find a record by its ID.
+pub async fn get(bridge: &III, id: &str) -> Result<Option<InvocationRecord>, IIIError> {
+    let value = state::get(bridge, HISTORY_GROUP, id, HISTORY_TIMEOUT).await?;
+    Ok(serde_json::from_value(value).ok())
+}
+
+/// Delete a record by its ID, returning whether it existed.
+pub async fn delete(bridge: &III, id: &str) -> Result<bool, IIIError> {
+    if state::get(bridge, HISTORY_GROUP, id, HISTORY_TIMEOUT)
+        .await?
+        .is_null()
+    {
+        return Ok(false);
+    }
+    bridge
+        .call_with_timeout(
+            "state::delete",
+            json!({ "scope": HISTORY_GROUP, "key": id }),
+            HISTORY_TIMEOUT,
+        )
+        .await?;
+    Ok(true)
+}
@@ -0,0 +1,265 @@
+use iii_sdk::{IIIError, III};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::AbortHandle;
+
+use crate::bridge::history::{self, InvocationRecord};
+use crate::util::now_ms;
+
+/// Maximum number of async invocations kept in memory. The oldest finished
+/// jobs are evicted first; when every slot holds a running job, new async
+/// invocations are rejected.
+pub const MAX_JOBS: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+/// An async invocation started through `engine::console::invoke`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Job {
+    pub id: String,
+    pub function_id: String,
+    pub engine: String,
+    pub status: JobStatus,
+    /// Unix timestamp in milliseconds
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+    /// Time spent so far for running jobs, total time for finished ones
+    pub duration_ms: u64,
+    pub timeout_ms: u64,
+    pub result: Option<Value>,
+    pub error: Option<String>,
+    /// Trace the invocation was started in, when tracing is enabled
+    pub trace_id: Option<String>,
+}
+
+/// What a running job needs to record itself in the history when cancelled.
+struct JobCall {
+    bridge: III,
+    input: Value,
+    replay_of: Option<String>,
+}
+
+struct JobEntry {
+    job: Job,
+    started: Instant,
+    handle: Option<AbortHandle>,
+    /// Set while the job runs
+    call: Option<JobCall>,
+}
+
+impl JobEntry {
+    fn snapshot(&self) -> Job {
+        let mut job = self.job.clone();
+        if job.status == JobStatus::Running {
+            job.duration_ms = self.started.elapsed().as_millis() as u64;
+        }
+        job
+    }
+
+    fn finish(&mut self, status: JobStatus) {
+        self.job.status = status;
+        self.job.finished_at = Some(now_ms());
+        self.job.duration_ms = self.started.elapsed().as_millis() as u64;
+        self.handle = None;
+        self.call = None;
+    }
+}
+
+#[derive(Default)]
+struct JobTable {
+    jobs: HashMap<String, JobEntry>,
+    /// Job IDs in start order, oldest first
+    order: VecDeque<String>,
+}
+
+impl JobTable {
+    /// Make room for one more job, evicting the oldest finished one if needed.
+    fn reserve(&mut self) -> bool {
+        if self.jobs.len() < MAX_JOBS {
+            return true;
+        }
+        let evict = self.order.iter().position(|id| {
+            self.jobs
+                .get(id)
+                .is_some_and(|entry| entry.job.status != JobStatus::Running)
+        });
+        match evict.and_then(|index| self.order.remove(index)) {
+            Some(id) => {
+                self.jobs.remove(&id);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Outcome of a cancellation request.
+pub enum Cancel {
+    Cancelled(Job),
+    AlreadyFinished(Job),
+    NotFound,
+}
+
+/// In-memory registry of async invocations, shared by every engine.
+#[derive(Clone, Default)]
+pub struct Jobs {
+    table: Arc<Mutex<JobTable>>,
+}
+
+impl Jobs {
+    /// Start calling `function_id` in the background and return the new job.
+    /// The finished or cancelled call is recorded in the invocation history.
+    ///
+    /// Fails when the registry is full of running jobs.
+    pub fn start(
+        &self,
+        engine: &str,
+        bridge: III,
+        function_id: String,
+        data: Value,
+        timeout: Duration,
+        replay_of: Option<String>,
+    ) -> Result<Job, String> {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        // Captured here, where the incoming request's trace context is active
+        let trace_id = iii_sdk::current_trace_id();
+        let traceparent = iii_sdk::inject_traceparent();
+
+        let mut table = self.table.lock().unwrap();
+        if !table.reserve() {
+            return Err(format!("Too many running invocations (limit {})", MAX_JOBS));
+        }
+
+        let job = Job {
+            id: id.clone(),
+            function_id: function_id.clone(),
+            engine: engine.to_string(),
+            status: JobStatus::Running,
+            started_at: now_ms(),
+            finished_at: None,
+            duration_ms: 0,
+            timeout_ms: timeout.as_millis() as u64,
+            result: None,
+            error: None,
+            trace_id,
+        };
+
+        let call = JobCall {
+            bridge: bridge.clone(),
+            input: data.clone(),
+            replay_of: replay_of.clone(),
+        };
+        let jobs = self.clone();
+        let job_id = id.clone();
+        let engine = engine.to_string();
+        let started_at = job.started_at;
+        let task = tokio::spawn(async move {
+            let started = Instant::now();
+            let span_name = format!("console invoke {}", function_id);
+            let call_data = data.clone();
+            let result = iii_sdk::with_span(&span_name, traceparent.as_deref(), None, || async {
+                Ok(bridge
+                    .call_with_timeout(&function_id, call_data, timeout)
+                    .await)
+            })
+            .await
+            .unwrap_or_else(|e| Err(IIIError::Handler(e.to_string())));
+
+            let mut record = InvocationRecord::new(
+                &function_id,
+                &engine,
+                data,
+                &result,
+                started_at,
+                started.elapsed(),
+            );
+            record.job_id = Some(job_id.clone());
+            record.replay_of = replay_of;
+            // A cancelled job was recorded when it was cancelled
+            if jobs.complete(&job_id, result) {
+                history::record(&bridge, record);
+            }
+        });
+
+        table.jobs.insert(
+            id.clone(),
+            JobEntry {
+                job: job.clone(),
+                started: Instant::now(),
+                handle: Some(task.abort_handle()),
+                call: Some(call),
+            },
+        );
+        table.order.push_back(id);
+        Ok(job)
+    }
+
+    /// Store the result of a job, returning false if it was no longer running.
+    fn complete(&self, id: &str, result: Result<Value, IIIError>) -> bool {
+        let mut table = self.table.lock().unwrap();
+        let Some(entry) = table.jobs.get_mut(id) else {
+            return false;
+        };
+        if entry.job.status != JobStatus::Running {
+            return false;
+        }
+        match result {
+            Ok(value) => {
+                entry.job.result = Some(value);
+                entry.finish(JobStatus::Succeeded);
+            }
+            Err(err) => {
+                entry.job.error = Some(err.to_string());
+                entry.finish(JobStatus::Failed);
+            }
+        }
+        true
+    }
+
+    pub fn get(&self, id: &str) -> Option<Job> {
+        let table = self.table.lock().unwrap();
+        table.jobs.get(id).map(JobEntry::snapshot)
+    }
+
+    /// Stop waiting for a running job, recording it in the history with the
+    /// error `cancelled`. The engine may still finish running the function,
+    /// but its result is discarded.
+    pub fn cancel(&self, id: &str) -> Cancel {
+        let mut table = self.table.lock().unwrap();
+        let Some(entry) = table.jobs.get_mut(id) else {
+            return Cancel::NotFound;
+        };
+        if entry.job.status != JobStatus::Running {
+            return Cancel::AlreadyFinished(entry.snapshot());
+        }
+        if let Some(handle) = entry.handle.take() {
+            handle.abort();
+        }
+        if let Some(call) = entry.call.take() {
+            let mut record = InvocationRecord::new(
+                &entry.job.function_id,
+                &entry.job.engine,
+                call.input,
+                &Err(IIIError::Handler("cancelled".to_string())),
+                entry.job.started_at,
+                entry.started.elapsed(),
+            );
+            record.error = Some("cancelled".to_string());
+            record.job_id = Some(entry.job.id.clone());
+            record.replay_of = call.replay_of;
+            history::record(&call.bridge, record);
+        }
+        entry.finish(JobStatus::Cancelled);
+        Cancel::Cancelled(entry.snapshot())
+    }
+}
@@ -1,6 +1,39 @@
+mod audit;
+mod engines;
 mod error;
 mod functions;
+mod history;
+mod jobs;
+mod params;
+mod presets;
+mod schema;
+mod state;
+mod timeouts;
 mod triggers;
 
+pub use audit::AuditLog;
+pub use engines::{EngineProfile, Engines, DEFAULT_ENGINE, ENGINE_HEADER};
+pub use error::ConsoleError;
 pub use functions::register_functions;
+pub use jobs::Jobs;
+pub use schema::Schemas;
+pub use state::{
+    check_readable, export_group, import, parse_export_text, to_document, to_ndjson, ConflictMode,
+    ImportError, StateStats,
+};
+pub use timeouts::{Timeouts, DEFAULT_MAX_TIMEOUT_MS, DEFAULT_TIMEOUT_MS};
 pub use triggers::register_triggers;
+
+use crate::auth::AuthState;
+
+/// Shared state available to every console function handler.
+#[derive(Clone)]
+pub struct Services {
+    pub auth: AuthState,
+    pub audit: AuditLog,
+    pub engines: Engines,
+    pub timeouts: Timeouts,
+    pub jobs: Jobs,
+    pub state_stats: StateStats,
+    pub schemas: Schemas,
+}
@@ -0,0 +1,459 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::bridge::error::ConsoleError;
+
+/// Where in a handler's input a parameter can come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// `path_params`, filled from trigger paths such as `/states/:group/item`
+    Path,
+    /// `query_params`, whose values are always strings
+    Query,
+    /// The request body, as sent by API triggers
+    Body,
+    /// The top-level input, used when a function is called directly
+    Top,
+}
+
+/// Sources checked, in order, unless a parameter declares its own.
+pub const DEFAULT_SOURCES: &[Source] = &[Source::Path, Source::Query, Source::Body, Source::Top];
+
+/// The type a parameter is coerced to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// A non-empty string; numbers and booleans are turned into strings
+    String,
+    /// A whole number, also accepted as a numeric string
+    Integer,
+    /// `true`/`false`, also accepted as `"true"`, `"false"`, `"1"` or `"0"`
+    Bool,
+    /// Any JSON value, taken as is
+    Json,
+}
+
+/// A custom check on a string value, returning why it is rejected.
+pub type Check = fn(&str) -> Result<(), String>;
+
+#[derive(Debug, Clone, Copy)]
+enum Fallback {
+    Bool(bool),
+    Integer(i64),
+    EmptyObject,
+}
+
+/// The declaration of one request parameter: its type, where it is read
+/// from, its default and its constraints.
+#[derive(Debug, Clone, Copy)]
+pub struct Param {
+    name: &'static str,
+    alias: Option<&'static str>,
+    kind: Kind,
+    required: bool,
+    default: Option<Fallback>,
+    sources: &'static [Source],
+    min: Option<i64>,
+    max: Option<i64>,
+    check: Option<Check>,
+}
+
+impl Param {
+    const fn new(name: &'static str, kind: Kind) -> Self {
+        Self {
+            name,
+            alias: None,
+            kind,
+            required: false,
+            default: None,
+            sources: DEFAULT_SOURCES,
+            min: None,
+            max: None,
+            check: None,
+        }
+    }
+
+    pub const fn string(name: &'static str) -> Self {
+        Self::new(name, Kind::String)
+    }
+
+    pub const fn integer(name: &'static str) -> Self {
+        Self::new(name, Kind::Integer)
+    }
+
+    pub const fn bool(name: &'static str) -> Self {
+        Self::new(name, Kind::Bool)
+    }
+
+    pub const fn json(name: &'static str) -> Self {
+        Self::new(name, Kind::Json)
+    }
+
+    pub const fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Another key the parameter is read from when `name` is absent from every source.
+    pub const fn alias(mut self, alias: &'static str) -> Self {
+        self.alias = Some(alias);
+        self
+    }
+
+    /// Only read the parameter from these sources, in this order.
+    pub const fn sources(mut self, sources: &'static [Source]) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    pub const fn default_bool(mut self, value: bool) -> Self {
+        self.default = Some(Fallback::Bool(value));
+        self
+    }
+
+    pub const fn default_integer(mut self, value: i64) -> Self {
+        self.default = Some(Fallback::Integer(value));
+        self
+    }
+
+    /// Default a JSON parameter to `{}`.
+    pub const fn default_object(mut self) -> Self {
+        self.default = Some(Fallback::EmptyObject);
+        self
+    }
+
+    /// Inclusive bounds of an integer parameter.
+    pub const fn range(mut self, min: i64, max: i64) -> Self {
+        self.min = Some(min);
+        self.max = Some(max);
+        self
+    }
+
+    pub const fn min(mut self, min: i64) -> Self {
+        self.min = Some(min);
+        self
+    }
+
+    /// An extra check on a string parameter, returning why a value is rejected.
+    pub const fn check(mut self, check: Check) -> Self {
+        self.check = Some(check);
+        self
+    }
+
+    /// The first value found for the parameter. Nulls and empty strings count
+    /// as absent, except for JSON parameters, where they are values like any other.
+    fn lookup<'a>(&self, input: &'a Value) -> Option<&'a Value> {
+        std::iter::once(self.name)
+            .chain(self.alias)
+            .flat_map(|key| self.sources.iter().map(move |source| (key, source)))
+            .find_map(|(key, source)| {
+                let container = match source {
+                    Source::Path => input.get("path_params")?,
+                    Source::Query => input.get("query_params")?,
+                    Source::Body => input.get("body")?,
+                    Source::Top => input,
+                };
+                match (self.kind, container.get(key)?) {
+                    (Kind::Json, value) => Some(value),
+                    (_, Value::Null) => None,
+                    (_, Value::String(s)) if s.is_empty() => None,
+                    (_, value) => Some(value),
+                }
+            })
+    }
+
+    fn coerce(&self, value: &Value) -> Result<Value, String> {
+        let value = match (self.kind, value) {
+            (Kind::Json, value) => return Ok(value.clone()),
+            (Kind::String, Value::String(_)) => value.clone(),
+            (Kind::String, Value::Number(n)) => Value::String(n.to_string()),
+            (Kind::String, Value::Bool(b)) => Value::String(b.to_string()),
+            (Kind::String, _) => return Err("must be a string".to_string()),
+            (Kind::Integer, Value::Number(n)) => match n.as_i64() {
+                Some(n) => Value::from(n),
+                None => return Err("must be an integer".to_string()),
+            },
+            (Kind::Integer, Value::String(s)) => match s.trim().parse::<i64>() {
+                Ok(n) => Value::from(n),
+                Err(_) => return Err("must be an integer".to_string()),
+            },
+            (Kind::Integer, _) => return Err("must be an integer".to_string()),
+            (Kind::Bool, Value::Bool(_)) => value.clone(),
+            (Kind::Bool, Value::String(s)) => match s.to_ascii_lowercase().as_str() {
+                "true" | "1" => Value::Bool(true),
+                "false" | "0" => Value::Bool(false),
+                _ => return Err("must be true or false".to_string()),
+            },
+            (Kind::Bool, Value::Number(n)) => match n.as_u64() {
+                Some(1) => Value::Bool(true),
+                Some(0) => Value::Bool(false),
+                _ => return Err("must be true or false".to_string()),
+            },
+            (Kind::Bool, _) => return Err("must be true or false".to_string()),
+        };
+
+        if let Some(n) = value.as_i64() {
+            match (self.min, self.max) {
+                (Some(min), Some(max)) if n < min || n > max => {
+                    return Err(format!("must be between {} and {}", min, max))
+                }
+                (Some(min), None) if n < min => return Err(format!("must be at least {}", min)),
+                _ => {}
+            }
+        }
+        if let (Some(check), Some(s)) = (self.check, value.as_str()) {
+            check(s)?;
+        }
+        Ok(value)
+    }
+}
+
+/// Why one parameter was rejected.
+#[derive(Debug, Clone, Serialize)]
+pub struct ParamError {
+    pub name: String,
+    pub reason: String,
+    /// Whether the parameter was required and absent
+    #[serde(skip)]
+    pub missing: bool,
+}
+
+impl ParamError {
+    pub fn missing(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            reason: "is required".to_string(),
+            missing: true,
+        }
+    }
+
+    pub fn invalid(name: &str, reason: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            reason: reason.into(),
+            missing: false,
+        }
+    }
+}
+
+/// Parameters read from a handler's input according to their declarations.
+#[derive(Debug, Default)]
+pub struct Params {
+    values: Map<String, Value>,
+}
+
+impl Params {
+    /// Read and coerce every declared parameter, applying defaults.
+    ///
+    /// All missing and invalid parameters are reported together in the error.
+    pub fn extract(input: &Value, params: &[Param]) -> Result<Self, ConsoleError> {
+        let mut values = Map::new();
+        let mut errors = Vec::new();
+
+        for param in params {
+            match param.lookup(input) {
+                Some(value) => match param.coerce(value) {
+                    Ok(value) => {
+                        values.insert(param.name.to_string(), value);
+                    }
+                    Err(reason) => errors.push(ParamError::invalid(param.name, reason)),
+                },
+                None if param.required => errors.push(ParamError::missing(param.name)),
+                None => {
+                    let default = match param.default {
+                        Some(Fallback::Bool(b)) => Value::Bool(b),
+                        Some(Fallback::Integer(n)) => Value::from(n),
+                        Some(Fallback::EmptyObject) => Value::Object(Map::new()),
+                        None => continue,
+                    };
+                    values.insert(param.name.to_string(), default);
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(Self { values })
+        } else {
+            Err(ConsoleError::invalid_parameters(errors))
+        }
+    }
+
+    pub fn str(&self, name: &str) -> Option<&str> {
+        self.values.get(name).and_then(|v| v.as_str())
+    }
+
+    /// A string parameter, empty if it was not provided.
+    pub fn string(&self, name: &str) -> String {
+        self.str(name).unwrap_or_default().to_string()
+    }
+
+    pub fn opt_string(&self, name: &str) -> Option<String> {
+        self.str(name).map(String::from)
+    }
+
+    /// A boolean parameter, false if it was not provided.
+    pub fn bool(&self, name: &str) -> bool {
+        self.values
+            .get(name)
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+    }
+
+    /// A non-negative integer parameter; declare it with a minimum of 0 or more.
+    pub fn u64(&self, name: &str) -> Option<u64> {
+        self.values.get(name).and_then(|v| v.as_u64())
+    }
+
+    pub fn usize(&self, name: &str) -> usize {
+        self.u64(name).unwrap_or_default() as usize
+    }
+
+    /// Take a JSON parameter out of the set.
+    pub fn take(&mut self, name: &str) -> Option<Value> {
+        self.values.remove(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn failed(input: Value, params: &[Param]) -> Vec<(String, String)> {
+        let error = Params::extract(&input, params).unwrap_err();
+        error.details.unwrap()["parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| {
+                let field = |f: &str| e[f].as_str().unwrap().to_string();
+                (field("name"), field("reason"))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sources_are_read_in_order() {
+        let input = json!({
+            "path_params": { "group": "from-path" },
+            "query_params": { "group": "from-query", "key": "from-query" },
+            "body": { "group": "from-body", "key": "from-body", "value": 1 },
+            "group": "from-top",
+            "value": 2
+        });
+        let params = Params::extract(
+            &input,
+            &[
+                Param::string("group"),
+                Param::string("key"),
+                Param::json("value").sources(&[Source::Top, Source::Body]),
+            ],
+        )
+        .unwrap();
+        assert_eq!(params.str("group"), Some("from-path"));
+        assert_eq!(params.str("key"), Some("from-query"));
+        assert_eq!(params.values["value"], json!(2));
+    }
+
+    #[test]
+    fn alias_is_read_when_the_name_is_absent() {
+        let param = Param::string("flow_id").alias("id");
+        let params = Params::extract(&json!({ "body": { "id": "a" } }), &[param]).unwrap();
+        assert_eq!(params.str("flow_id"), Some("a"));
+
+        let input = json!({ "body": { "id": "a" }, "flow_id": "b" });
+        let params = Params::extract(&input, &[param]).unwrap();
+        assert_eq!(params.str("flow_id"), Some("b"));
+    }
+
+    #[test]
+    fn empty_strings_and_nulls_are_absent_unless_json() {
+        let input = json!({ "body": { "name": "", "limit": null, "value": null, "text": "" } });
+        let params = Params::extract(
+            &input,
+            &[
+                Param::string("name"),
+                Param::integer("limit").default_integer(10),
+                Param::json("value"),
+                Param::json("text"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(params.str("name"), None);
+        assert_eq!(params.u64("limit"), Some(10));
+        assert_eq!(params.values.get("value"), Some(&Value::Null));
+        assert_eq!(params.str("text"), Some(""));
+
+        let failures = failed(json!({ "name": "" }), &[Param::string("name").required()]);
+        assert_eq!(failures, [("name".to_string(), "is required".to_string())]);
+    }
+
+    #[test]
+    fn strings_are_coerced() {
+        let input = json!({
+            "query_params": { "limit": " 42 ", "async": "true", "dry_run": "0" },
+            "body": { "id": 7, "flag": 1 }
+        });
+        let params = Params::extract(
+            &input,
+            &[
+                Param::integer("limit"),
+                Param::bool("async"),
+                Param::bool("dry_run").default_bool(true),
+                Param::string("id"),
+                Param::bool("flag"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(params.u64("limit"), Some(42));
+        assert!(params.bool("async"));
+        assert!(!params.bool("dry_run"));
+        assert_eq!(params.str("id"), Some("7"));
+        assert!(params.bool("flag"));
+    }
+
+    #[test]
+    fn ranges_are_enforced() {
+        let limit = Param::integer("limit").range(1, 100);
+        let offset = Param::integer("offset").min(0);
+        assert!(Params::extract(&json!({ "limit": 100, "offset": 0 }), &[limit, offset]).is_ok());
+        assert_eq!(
+            failed(json!({ "limit": 101, "offset": -1 }), &[limit, offset]),
+            [
+                ("limit".to_string(), "must be between 1 and 100".to_string()),
+                ("offset".to_string(), "must be at least 0".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn every_error_is_reported_at_once() {
+        fn not_x(value: &str) -> Result<(), String> {
+            match value {
+                "x" => Err("must not be x".to_string()),
+                _ => Ok(()),
+            }
+        }
+        let params = [
+            Param::string("group").required(),
+            Param::integer("limit"),
+            Param::bool("dry_run"),
+            Param::string("name").check(not_x),
+            Param::string("key").required(),
+        ];
+        let input = json!({ "limit": "many", "dry_run": "maybe", "name": "x", "key": "k" });
+        let error = Params::extract(&input, &params).unwrap_err();
+        assert_eq!(error.code, "invalid_parameter");
+        assert_eq!(
+            failed(input, &params),
+            [
+                ("group".to_string(), "is required".to_string()),
+                ("limit".to_string(), "must be an integer".to_string()),
+                ("dry_run".to_string(), "must be true or false".to_string()),
+                ("name".to_string(), "must not be x".to_string()),
+            ]
+        );
+
+        let error = Params::extract(&json!({}), &params).unwrap_err();
+        assert_eq!(error.code, "missing_parameter");
+    }
+}
@@ -0,0 +1,221 @@
+use iii_sdk::{IIIError, III};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::util::now_ms;
+
+/// State group ID used to persist invocation presets, one entry per function.
+pub const PRESETS_GROUP: &str = "__console.invokePresets";
+
+/// Version of the preset export format.
+pub const EXPORT_VERSION: u64 = 1;
+
+/// A named input saved for one function.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preset {
+    pub function_id: String,
+    pub name: String,
+    pub input: Value,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Unix timestamp in milliseconds of the last save
+    #[serde(default)]
+    pub updated_at: u64,
+}
+
+/// The presets stored for one function.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PresetSet {
+    function_id: String,
+    presets: Vec<Preset>,
+}
+
+/// Preset names appear in trigger paths, so they cannot contain `/`, and are
+/// shown in the UI, so they cannot be blank or contain control characters.
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("must not be blank".to_string());
+    }
+    if name.chars().count() > 100 || name.contains('/') || name.chars().any(char::is_control) {
+        return Err(format!(
+            "{:?} must be 1-100 characters without '/' or control characters",
+            name
+        ));
+    }
+    Ok(())
+}
+
+async fn load(bridge: &III, function_id: &str, timeout: Duration) -> Result<PresetSet, IIIError> {
+    let data = bridge
+        .call_with_timeout(
+            "state::get",
+            json!({ "scope": PRESETS_GROUP, "key": function_id }),
+            timeout,
+        )
+        .await?;
+    if data.is_null() {
+        return Ok(PresetSet {
+            function_id: function_id.to_string(),
+            presets: Vec::new(),
+        });
+    }
+    Ok(serde_json::from_value(data)?)
+}
+
+/// Write a function's presets, removing its entry once the last one is deleted.
+async fn store(bridge: &III, set: &PresetSet, timeout: Duration) -> Result<(), IIIError> {
+    let (state_function, state_input) = if set.presets.is_empty() {
+        (
+            "state::delete",
+            json!({ "scope": PRESETS_GROUP, "key": set.function_id }),
+        )
+    } else {
+        (
+            "state::set",
+            json!({ "scope": PRESETS_GROUP, "key": set.function_id, "value": set }),
+        )
+    };
+    bridge
+        .call_with_timeout(state_function, state_input, timeout)
+        .await
+        .map(|_| ())
+}
+
+/// The presets saved for a function, sorted by name.
+pub async fn list(
+    bridge: &III,
+    function_id: &str,
+    timeout: Duration,
+) -> Result<Vec<Preset>, IIIError> {
+    Ok(load(bridge, function_id, timeout).await?.presets)
+}
+
+pub async fn get(
+    bridge: &III,
+    function_id: &str,
+    name: &str,
+    timeout: Duration,
+) -> Result<Option<Preset>, IIIError> {
+    Ok(list(bridge, function_id, timeout)
+        .await?
+        .into_iter()
+        .find(|p| p.name == name))
+}
+
+/// Create or replace a preset.
+pub async fn save(bridge: &III, mut preset: Preset, timeout: Duration) -> Result<Preset, IIIError> {
+    let mut set = load(bridge, &preset.function_id, timeout).await?;
+    preset.updated_at = now_ms();
+    set.presets.retain(|p| p.name != preset.name);
+    set.presets.push(preset.clone());
+    set.presets.sort_by(|a, b| a.name.cmp(&b.name));
+    store(bridge, &set, timeout).await?;
+    Ok(preset)
+}
+
+/// Delete a preset, returning whether it existed.
+pub async fn delete(
+    bridge: &III,
+    function_id: &str,
+    name: &str,
+    timeout: Duration,
+) -> Result<bool, IIIError> {
+    let mut set = load(bridge, function_id, timeout).await?;
+    let before = set.presets.len();
+    set.presets.retain(|p| p.name != name);
+    if set.presets.len() == before {
+        return Ok(false);
+    }
+    store(bridge, &set, timeout).await?;
+    Ok(true)
+}
+
+/// Every preset, optionally only those of one function.
+pub async fn export(
+    bridge: &III,
+    function_id: Option<&str>,
+    timeout: Duration,
+) -> Result<Vec<Preset>, IIIError> {
+    if let Some(function_id) = function_id {
+        return list(bridge, function_id, timeout).await;
+    }
+
+    let data = bridge
+        .call_with_timeout("state::list", json!({ "scope": PRESETS_GROUP }), timeout)
+        .await?;
+    let mut presets: Vec<Preset> = data
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| {
+                    let set = item.get("value").unwrap_or(item);
+                    serde_json::from_value::<PresetSet>(set.clone()).ok()
+                })
+                .flat_map(|set| set.presets)
+                .collect()
+        })
+        .unwrap_or_default();
+    presets.sort_by(|a, b| (&a.function_id, &a.name).cmp(&(&b.function_id, &b.name)));
+    Ok(presets)
+}
+
+/// Counts reported by an import.
+#[derive(Debug, Default, Serialize)]
+pub struct ImportSummary {
+    pub imported: usize,
+    pub skipped: usize,
+}
+
+/// Save imported presets, replacing presets with the same name unless
+/// `overwrite` is false.
+pub async fn import(
+    bridge: &III,
+    presets: Vec<Preset>,
+    overwrite: bool,
+    timeout: Duration,
+) -> Result<ImportSummary, IIIError> {
+    let mut by_function: BTreeMap<String, Vec<Preset>> = BTreeMap::new();
+    for preset in presets {
+        by_function
+            .entry(preset.function_id.clone())
+            .or_default()
+            .push(preset);
+    }
+
+    let mut summary = ImportSummary::default();
+    let now = now_ms();
+    for (function_id, presets) in by_function {
+        let mut set = load(bridge, &function_id, timeout).await?;
+        for mut preset in presets {
+            let exists = set.presets.iter().any(|p| p.name == preset.name);
+            if exists && !overwrite {
+                summary.skipped += 1;
+                continue;
+            }
+            preset.updated_at = now;
+            set.presets.retain(|p| p.name != preset.name);
+            set.presets.push(preset);
+            summary.imported += 1;
+        }
+        set.presets.sort_by(|a, b| a.name.cmp(&b.name));
+        store(bridge, &set, timeout).await?;
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_must_be_printable_path_segments() {
+        assert!(validate_name("happy path").is_ok());
+        assert!(validate_name(&"é".repeat(100)).is_ok());
+        for name in ["", "   ", "a/b", "tab\there", "line\n", &"x".repeat(101)] {
+            assert!(validate_name(name).is_err(), "{:?} was accepted", name);
+        }
+    }
+}
@@ -0,0 +1,413 @@
+use iii_sdk::{IIIError, III};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long the request schemas listed from an engine are reused.
+const SCHEMAS_TTL: Duration = Duration::from_secs(10);
+
+/// The request schema of each registered function, `None` for functions
+/// without one.
+type SchemaMap = HashMap<String, Option<Value>>;
+
+/// An engine's schemas and when they were listed.
+type Listed = (Instant, Arc<SchemaMap>);
+
+async fn list_schemas(bridge: &III, timeout: Duration) -> Result<SchemaMap, IIIError> {
+    let data = bridge
+        .call_with_timeout(
+            "engine::functions::list",
+            json!({ "include_internal": true }),
+            timeout,
+        )
+        .await?;
+
+    let functions = data
+        .get("functions")
+        .and_then(|v| v.as_array())
+        .or_else(|| data.as_array());
+    Ok(functions
+        .into_iter()
+        .flatten()
+        .filter_map(|f| {
+            let function_id = f.get("function_id")?.as_str()?.to_string();
+            let schema = f
+                .get("request_format")
+                .filter(|schema| schema.is_object() || schema.is_boolean())
+                .cloned();
+            Some((function_id, schema))
+        })
+        .collect())
+}
+
+/// Request schemas per engine, cached for a short while so invocations do not
+/// list every function each time.
+#[derive(Clone, Default)]
+pub struct Schemas {
+    cache: Arc<Mutex<HashMap<String, Listed>>>,
+}
+
+impl Schemas {
+    fn cached(&self, engine: &str) -> Option<Arc<SchemaMap>> {
+        let cache = self.cache.lock().unwrap();
+        cache
+            .get(engine)
+            .filter(|(at, _)| at.elapsed() < SCHEMAS_TTL)
+            .map(|(_, schemas)| schemas.clone())
+    }
+
+    /// The request schema a function registered, if any. A function missing
+    /// from the cached list is looked up again, in case it registered since.
+    pub async fn request_schema(
+        &self,
+        bridge: &III,
+        engine: &str,
+        function_id: &str,
+        timeout: Duration,
+    ) -> Result<Option<Value>, IIIError> {
+        if let Some(schema) = self
+            .cached(engine)
+            .and_then(|schemas| schemas.get(function_id).cloned())
+        {
+            return Ok(schema);
+        }
+        let schemas = Arc::new(list_schemas(bridge, timeout).await?);
+        let schema = schemas.get(function_id).cloned().flatten();
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(engine.to_string(), (Instant::now(), schemas));
+        Ok(schema)
+    }
+}
+
+/// One place where an input does not match a schema.
+#[derive(Debug, Clone, Serialize)]
+pub struct Violation {
+    /// JSON pointer to the offending value in the input
+    pub pointer: String,
+    /// JSON pointer to the schema keyword that failed
+    pub schema_pointer: String,
+    pub keyword: String,
+    pub message: String,
+}
+
+/// Check `input` against a JSON Schema, returning every violation.
+///
+/// Fails when the schema itself cannot be compiled.
+pub fn validate(schema: &Value, input: &Value) -> Result<Vec<Violation>, String> {
+    let validator = jsonschema::validator_for(schema).map_err(|e| e.to_string())?;
+    Ok(validator
+        .iter_errors(input)
+        .map(|error| Violation {
+            pointer: error.instance_path().to_string(),
+            schema_pointer: error.schema_path().to_string(),
+            keyword: error.kind().keyword().to_string(),
+            message: error.to_string(),
+        })
+        .collect())
+}
+
+/// How deep nested schemas and `$ref`s are followed when building an example.
+const MAX_EXAMPLE_DEPTH: usize = 8;
+
+/// The most items an example array is filled with for its `minItems`.
+const MAX_EXAMPLE_ITEMS: u64 = 10;
+
+/// The longest string an example is padded to for its `minLength`.
+const MAX_EXAMPLE_LENGTH: usize = 4096;
+
+/// Build an example value for a JSON Schema.
+///
+/// Explicit `examples`, `default`, `const` and `enum` values are used first;
+/// otherwise a value is made up that respects the type, format and bounds.
+/// Optional properties are left out when `required_only` is set.
+///
+/// Fails when a string would have to be longer than [`MAX_EXAMPLE_LENGTH`],
+/// an array would need more than [`MAX_EXAMPLE_ITEMS`] items, or an array
+/// with `uniqueItems` would need more than one.
+pub fn example(schema: &Value, required_only: bool) -> Result<Value, String> {
+    ExampleBuilder {
+        root: schema,
+        required_only,
+    }
+    .build(schema, None, 0)
+}
+
+struct ExampleBuilder<'a> {
+    root: &'a Value,
+    required_only: bool,
+}
+
+impl ExampleBuilder<'_> {
+    /// Follow a local `$ref` such as `#/$defs/Address`.
+    fn resolve<'s>(&'s self, schema: &'s Value) -> &'s Value {
+        let Some(reference) = schema.get("$ref").and_then(|v| v.as_str()) else {
+            return schema;
+        };
+        reference
+            .strip_prefix('#')
+            .and_then(|pointer| self.root.pointer(pointer))
+            .unwrap_or(schema)
+    }
+
+    fn build(&self, schema: &Value, name: Option<&str>, depth: usize) -> Result<Value, String> {
+        if depth > MAX_EXAMPLE_DEPTH {
+            return Ok(Value::Null);
+        }
+        let schema = self.resolve(schema);
+        let Some(obj) = schema.as_object() else {
+            return Ok(Value::Null);
+        };
+
+        if let Some(example) = obj
+            .get("examples")
+            .and_then(|v| v.as_array())
+            .and_then(|v| v.first())
+            .or_else(|| obj.get("example"))
+            .or_else(|| obj.get("default"))
+            .or_else(|| obj.get("const"))
+            .or_else(|| obj.get("enum").and_then(|v| v.as_array()?.first()))
+        {
+            return Ok(example.clone());
+        }
+
+        for key in ["oneOf", "anyOf"] {
+            if let Some(first) = obj.get(key).and_then(|v| v.as_array()?.first()) {
+                return self.build(first, name, depth + 1);
+            }
+        }
+        if let Some(all) = obj.get("allOf").and_then(|v| v.as_array()) {
+            let mut merged = serde_json::Map::new();
+            for part in all {
+                match self.build(part, name, depth + 1)? {
+                    Value::Object(fields) => merged.extend(fields),
+                    other if merged.is_empty() => return Ok(other),
+                    _ => {}
+                }
+            }
+            return Ok(Value::Object(merged));
+        }
+
+        let type_name = match obj.get("type") {
+            Some(Value::String(t)) => t.as_str(),
+            Some(Value::Array(types)) => types
+                .iter()
+                .filter_map(|t| t.as_str())
+                .find(|t| *t != "null")
+                .unwrap_or("null"),
+            _ if obj.contains_key("properties") => "object",
+            _ if obj.contains_key("items") => "array",
+            _ => return Ok(Value::Null),
+        };
+
+        Ok(match type_name {
+            "object" => self.object(obj, depth)?,
+            "array" => {
+                let count = array_length(obj)?;
+                let item = match obj.get("items") {
+                    Some(items) => self.build(items, name, depth + 1)?,
+                    None => Value::Null,
+                };
+                Value::Array(vec![item; count])
+            }
+            "string" => Value::String(string_example(obj, name)?),
+            "integer" => number_example(obj, true),
+            "number" => number_example(obj, false),
+            "boolean" => Value::Bool(true),
+            _ => Value::Null,
+        })
+    }
+
+    fn object(&self, obj: &serde_json::Map<String, Value>, depth: usize) -> Result<Value, String> {
+        let required: Vec<&str> = obj
+            .get("required")
+            .and_then(|v| v.as_array())
+            .map(|r| r.iter().filter_map(|v| v.as_str()).collect())
+            .unwrap_or_default();
+
+        let mut out = serde_json::Map::new();
+        if let Some(properties) = obj.get("properties").and_then(|v| v.as_object()) {
+            for (key, property) in properties {
+                if self.required_only && !required.contains(&key.as_str()) {
+                    continue;
+                }
+                out.insert(key.clone(), self.build(property, Some(key), depth + 1)?);
+            }
+        }
+        Ok(Value::Object(out))
+    }
+}
+
+/// How many copies of its item an example array holds: one, or `minItems`.
+fn array_length(obj: &serde_json::Map<String, Value>) -> Result<usize, String> {
+    let min = obj.get("minItems").and_then(|v| v.as_u64());
+    let count = obj
+        .get("maxItems")
+        .and_then(|v| v.as_u64())
+        .map_or(min.unwrap_or(1), |max| min.unwrap_or(1).min(max));
+    if count > MAX_EXAMPLE_ITEMS {
+        return Err(format!(
+            "minItems {} is more than the {} items an example may have",
+            count, MAX_EXAMPLE_ITEMS
+        ));
+    }
+    let unique = obj.get("uniqueItems").and_then(|v| v.as_bool()) == Some(true);
+    if unique && count > 1 {
+        return Err(format!(
+            "uniqueItems needs {} distinct items, and an example repeats one",
+            count
+        ));
+    }
+    Ok(count as usize)
+}
+
+/// An example string, with `minLength` and `maxLength` counted in characters.
+fn string_example(
+    obj: &serde_json::Map<String, Value>,
+    name: Option<&str>,
+) -> Result<String, String> {
+    let format = obj.get("format").and_then(|v| v.as_str());
+    let camel_id = name.is_some_and(|n| n.ends_with("Id"));
+    let name = name.unwrap_or_default().to_ascii_lowercase();
+
+    let value = match format {
+        Some("email") | Some("idn-email") => "user@example.com".to_string(),
+        Some("uri") | Some("url") | Some("iri") => "https://example.com".to_string(),
+        Some("date-time") => "2024-01-01T12:00:00Z".to_string(),
+        Some("date") => "2024-01-01".to_string(),
+        Some("time") => "12:00:00Z".to_string(),
+        Some("duration") => "PT1H".to_string(),
+        Some("uuid") => "123e4567-e89b-12d3-a456-426614174000".to_string(),
+        Some("ipv4") => "192.0.2.1".to_string(),
+        Some("ipv6") => "2001:db8::1".to_string(),
+        Some("hostname") | Some("idn-hostname") => "example.com".to_string(),
+        _ if name.contains("email") => "user@example.com".to_string(),
+        _ if name.contains("url") || name.contains("uri") => "https://example.com".to_string(),
+        _ if name == "id" || name.ends_with("_id") || camel_id => "id_123".to_string(),
+        _ if name.contains("name") => "Example".to_string(),
+        _ if !name.is_empty() => name.clone(),
+        _ => "string".to_string(),
+    };
+
+    let min = obj.get("minLength").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+    let max = obj
+        .get("maxLength")
+        .and_then(|v| v.as_u64())
+        .map(|v| v as usize);
+    if min > MAX_EXAMPLE_LENGTH {
+        return Err(format!(
+            "minLength {} is longer than the {} characters an example may have",
+            min, MAX_EXAMPLE_LENGTH
+        ));
+    }
+    let mut value = value;
+    let length = value.chars().count();
+    if length < min {
+        value.push_str(&"x".repeat(min - length));
+    }
+    if let Some(max) = max {
+        value = value.chars().take(max).collect();
+    }
+    Ok(value)
+}
+
+fn number_example(obj: &serde_json::Map<String, Value>, integer: bool) -> Value {
+    let bound = |key: &str| obj.get(key).and_then(|v| v.as_f64());
+    let step = if integer { 1.0 } else { 0.5 };
+
+    let min = bound("minimum").or_else(|| bound("exclusiveMinimum").map(|m| m + step));
+    let max = bound("maximum").or_else(|| bound("exclusiveMaximum").map(|m| m - step));
+    let mut value = match (min, max) {
+        (Some(min), Some(max)) => min + ((max - min) / 2.0),
+        (Some(min), None) => min.max(1.0),
+        (None, Some(max)) => max.min(1.0),
+        (None, None) => 1.0,
+    };
+
+    if let Some(multiple) = bound("multipleOf").filter(|m| *m > 0.0) {
+        value = (value / multiple).ceil() * multiple;
+        if max.is_some_and(|max| value > max) {
+            value -= multiple;
+        }
+        // No multiple fits between the bounds; keep the value in range instead
+        if let Some(min) = min.filter(|min| value < *min) {
+            value = min;
+        }
+    }
+
+    if integer {
+        let value = if min.is_some() && max.is_some() {
+            value.floor()
+        } else {
+            value.round()
+        };
+        json!(value as i64)
+    } else {
+        json!(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_lengths_count_characters() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "größe": { "type": "string", "maxLength": 3 },
+                "ß": { "type": "string", "minLength": 3 }
+            }
+        });
+        assert_eq!(
+            example(&schema, false).unwrap(),
+            json!({ "größe": "grö", "ß": "ßxx" })
+        );
+    }
+
+    #[test]
+    fn oversized_examples_are_bounded() {
+        let array = json!({ "type": "array", "items": { "type": "integer" }, "minItems": 10 });
+        assert_eq!(
+            example(&array, false).unwrap(),
+            Value::Array(vec![json!(1); 10])
+        );
+
+        let array = json!({ "type": "array", "items": { "type": "integer" }, "minItems": 11 });
+        assert!(example(&array, false).is_err());
+
+        let string = json!({ "type": "string", "minLength": 1_000_000 });
+        assert!(example(&string, false).is_err());
+    }
+
+    #[test]
+    fn unique_arrays_are_not_filled_with_copies() {
+        let array = json!({ "type": "array", "items": { "type": "string" }, "uniqueItems": true });
+        assert_eq!(example(&array, false).unwrap(), json!(["string"]));
+
+        let array = json!({
+            "type": "array",
+            "items": { "type": "string" },
+            "uniqueItems": true,
+            "minItems": 2
+        });
+        assert!(example(&array, false).is_err());
+
+        let array = json!({ "type": "array", "items": { "type": "string" }, "maxItems": 0 });
+        assert_eq!(example(&array, false).unwrap(), json!([]));
+    }
+
+    #[test]
+    fn multiples_stay_in_range() {
+        let schema = json!({ "type": "number", "minimum": 1.1, "maximum": 1.9, "multipleOf": 1.0 });
+        let value = example(&schema, false).unwrap().as_f64().unwrap();
+        assert!((1.1..=1.9).contains(&value));
+
+        let schema = json!({ "type": "integer", "minimum": 10, "maximum": 20, "multipleOf": 4 });
+        assert_eq!(example(&schema, false).unwrap(), json!(16));
+    }
+}
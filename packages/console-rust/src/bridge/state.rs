@@ -0,0 +1,1056 @@
+use futures_util::{stream, StreamExt};
+use iii_sdk::{IIIError, III};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use serde_json_path::JsonPath;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use wildmatch::WildMatch;
+
+use crate::auth::{AuthState, Principal};
+use crate::bridge::error::ConsoleError;
+use crate::bridge::{audit, functions, history, presets};
+use crate::util::now_ms;
+
+/// State groups counted at the same time by `state_groups_list`.
+const STATS_CONCURRENCY: usize = 8;
+
+/// How long the stats of a group are reused before the group is listed again.
+const STATS_TTL: Duration = Duration::from_secs(15);
+
+/// State calls made at the same time by bulk operations.
+const BULK_CONCURRENCY: usize = 8;
+
+/// Version written to JSON exports.
+pub const EXPORT_VERSION: u32 = 1;
+
+/// Item fields read as a last-modified time, in Unix milliseconds.
+const MODIFIED_FIELDS: &[&str] = &[
+    "updated_at",
+    "updatedAt",
+    "modified_at",
+    "last_modified",
+    "timestamp",
+];
+
+/// Prefix of the groups the console keeps its own records in.
+pub const CONSOLE_GROUP_PREFIX: &str = "__console.";
+
+/// Whether a group holds console records, which the state functions and
+/// imports must not change.
+pub fn is_console_group(group: &str) -> bool {
+    group.starts_with(CONSOLE_GROUP_PREFIX)
+}
+
+/// Console groups and the console function that owns each one. Reading a
+/// group as state needs the same permission as calling its owner.
+const CONSOLE_GROUP_OWNERS: &[(&str, &str)] = &[
+    (audit::AUDIT_GROUP, audit::AUDIT_READ_FUNCTION),
+    (
+        history::HISTORY_GROUP,
+        "engine::console::invocation_history_list",
+    ),
+    (
+        presets::PRESETS_GROUP,
+        "engine::console::invoke_presets_list",
+    ),
+    (
+        functions::FLOW_CONFIG_GROUP,
+        "engine::console::flow_config_get",
+    ),
+];
+
+/// Owner of the console groups missing above, so that only roles allowed
+/// every console function can read them.
+const UNKNOWN_GROUP_OWNER: &str = "engine::console::*";
+
+/// Whether the principal may read a group through the state functions or an
+/// export. Console groups need the permission of the function that owns them.
+pub fn group_visible(auth: &AuthState, principal: &Principal, group: &str) -> bool {
+    if !is_console_group(group) {
+        return true;
+    }
+    let owner = CONSOLE_GROUP_OWNERS
+        .iter()
+        .find(|(owned, _)| *owned == group)
+        .map_or(UNKNOWN_GROUP_OWNER, |(_, owner)| owner);
+    auth.allows(principal, owner)
+}
+
+/// Check that the principal may read every group, with a 403 naming the first
+/// one it may not.
+pub fn check_readable<'a>(
+    auth: &AuthState,
+    principal: &Principal,
+    groups: impl IntoIterator<Item = &'a str>,
+) -> Result<(), ConsoleError> {
+    match groups
+        .into_iter()
+        .find(|group| !group_visible(auth, principal, group))
+    {
+        Some(hidden) => Err(ConsoleError::new(
+            403,
+            "forbidden",
+            format!("Not allowed to read group '{}'", hidden),
+        )
+        .with_details(json!({ "group": hidden }))),
+        None => Ok(()),
+    }
+}
+
+/// Every item of a state group, as returned by `state::list`.
+pub async fn list(bridge: &III, scope: &str, timeout: Duration) -> Result<Vec<Value>, IIIError> {
+    let data = bridge
+        .call_with_timeout("state::list", json!({ "scope": scope }), timeout)
+        .await?;
+    Ok(match data {
+        Value::Array(items) => items,
+        _ => Vec::new(),
+    })
+}
+
+/// The current value of an item, `null` when the key does not exist.
+pub async fn get(
+    bridge: &III,
+    scope: &str,
+    key: &str,
+    timeout: Duration,
+) -> Result<Value, IIIError> {
+    bridge
+        .call_with_timeout("state::get", json!({ "scope": scope, "key": key }), timeout)
+        .await
+}
+
+/// Content hash of a value: the SHA-256 of its compact JSON, object keys sorted.
+pub fn value_hash(value: &Value) -> String {
+    Sha256::digest(value.to_string().as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// What a caller last read of an item, checked before it is overwritten.
+#[derive(Debug)]
+pub enum Expected {
+    Value(Value),
+    /// A [`value_hash`]; the hash of `null` expects the key not to exist
+    Hash(String),
+}
+
+impl Expected {
+    pub fn matches(&self, current: &Value) -> bool {
+        match self {
+            Self::Value(expected) => expected == current,
+            Self::Hash(hash) => hash.eq_ignore_ascii_case(&value_hash(current)),
+        }
+    }
+}
+
+/// The key of a listed item: `key` for `{ key, value }` entries, else the
+/// value's own `id`.
+pub fn item_key(item: &Value) -> Option<String> {
+    let key = match item.get("value") {
+        Some(_) => item.get("key"),
+        None => item.get("id"),
+    }?;
+    match key {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// The stored value of a listed item.
+pub fn item_value(item: &Value) -> &Value {
+    match (item.get("key"), item.get("value")) {
+        (Some(_), Some(value)) => value,
+        _ => item,
+    }
+}
+
+/// Parse a JSONPath filter on item values. A bare predicate such as
+/// `@.status == "active"` keeps items it holds for; a full query starting with
+/// `$` keeps items it selects anything in.
+pub fn parse_filter(filter: &str) -> Result<JsonPath, String> {
+    let filter = filter.trim();
+    let query = if filter.starts_with('$') {
+        filter.to_string()
+    } else {
+        format!("$[?{}]", filter)
+    };
+    JsonPath::parse(&query).map_err(|e| format!("is not a valid JSONPath filter: {}", e))
+}
+
+/// Which items of a group to return, and in which order.
+pub struct ItemQuery {
+    pub prefix: Option<String>,
+    /// Glob on keys, with `*` and `?`
+    pub pattern: Option<String>,
+    pub filter: Option<String>,
+    pub descending: bool,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+/// One page of matching items.
+pub struct ItemPage {
+    pub items: Vec<Value>,
+    /// Items matching the query across every page
+    pub total: usize,
+    /// Cursor of the next page, if there is one
+    pub next_cursor: Option<String>,
+}
+
+impl ItemQuery {
+    /// Filter and sort a group's items by key, items without a key last, and
+    /// cut the requested page.
+    pub fn page(&self, items: Vec<Value>) -> Result<ItemPage, String> {
+        let pattern = self.pattern.as_deref().map(WildMatch::new);
+        let filter = self.filter.as_deref().map(parse_filter).transpose()?;
+        let bare_predicate = self
+            .filter
+            .as_deref()
+            .is_some_and(|f| !f.trim().starts_with('$'));
+
+        let mut matching: Vec<(Option<String>, Value)> = items
+            .into_iter()
+            .map(|item| (item_key(&item), item))
+            .filter(|(key, item)| {
+                let key = key.as_deref();
+                self.prefix
+                    .as_deref()
+                    .is_none_or(|prefix| key.is_some_and(|k| k.starts_with(prefix)))
+                    && pattern
+                        .as_ref()
+                        .is_none_or(|pattern| key.is_some_and(|k| pattern.matches(k)))
+                    && filter.as_ref().is_none_or(|filter| {
+                        let value = item_value(item);
+                        if bare_predicate {
+                            !filter.query(&json!([value])).is_empty()
+                        } else {
+                            !filter.query(value).is_empty()
+                        }
+                    })
+            })
+            .collect();
+
+        matching.sort_by(|(a, _), (b, _)| match (a, b) {
+            (Some(a), Some(b)) if self.descending => b.cmp(a),
+            (Some(a), Some(b)) => a.cmp(b),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        });
+
+        let total = matching.len();
+        let end = self.offset.saturating_add(self.limit).min(total);
+        let items = matching
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit)
+            .map(|(_, item)| item)
+            .collect();
+        Ok(ItemPage {
+            items,
+            total,
+            next_cursor: (end < total).then(|| end.to_string()),
+        })
+    }
+}
+
+/// Outcome for one key of a bulk operation.
+#[derive(Debug, Serialize)]
+pub struct KeyResult {
+    pub key: String,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Make one state call per key, a few at a time, reporting each outcome in
+/// the order of the calls.
+async fn for_each_key(
+    bridge: &III,
+    function: &'static str,
+    calls: Vec<(String, Value)>,
+    timeout: Duration,
+) -> Vec<KeyResult> {
+    let bridge = bridge.clone();
+    let calls = calls.into_iter().map(move |(key, input)| {
+        let bridge = bridge.clone();
+        async move {
+            let error = bridge
+                .call_with_timeout(function, input, timeout)
+                .await
+                .err()
+                .map(|err| ConsoleError::from(err).message);
+            KeyResult {
+                key,
+                ok: error.is_none(),
+                error,
+            }
+        }
+    });
+    stream::iter(calls)
+        .buffered(BULK_CONCURRENCY)
+        .collect()
+        .await
+}
+
+/// Delete each key of a group.
+pub async fn delete_keys(
+    bridge: &III,
+    scope: &str,
+    keys: Vec<String>,
+    timeout: Duration,
+) -> Vec<KeyResult> {
+    let calls = keys
+        .into_iter()
+        .map(|key| {
+            let input = json!({ "scope": scope, "key": key });
+            (key, input)
+        })
+        .collect();
+    for_each_key(bridge, "state::delete", calls, timeout).await
+}
+
+/// Set each `(key, value)` pair in a group.
+pub async fn set_items(
+    bridge: &III,
+    scope: &str,
+    items: Vec<(String, Value)>,
+    timeout: Duration,
+) -> Vec<KeyResult> {
+    let calls = items
+        .into_iter()
+        .map(|(key, value)| {
+            let input = json!({ "scope": scope, "key": key, "value": value });
+            (key, input)
+        })
+        .collect();
+    for_each_key(bridge, "state::set", calls, timeout).await
+}
+
+/// Token a whole-group clear must be confirmed with, derived from the keys it
+/// deletes, so a clear only goes through while the group still holds what was
+/// previewed.
+pub fn clear_token(scope: &str, keys: &[String]) -> String {
+    let mut keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+    keys.sort_unstable();
+    let mut hasher = Sha256::new();
+    hasher.update(scope.as_bytes());
+    for key in keys {
+        hasher.update([0]);
+        hasher.update(key.as_bytes());
+    }
+    hasher.finalize()[..8]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// One exported item, written as one line of an NDJSON export.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportRecord {
+    pub group: String,
+    pub key: String,
+    pub value: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportItem {
+    key: String,
+    value: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportGroup {
+    id: String,
+    items: Vec<ExportItem>,
+}
+
+/// A JSON export: every item of each group, grouped.
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportDocument {
+    version: u32,
+    #[serde(default)]
+    exported_at: u64,
+    groups: Vec<ExportGroup>,
+}
+
+/// A group's items as export records, by key. Items without a key could not
+/// be restored and are left out.
+pub async fn export_group(
+    bridge: &III,
+    group: &str,
+    timeout: Duration,
+) -> Result<Vec<ExportRecord>, IIIError> {
+    let mut records: Vec<ExportRecord> = list(bridge, group, timeout)
+        .await?
+        .iter()
+        .filter_map(|item| {
+            Some(ExportRecord {
+                group: group.to_string(),
+                key: item_key(item)?,
+                value: item_value(item).clone(),
+            })
+        })
+        .collect();
+    records.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok(records)
+}
+
+/// Records as NDJSON, one line each.
+pub fn to_ndjson(records: &[ExportRecord]) -> String {
+    records
+        .iter()
+        .map(|record| format!("{}\n", json!(record)))
+        .collect()
+}
+
+/// Exported groups as a JSON document.
+pub fn to_document(groups: Vec<(String, Vec<ExportRecord>)>) -> Value {
+    let groups = groups
+        .into_iter()
+        .map(|(id, records)| ExportGroup {
+            id,
+            items: records
+                .into_iter()
+                .map(|r| ExportItem {
+                    key: r.key,
+                    value: r.value,
+                })
+                .collect(),
+        })
+        .collect();
+    json!(ExportDocument {
+        version: EXPORT_VERSION,
+        exported_at: now_ms(),
+        groups,
+    })
+}
+
+/// Records of an export: a JSON document, an array of records, or NDJSON text.
+pub fn parse_export(data: Value) -> Result<Vec<ExportRecord>, String> {
+    let records: Vec<ExportRecord> = match data {
+        Value::String(text) => return parse_export_text(&text),
+        Value::Array(_) => serde_json::from_value(data)
+            .map_err(|e| format!("is not an array of {{ group, key, value }} records: {}", e))?,
+        Value::Object(_) => {
+            let document: ExportDocument = serde_json::from_value(data)
+                .map_err(|e| format!("is not a state export: {}", e))?;
+            if document.version > EXPORT_VERSION {
+                return Err(format!(
+                    "is a version {} export; this console reads up to version {}",
+                    document.version, EXPORT_VERSION
+                ));
+            }
+            document
+                .groups
+                .into_iter()
+                .flat_map(|group| {
+                    let id = group.id;
+                    group.items.into_iter().map(move |item| ExportRecord {
+                        group: id.clone(),
+                        key: item.key,
+                        value: item.value,
+                    })
+                })
+                .collect()
+        }
+        _ => return Err("must be a state export".to_string()),
+    };
+    validated(records)
+}
+
+fn validated(records: Vec<ExportRecord>) -> Result<Vec<ExportRecord>, String> {
+    match records
+        .iter()
+        .find(|r| r.group.is_empty() || r.key.is_empty())
+    {
+        Some(_) => Err("has a record with an empty group or key".to_string()),
+        None => Ok(records),
+    }
+}
+
+/// Records of an export file, in either format.
+pub fn parse_export_text(text: &str) -> Result<Vec<ExportRecord>, String> {
+    // A one-line NDJSON export is also a JSON object, but not a document
+    match serde_json::from_str::<Value>(text) {
+        Ok(data @ Value::Array(_)) => return parse_export(data),
+        Ok(data) if data.get("groups").is_some() => return parse_export(data),
+        _ => {}
+    }
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str::<ExportRecord>(line).map_err(|e| {
+                format!(
+                    "line {} is not a {{ group, key, value }} record: {}",
+                    i + 1,
+                    e
+                )
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .and_then(validated)
+}
+
+/// What an import does with keys that already exist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictMode {
+    Overwrite,
+    SkipExisting,
+    /// Import nothing if any key exists
+    Fail,
+}
+
+impl ConflictMode {
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode {
+            "overwrite" => Some(Self::Overwrite),
+            "skip-existing" | "skip_existing" => Some(Self::SkipExisting),
+            "fail" | "fail-on-conflict" | "fail_on_conflict" => Some(Self::Fail),
+            _ => None,
+        }
+    }
+}
+
+/// Outcome of an import.
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    /// Groups written to
+    pub groups: Vec<String>,
+    pub imported: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    /// The keys that failed, with why
+    pub errors: Vec<Value>,
+}
+
+/// Why an import wrote nothing.
+#[derive(Debug)]
+pub enum ImportError {
+    Engine(IIIError),
+    /// Existing `(group, key)` pairs, in fail-on-conflict mode
+    Conflict(Vec<(String, String)>),
+    /// Console groups the records would write to
+    Protected(Vec<String>),
+}
+
+impl From<IIIError> for ImportError {
+    fn from(error: IIIError) -> Self {
+        Self::Engine(error)
+    }
+}
+
+/// Records by group and key; a key repeated in the records keeps its last value.
+fn by_group(records: Vec<ExportRecord>) -> BTreeMap<String, BTreeMap<String, Value>> {
+    let mut groups: BTreeMap<String, BTreeMap<String, Value>> = BTreeMap::new();
+    for record in records {
+        groups
+            .entry(record.group)
+            .or_default()
+            .insert(record.key, record.value);
+    }
+    groups
+}
+
+/// What an import does with one group's items.
+#[derive(Debug, Default, PartialEq)]
+struct GroupPlan {
+    writes: Vec<(String, Value)>,
+    skipped: usize,
+    /// Existing keys, in fail-on-conflict mode
+    conflicts: Vec<String>,
+}
+
+fn plan_group(
+    items: BTreeMap<String, Value>,
+    existing: &HashSet<String>,
+    mode: ConflictMode,
+) -> GroupPlan {
+    let mut plan = GroupPlan::default();
+    for (key, value) in items {
+        match (existing.contains(&key), mode) {
+            (true, ConflictMode::SkipExisting) => plan.skipped += 1,
+            (true, ConflictMode::Fail) => plan.conflicts.push(key),
+            _ => plan.writes.push((key, value)),
+        }
+    }
+    plan
+}
+
+/// Write export records to their groups. Console groups are refused, and
+/// conflicts are checked for every group before anything is written; a key
+/// repeated in the records keeps its last value.
+pub async fn import(
+    bridge: &III,
+    records: Vec<ExportRecord>,
+    mode: ConflictMode,
+    timeout: Duration,
+) -> Result<ImportReport, ImportError> {
+    let groups = by_group(records);
+    let protected: Vec<String> = groups
+        .keys()
+        .filter(|group| is_console_group(group))
+        .cloned()
+        .collect();
+    if !protected.is_empty() {
+        return Err(ImportError::Protected(protected));
+    }
+
+    let mut report = ImportReport::default();
+    let mut conflicts = Vec::new();
+    let mut writes = Vec::new();
+    for (group, items) in groups {
+        // Overwrites do not care what exists
+        let existing: HashSet<String> = match mode {
+            ConflictMode::Overwrite => HashSet::new(),
+            _ => list(bridge, &group, timeout)
+                .await?
+                .iter()
+                .filter_map(item_key)
+                .collect(),
+        };
+        let plan = plan_group(items, &existing, mode);
+        report.skipped += plan.skipped;
+        conflicts.extend(plan.conflicts.into_iter().map(|key| (group.clone(), key)));
+        writes.push((group, plan.writes));
+    }
+    if !conflicts.is_empty() {
+        return Err(ImportError::Conflict(conflicts));
+    }
+
+    for (group, items) in writes {
+        for result in set_items(bridge, &group, items, timeout).await {
+            if result.ok {
+                report.imported += 1;
+            } else {
+                report.failed += 1;
+                report.errors.push(json!({
+                    "group": group,
+                    "key": result.key,
+                    "error": result.error
+                }));
+            }
+        }
+        report.groups.push(group);
+    }
+    Ok(report)
+}
+
+/// Delete the items of a group beyond its newest `keep`, ordered by the
+/// numeric `field` of their values. Returns how many were deleted.
+pub async fn trim(
+    bridge: &III,
+    scope: &str,
+    field: &str,
+    keep: usize,
+    timeout: Duration,
+) -> Result<usize, IIIError> {
+    let items = list(bridge, scope, timeout).await?;
+    if items.len() <= keep {
+        return Ok(0);
+    }
+    let mut stamped: Vec<(u64, String)> = items
+        .iter()
+        .filter_map(|item| {
+            let stamp = item_value(item).get(field).and_then(Value::as_u64);
+            Some((stamp.unwrap_or(0), item_key(item)?))
+        })
+        .collect();
+    stamped.sort_unstable_by(|a, b| b.cmp(a));
+    let stale = stamped.into_iter().skip(keep).map(|(_, key)| key).collect();
+    let results = delete_keys(bridge, scope, stale, timeout).await;
+    Ok(results.iter().filter(|r| r.ok).count())
+}
+
+/// Size and freshness of one state group.
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupStats {
+    pub count: usize,
+    /// Approximate size of the group, as serialized JSON
+    pub size_bytes: usize,
+    /// Latest modification time found on the items, when they carry one
+    pub last_modified: Option<u64>,
+}
+
+impl GroupStats {
+    fn of(items: &[Value]) -> Self {
+        Self {
+            count: items.len(),
+            size_bytes: items.iter().map(|item| item.to_string().len()).sum(),
+            last_modified: items
+                .iter()
+                .filter_map(|item| {
+                    let fields = item.get("value").filter(|v| v.is_object()).unwrap_or(item);
+                    MODIFIED_FIELDS
+                        .iter()
+                        .find_map(|field| fields.get(field)?.as_u64())
+                })
+                .max(),
+        }
+    }
+}
+
+type StatsKey = (String, String);
+
+/// Group stats per engine, cached for a short while since computing them
+/// lists every item of the group.
+#[derive(Clone, Default)]
+pub struct StateStats {
+    cache: Arc<Mutex<HashMap<StatsKey, (Instant, GroupStats)>>>,
+}
+
+impl StateStats {
+    fn cached(&self, key: &StatsKey) -> Option<GroupStats> {
+        let cache = self.cache.lock().unwrap();
+        cache
+            .get(key)
+            .filter(|(at, _)| at.elapsed() < STATS_TTL)
+            .map(|(_, stats)| stats.clone())
+    }
+
+    async fn group(
+        &self,
+        bridge: &III,
+        engine: &str,
+        group: &str,
+        timeout: Duration,
+    ) -> Result<GroupStats, IIIError> {
+        let key = (engine.to_string(), group.to_string());
+        if let Some(stats) = self.cached(&key) {
+            return Ok(stats);
+        }
+        let stats = GroupStats::of(&list(bridge, group, timeout).await?);
+        let mut cache = self.cache.lock().unwrap();
+        cache.retain(|_, (at, _)| at.elapsed() < STATS_TTL);
+        cache.insert(key, (Instant::now(), stats.clone()));
+        Ok(stats)
+    }
+
+    /// Stats of each group, in order, listing at most a few groups at a time.
+    pub async fn groups(
+        &self,
+        bridge: &III,
+        engine: &str,
+        groups: &[String],
+        timeout: Duration,
+    ) -> Vec<Result<GroupStats, IIIError>> {
+        // Lookups own what they use, so the buffered stream stays `Send`
+        let (stats, bridge, engine) = (self.clone(), bridge.clone(), engine.to_string());
+        let lookups = groups.iter().cloned().map(move |group| {
+            let (stats, bridge, engine) = (stats.clone(), bridge.clone(), engine.clone());
+            async move { stats.group(&bridge, &engine, &group, timeout).await }
+        });
+        stream::iter(lookups)
+            .buffered(STATS_CONCURRENCY)
+            .collect()
+            .await
+    }
+
+    /// Forget a group's stats after the console changed it.
+    pub fn invalidate(&self, engine: &str, group: &str) {
+        self.cache
+            .lock()
+            .unwrap()
+            .remove(&(engine.to_string(), group.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Roles;
+
+    fn principal(role: &str) -> Principal {
+        Principal {
+            name: role.to_string(),
+            role: role.to_string(),
+        }
+    }
+
+    #[test]
+    fn console_groups_need_their_owner() {
+        let auth = AuthState::new(Some("secret".to_string()), Roles::default());
+        let viewer = principal("viewer");
+        let operator = principal("operator");
+
+        let err = check_readable(&auth, &viewer, ["orders", "__console.invocations"]).unwrap_err();
+        assert_eq!(err.status_code, 403);
+        assert_eq!(
+            err.details,
+            Some(json!({ "group": "__console.invocations" }))
+        );
+        assert!(check_readable(&auth, &operator, ["orders", "__console.invocations"]).is_ok());
+
+        assert!(group_visible(&auth, &viewer, "__console.invokePresets"));
+        assert!(group_visible(&auth, &viewer, "__console.flowConfigs"));
+        assert!(!group_visible(&auth, &operator, "__console.audit"));
+        assert!(!group_visible(&auth, &operator, "__console.other"));
+        assert!(group_visible(&auth, &principal("admin"), "__console.other"));
+    }
+
+    fn all() -> ItemQuery {
+        ItemQuery {
+            prefix: None,
+            pattern: None,
+            filter: None,
+            descending: false,
+            offset: 0,
+            limit: 100,
+        }
+    }
+
+    fn items() -> Vec<Value> {
+        vec![
+            json!({ "key": "user:2", "value": { "status": "active", "attempts": 5 } }),
+            json!({ "key": "order:1", "value": { "status": "pending", "attempts": 1 } }),
+            json!({ "key": "user:1", "value": { "status": "disabled", "attempts": 4 } }),
+            json!({ "id": "user:3", "status": "active", "attempts": 0 }),
+            json!({ "value": { "status": "active" } }),
+        ]
+    }
+
+    fn keys(page: &ItemPage) -> Vec<Option<String>> {
+        page.items.iter().map(item_key).collect()
+    }
+
+    #[test]
+    fn pages_follow_the_cursor() {
+        let mut query = ItemQuery { limit: 2, ..all() };
+        let mut seen = Vec::new();
+        let mut cursors = Vec::new();
+        loop {
+            let page = query.page(items()).unwrap();
+            assert_eq!(page.total, 5);
+            seen.extend(keys(&page));
+            cursors.push(page.next_cursor.clone());
+            match page.next_cursor {
+                Some(cursor) => query.offset = cursor.parse().unwrap(),
+                None => break,
+            }
+        }
+        assert_eq!(
+            cursors,
+            [Some("2".to_string()), Some("4".to_string()), None]
+        );
+        assert_eq!(
+            seen,
+            [
+                Some("order:1".to_string()),
+                Some("user:1".to_string()),
+                Some("user:2".to_string()),
+                Some("user:3".to_string()),
+                None,
+            ]
+        );
+
+        let past_the_end = ItemQuery {
+            offset: 10,
+            ..all()
+        }
+        .page(items())
+        .unwrap();
+        assert!(past_the_end.items.is_empty());
+        assert_eq!((past_the_end.total, past_the_end.next_cursor), (5, None));
+    }
+
+    #[test]
+    fn descending_keeps_items_without_a_key_last() {
+        let page = ItemQuery {
+            descending: true,
+            ..all()
+        }
+        .page(items())
+        .unwrap();
+        assert_eq!(keys(&page)[0].as_deref(), Some("user:3"));
+        assert_eq!(keys(&page)[4], None);
+    }
+
+    #[test]
+    fn prefix_and_pattern_match_keys() {
+        let page = ItemQuery {
+            prefix: Some("user:".to_string()),
+            ..all()
+        }
+        .page(items())
+        .unwrap();
+        assert_eq!(page.total, 3);
+
+        let page = ItemQuery {
+            pattern: Some("*:1".to_string()),
+            ..all()
+        }
+        .page(items())
+        .unwrap();
+        assert_eq!(
+            keys(&page),
+            [Some("order:1".to_string()), Some("user:1".to_string())]
+        );
+
+        let page = ItemQuery {
+            pattern: Some("user:?".to_string()),
+            descending: true,
+            limit: 1,
+            ..all()
+        }
+        .page(items())
+        .unwrap();
+        assert_eq!(keys(&page), [Some("user:3".to_string())]);
+        assert_eq!((page.total, page.next_cursor), (3, Some("1".to_string())));
+    }
+
+    #[test]
+    fn filters_match_values() {
+        let page = ItemQuery {
+            filter: Some(r#"@.status == "active" && @.attempts > 3"#.to_string()),
+            ..all()
+        }
+        .page(items())
+        .unwrap();
+        assert_eq!(keys(&page), [Some("user:2".to_string())]);
+
+        // Bare values without a { key, value } wrapper are filtered as they are
+        let page = ItemQuery {
+            filter: Some("@.attempts == 0".to_string()),
+            ..all()
+        }
+        .page(items())
+        .unwrap();
+        assert_eq!(keys(&page), [Some("user:3".to_string())]);
+
+        let page = ItemQuery {
+            filter: Some("$.attempts".to_string()),
+            ..all()
+        }
+        .page(items())
+        .unwrap();
+        assert_eq!(page.total, 4);
+
+        let invalid = ItemQuery {
+            filter: Some("@.status ==".to_string()),
+            ..all()
+        };
+        assert!(invalid.page(items()).is_err());
+    }
+
+    #[test]
+    fn clear_tokens_follow_the_keys() {
+        let keys = |keys: &[&str]| keys.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+        let token = clear_token("users", &keys(&["a", "b"]));
+        assert_eq!(token.len(), 16);
+        assert_eq!(token, clear_token("users", &keys(&["b", "a"])));
+        assert_ne!(token, clear_token("users", &keys(&["a", "b", "c"])));
+        assert_ne!(token, clear_token("users", &keys(&["ab"])));
+        assert_ne!(token, clear_token("sessions", &keys(&["a", "b"])));
+    }
+
+    fn record(group: &str, key: &str, value: Value) -> ExportRecord {
+        ExportRecord {
+            group: group.to_string(),
+            key: key.to_string(),
+            value,
+        }
+    }
+
+    #[test]
+    fn export_text_is_ndjson_or_a_document() {
+        let ndjson = concat!(
+            r#"{"group":"users","key":"1","value":{"name":"Ada"}}"#,
+            "\n\n",
+            r#"{"group":"users","key":"2","value":null}"#,
+            "\n"
+        );
+        let records = parse_export_text(ndjson).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].value, Value::Null);
+
+        // A single NDJSON line is a JSON object too, but not a document
+        let one_line = r#"{"group":"users","key":"1","value":1}"#;
+        assert_eq!(parse_export_text(one_line).unwrap()[0].key, "1");
+
+        let document = json!({
+            "version": 1,
+            "groups": [{ "id": "users", "items": [{ "key": "1", "value": 1 }] }]
+        });
+        let records = parse_export_text(&document.to_string()).unwrap();
+        assert_eq!(
+            (records[0].group.as_str(), records[0].key.as_str()),
+            ("users", "1")
+        );
+
+        let array = json!([{ "group": "users", "key": "1", "value": 1 }]);
+        assert_eq!(parse_export_text(&array.to_string()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn invalid_exports_are_rejected() {
+        let newer = json!({ "version": EXPORT_VERSION + 1, "groups": [] });
+        assert!(parse_export_text(&newer.to_string())
+            .unwrap_err()
+            .contains("version 2"));
+
+        let bad_line = "{\"group\":\"users\",\"key\":\"1\",\"value\":1}\nnot json\n";
+        assert!(parse_export_text(bad_line)
+            .unwrap_err()
+            .starts_with("line 2 "));
+
+        let empty_key = r#"{"group":"users","key":"","value":1}"#;
+        assert!(parse_export_text(empty_key).is_err());
+    }
+
+    #[test]
+    fn repeated_keys_keep_their_last_value() {
+        let groups = by_group(vec![
+            record("users", "1", json!("first")),
+            record("orders", "1", json!("order")),
+            record("users", "1", json!("last")),
+        ]);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups["users"].len(), 1);
+        assert_eq!(groups["users"]["1"], json!("last"));
+    }
+
+    #[test]
+    fn conflict_modes_plan_each_group() {
+        let items = || {
+            by_group(vec![
+                record("g", "new", json!(1)),
+                record("g", "old", json!(2)),
+            ])
+            .remove("g")
+            .unwrap()
+        };
+        let existing = HashSet::from(["old".to_string()]);
+
+        let plan = plan_group(items(), &existing, ConflictMode::Overwrite);
+        assert_eq!(plan.writes.len(), 2);
+        assert_eq!((plan.skipped, plan.conflicts.len()), (0, 0));
+
+        let plan = plan_group(items(), &existing, ConflictMode::SkipExisting);
+        assert_eq!(plan.writes, [("new".to_string(), json!(1))]);
+        assert_eq!((plan.skipped, plan.conflicts.len()), (1, 0));
+
+        let plan = plan_group(items(), &existing, ConflictMode::Fail);
+        assert_eq!(plan.conflicts, ["old".to_string()]);
+        assert_eq!(plan.skipped, 0);
+    }
+
+    #[test]
+    fn conflict_modes_parse_their_aliases() {
+        assert_eq!(
+            ConflictMode::parse("skip_existing"),
+            Some(ConflictMode::SkipExisting)
+        );
+        assert_eq!(
+            ConflictMode::parse("fail-on-conflict"),
+            Some(ConflictMode::Fail)
+        );
+        assert_eq!(ConflictMode::parse("replace"), None);
+    }
+}
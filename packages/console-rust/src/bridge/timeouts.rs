@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Timeout for engine calls made by handlers without a specific setting.
+pub const DEFAULT_TIMEOUT_MS: u64 = 5_000;
+
+/// Upper bound for per-request `timeout_ms` overrides.
+pub const DEFAULT_MAX_TIMEOUT_MS: u64 = 300_000;
+
+/// Handlers whose engine calls need longer than the default.
+const BUILTIN_TIMEOUTS_MS: &[(&str, u64)] = &[
+    ("engine::console::otel_traces_tree", 10_000),
+    ("engine::console::streams_list", 10_000),
+    ("engine::console::invoke", 30_000),
+    ("engine::console::cron_trigger", 30_000),
+    ("engine::console::invocation_replay", 30_000),
+];
+
+/// Timeouts applied to the engine calls made by each console handler.
+#[derive(Debug, Clone)]
+pub struct Timeouts {
+    default: Duration,
+    max: Duration,
+    per_function: HashMap<String, Duration>,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self::new(DEFAULT_TIMEOUT_MS, DEFAULT_MAX_TIMEOUT_MS, HashMap::new())
+    }
+}
+
+impl Timeouts {
+    /// Build the timeouts from the configured values, on top of the built-in
+    /// per-handler ones. Function keys without `::` are short for
+    /// `engine::console::<key>`.
+    pub fn new(default_ms: u64, max_ms: u64, per_function_ms: HashMap<String, u64>) -> Self {
+        let mut per_function: HashMap<String, Duration> = BUILTIN_TIMEOUTS_MS
+            .iter()
+            .map(|(id, ms)| (id.to_string(), Duration::from_millis(*ms)))
+            .collect();
+
+        for (key, ms) in per_function_ms {
+            let id = if key.contains("::") {
+                key
+            } else {
+                format!("engine::console::{}", key)
+            };
+            per_function.insert(id, Duration::from_millis(ms));
+        }
+
+        Self {
+            default: Duration::from_millis(default_ms),
+            max: Duration::from_millis(max_ms),
+            per_function,
+        }
+    }
+
+    /// The configured timeout for a console function's engine calls.
+    pub fn for_function(&self, function_id: &str) -> Duration {
+        self.per_function
+            .get(function_id)
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    /// The longest timeout a request may ask for.
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// A request's `timeout_ms` override, as read by the handler's params,
+    /// capped at the server maximum.
+    pub fn requested(&self, timeout_ms: Option<u64>) -> Option<Duration> {
+        timeout_ms.map(|ms| Duration::from_millis(ms).min(self.max))
+    }
+
+    /// The timeout for one request: its override if it carries one, otherwise
+    /// the configured timeout.
+    pub fn for_request(&self, timeout_ms: Option<u64>, configured: Duration) -> Duration {
+        self.requested(timeout_ms).unwrap_or(configured)
+    }
+}
@@ -73,6 +73,36 @@ pub fn register_triggers(bridge: &III) -> Result<(), IIIError> {
             "_console/states/:group/item/:key",
             "DELETE",
         ),
+        (
+            "engine::console::state_items_set",
+            "_console/states/:group/items",
+            "POST",
+        ),
+        (
+            "engine::console::state_items_delete",
+            "_console/states/:group/items/delete",
+            "POST",
+        ),
+        (
+            "engine::console::state_items_delete_matching",
+            "_console/states/:group/items/delete-matching",
+            "POST",
+        ),
+        (
+            "engine::console::state_group_clear",
+            "_console/states/:group/clear",
+            "POST",
+        ),
+        (
+            "engine::console::state_export",
+            "_console/states/export",
+            "GET",
+        ),
+        (
+            "engine::console::state_import",
+            "_console/states/import",
+            "POST",
+        ),
         // Streams discovery (separate from state)
         (
             "engine::console::streams_list",
@@ -97,6 +127,67 @@ pub fn register_triggers(bridge: &III) -> Result<(), IIIError> {
         ),
         // Function invocation endpoint
         ("engine::console::invoke", "_console/invoke", "POST"),
+        // Async invocation jobs started with `async: true`
+        (
+            "engine::console::invocation_status",
+            "_console/invocations/:job_id",
+            "GET",
+        ),
+        (
+            "engine::console::invocation_cancel",
+            "_console/invocations/:job_id/cancel",
+            "POST",
+        ),
+        // Example input built from a function's request schema
+        (
+            "engine::console::function_example",
+            "_console/functions/:function_id/example",
+            "GET",
+        ),
+        // Saved invocation inputs, per function
+        (
+            "engine::console::invoke_presets_list",
+            "_console/invoke-presets/:function_id",
+            "GET",
+        ),
+        (
+            "engine::console::invoke_preset_save",
+            "_console/invoke-presets/:function_id/:preset",
+            "POST",
+        ),
+        (
+            "engine::console::invoke_preset_delete",
+            "_console/invoke-presets/:function_id/:preset",
+            "DELETE",
+        ),
+        (
+            "engine::console::invoke_presets_export",
+            "_console/invoke-presets",
+            "GET",
+        ),
+        (
+            "engine::console::invoke_presets_import",
+            "_console/invoke-presets",
+            "POST",
+        ),
+        // Invocation history and replay
+        (
+            "engine::console::invocation_history_list",
+            "_console/invocation-history",
+            "GET",
+        ),
+        (
+            "engine::console::invocation_history_delete",
+            "_console/invocation-history/:invocation_id",
+            "DELETE",
+        ),
+        (
+            "engine::console::invocation_replay",
+            "_console/invocation-history/:invocation_id/replay",
+            "POST",
+        ),
+        // Audit trail of mutating console calls
+        ("engine::console::audit_list", "_console/audit", "GET"),
     ];
 
     // Register each trigger with the bridge
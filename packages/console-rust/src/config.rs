@@ -0,0 +1,316 @@
+use anyhow::{bail, Context, Result};
+use clap::parser::ValueSource;
+use clap::ArgMatches;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+
+use crate::bridge::EngineProfile;
+
+/// Config file name looked up in the working directory.
+const LOCAL_CONFIG_FILE: &str = "iii-console.toml";
+
+/// Console configuration file (`iii-console.toml`).
+///
+/// Every value is optional; anything left out falls back to the environment
+/// or the built-in default.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    pub port: Option<u16>,
+    pub host: Option<String>,
+    pub base_path: Option<String>,
+    #[serde(default)]
+    pub engine: EngineSection,
+    #[serde(default)]
+    pub otel: OtelSection,
+    #[serde(default)]
+    pub features: FeaturesSection,
+    #[serde(default)]
+    pub auth: AuthSection,
+    #[serde(default)]
+    pub tls: TlsSection,
+    #[serde(default)]
+    pub audit: AuditSection,
+    #[serde(default)]
+    pub timeouts: TimeoutsSection,
+    /// Additional named engines, next to the default one from `[engine]`
+    #[serde(default)]
+    pub engines: Vec<EngineProfile>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EngineSection {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub ws_port: Option<u16>,
+    pub bridge_port: Option<u16>,
+    /// Proxy engine traffic through the console origin (default: true)
+    pub proxy: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OtelSection {
+    pub enabled: Option<bool>,
+    pub service_name: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeaturesSection {
+    pub enable_flow: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuthSection {
+    pub token: Option<String>,
+    pub roles_config: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsSection {
+    pub cert: Option<PathBuf>,
+    pub key: Option<PathBuf>,
+    pub self_signed: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuditSection {
+    pub log: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimeoutsSection {
+    pub default_ms: Option<u64>,
+    pub max_ms: Option<u64>,
+    /// Per-function timeouts, keyed by function ID or `engine::console::` suffix
+    #[serde(default)]
+    pub functions: HashMap<String, u64>,
+}
+
+/// Find and parse the config file.
+///
+/// An explicit path must exist. Otherwise `./iii-console.toml` is tried first,
+/// then `$XDG_CONFIG_HOME/iii-console/config.toml`.
+pub fn load_file(explicit: Option<&Path>) -> Result<Option<(PathBuf, FileConfig)>> {
+    let path = match explicit {
+        Some(path) => path.to_path_buf(),
+        None => match discover() {
+            Some(path) => path,
+            None => return Ok(None),
+        },
+    };
+
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read config file {}", path.display()))?;
+    let file: FileConfig = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse config file {}", path.display()))?;
+    Ok(Some((path, file)))
+}
+
+fn discover() -> Option<PathBuf> {
+    let local = PathBuf::from(LOCAL_CONFIG_FILE);
+    if local.is_file() {
+        return Some(local);
+    }
+
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    let xdg = config_home.join("iii-console").join("config.toml");
+    xdg.is_file().then_some(xdg)
+}
+
+/// Where a resolved setting came from.
+#[derive(Debug, Clone)]
+pub enum Source {
+    Cli,
+    Env(String),
+    File,
+    Default,
+}
+
+struct Entry {
+    key: &'static str,
+    value: Option<String>,
+    source: Source,
+}
+
+/// Merges settings with CLI > env > file > default precedence, remembering
+/// where each value came from.
+pub struct Resolver<'a> {
+    matches: &'a ArgMatches,
+    command: &'a clap::Command,
+    file: Option<&'a Path>,
+    entries: Vec<Entry>,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(
+        matches: &'a ArgMatches,
+        command: &'a clap::Command,
+        file: Option<&'a Path>,
+    ) -> Self {
+        Self {
+            matches,
+            command,
+            file,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Source of an argument given on the command line or through its env var.
+    fn explicit_source(&self, arg: &str) -> Option<Source> {
+        match self.matches.value_source(arg)? {
+            ValueSource::CommandLine => Some(Source::Cli),
+            ValueSource::EnvVariable => {
+                let env = self
+                    .command
+                    .get_arguments()
+                    .find(|a| a.get_id() == arg)
+                    .and_then(|a| a.get_env())
+                    .map(|e| e.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                Some(Source::Env(env))
+            }
+            _ => None,
+        }
+    }
+
+    /// Resolve a setting that always has a value. `cli` holds the parsed
+    /// argument, which is the clap default when it was not given.
+    pub fn value<T: Debug>(&mut self, key: &'static str, arg: &str, cli: T, file: Option<T>) -> T {
+        let (value, source) = match (self.explicit_source(arg), file) {
+            (Some(source), _) => (cli, source),
+            (None, Some(file)) => (file, Source::File),
+            (None, None) => (cli, Source::Default),
+        };
+        self.record(key, Some(format!("{:?}", value)), source);
+        value
+    }
+
+    /// Resolve a setting that may be left unset.
+    pub fn optional<T: Debug>(
+        &mut self,
+        key: &'static str,
+        arg: &str,
+        cli: Option<T>,
+        file: Option<T>,
+    ) -> Option<T> {
+        let value = self.pick_optional(arg, cli, file);
+        let display = value.0.as_ref().map(|v| format!("{:?}", v));
+        self.record(key, display, value.1);
+        value.0
+    }
+
+    /// Resolve a setting whose value must never be printed.
+    pub fn secret(
+        &mut self,
+        key: &'static str,
+        arg: &str,
+        cli: Option<String>,
+        file: Option<String>,
+    ) -> Option<String> {
+        let value = self.pick_optional(arg, cli, file);
+        let display = value.0.as_ref().map(|_| "\"<redacted>\"".to_string());
+        self.record(key, display, value.1);
+        value.0
+    }
+
+    fn pick_optional<T>(&self, arg: &str, cli: Option<T>, file: Option<T>) -> (Option<T>, Source) {
+        match (self.explicit_source(arg), cli, file) {
+            (Some(source), Some(cli), _) => (Some(cli), source),
+            (_, _, Some(file)) => (Some(file), Source::File),
+            _ => (None, Source::Default),
+        }
+    }
+
+    /// Record a setting that can only come from the config file.
+    pub fn file_only(&mut self, key: &'static str, value: String) {
+        self.record(key, Some(value), Source::File);
+    }
+
+    fn record(&mut self, key: &'static str, value: Option<String>, source: Source) {
+        self.entries.push(Entry { key, value, source });
+    }
+
+    /// Render the merged settings as TOML-style lines annotated with their source.
+    pub fn report(&self) -> String {
+        let mut out = match self.file {
+            Some(path) => format!("# config file: {}\n", path.display()),
+            None => "# config file: none found\n".to_string(),
+        };
+
+        let lines: Vec<(String, String)> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let line = match &entry.value {
+                    Some(value) => format!("{} = {}", entry.key, value),
+                    None => format!("# {} (unset)", entry.key),
+                };
+                let source = match &entry.source {
+                    Source::Cli => "cli".to_string(),
+                    Source::Env(var) => format!("env {}", var),
+                    Source::File => "file".to_string(),
+                    Source::Default => "default".to_string(),
+                };
+                (line, source)
+            })
+            .collect();
+
+        let width = lines.iter().map(|(line, _)| line.len()).max().unwrap_or(0);
+        for (line, source) in lines {
+            out.push_str(&format!("{:width$}  # {}\n", line, source, width = width));
+        }
+        out
+    }
+}
+
+/// Reject setting combinations that only become visible after merging layers.
+pub fn validate_tls(
+    cert: &Option<PathBuf>,
+    key: &Option<PathBuf>,
+    self_signed: bool,
+) -> Result<()> {
+    match (cert, key) {
+        (Some(_), None) => bail!("tls.cert is set but tls.key is missing"),
+        (None, Some(_)) => bail!("tls.key is set but tls.cert is missing"),
+        (Some(_), Some(_)) if self_signed => {
+            bail!("tls.self_signed cannot be combined with tls.cert and tls.key")
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Normalize the path the console is served under to `/` or `/a/b`, without
+/// a trailing slash.
+pub fn normalize_base_path(path: &str) -> Result<String> {
+    let trimmed = path.trim().trim_matches('/');
+    if trimmed.is_empty() {
+        return Ok("/".to_string());
+    }
+    let valid_segment = |segment: &str| {
+        !segment.is_empty()
+            && segment != "."
+            && segment != ".."
+            && segment
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-._~".contains(c))
+    };
+    if !trimmed.split('/').all(valid_segment) {
+        bail!(
+            "Invalid base path '{}': use path segments of letters, digits, '-', '.', '_' or '~'",
+            path
+        );
+    }
+    Ok(format!("/{}", trimmed))
+}
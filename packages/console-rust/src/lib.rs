@@ -1,2 +1,9 @@
+pub mod assets;
+pub mod auth;
 pub mod bridge;
+pub mod config;
+pub mod live;
+pub mod proxy;
 pub mod server;
+pub mod tls;
+pub mod util;
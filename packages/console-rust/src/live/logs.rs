@@ -0,0 +1,213 @@
+use iii_sdk::{IIIError, III};
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+
+use super::{poll, Batch, Cursor, EventStream, QUERY_TIMEOUT};
+
+/// Log records fetched per engine query.
+const PAGE_SIZE: usize = 500;
+
+/// Engine queries made per poll before the rest is left for the next one.
+const MAX_PAGES: usize = 10;
+
+/// Server-side filters of a live log stream.
+#[derive(Debug, Default, Clone)]
+pub struct LogFilter {
+    /// Lowest OTEL severity number let through
+    pub severity_min: Option<u64>,
+    pub service: Option<String>,
+    pub trace_id: Option<String>,
+    /// Case-insensitive substring of the log body
+    pub text: Option<String>,
+}
+
+impl LogFilter {
+    fn matches(&self, log: &Value) -> bool {
+        let text = self.text.as_ref().map(|t| t.to_lowercase());
+        self.severity_min.is_none_or(|min| severity(log) >= min)
+            && self
+                .service
+                .as_ref()
+                .is_none_or(|service| service_name(log) == Some(service.as_str()))
+            && self
+                .trace_id
+                .as_ref()
+                .is_none_or(|id| log.get("trace_id").and_then(|v| v.as_str()) == Some(id))
+            && text.is_none_or(|text| body(log).to_lowercase().contains(&text))
+    }
+
+    fn query(&self, cursor: Cursor, offset: usize) -> Value {
+        let mut query = Map::new();
+        query.insert("start_time".into(), json!(cursor.millis()));
+        if let Some(trace_id) = &self.trace_id {
+            query.insert("trace_id".into(), json!(trace_id));
+        }
+        if let Some(severity_min) = self.severity_min {
+            query.insert("severity_min".into(), json!(severity_min));
+        }
+        query.insert("offset".into(), json!(offset));
+        query.insert("limit".into(), json!(PAGE_SIZE));
+        Value::Object(query)
+    }
+}
+
+/// The OTEL severity number of a level name such as `warn` or `ERROR`, or of
+/// a number given as text.
+pub fn severity_number(level: &str) -> Option<u64> {
+    if let Ok(number) = level.parse() {
+        return Some(number);
+    }
+    match level.to_ascii_lowercase().as_str() {
+        "trace" => Some(1),
+        "debug" => Some(5),
+        "info" => Some(9),
+        "warn" | "warning" => Some(13),
+        "error" => Some(17),
+        "fatal" => Some(21),
+        _ => None,
+    }
+}
+
+/// A record's severity number, falling back to its severity text.
+fn severity(log: &Value) -> u64 {
+    match log.get("severity_number").and_then(|v| v.as_u64()) {
+        Some(number) if number > 0 => number,
+        _ => log
+            .get("severity_text")
+            .and_then(|v| v.as_str())
+            .and_then(severity_number)
+            .unwrap_or(0),
+    }
+}
+
+fn service_name(log: &Value) -> Option<&str> {
+    log.get("service_name")
+        .and_then(|v| v.as_str())
+        .or_else(|| {
+            log.get("resource")
+                .and_then(|r| r.get("service.name"))
+                .and_then(|v| v.as_str())
+        })
+}
+
+fn body(log: &Value) -> String {
+    match log.get("body") {
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+        None => String::new(),
+    }
+}
+
+async fn fetch(bridge: III, filter: Arc<LogFilter>, cursor: Cursor) -> Result<Batch, IIIError> {
+    let mut records = Vec::new();
+    for page in 0..MAX_PAGES {
+        let data = bridge
+            .call_with_timeout(
+                "engine::logs::list",
+                filter.query(cursor, page * PAGE_SIZE),
+                QUERY_TIMEOUT,
+            )
+            .await?;
+        let logs = match data.get("logs") {
+            Some(Value::Array(logs)) => logs.clone(),
+            _ => Vec::new(),
+        };
+        let fetched = logs.len();
+
+        records.extend(logs.into_iter().filter_map(|log| {
+            let timestamp = log.get("timestamp_unix_nano").and_then(|v| v.as_u64())?;
+            filter.matches(&log).then_some((timestamp, log))
+        }));
+        if fetched < PAGE_SIZE {
+            return Ok(Batch {
+                records,
+                more: false,
+            });
+        }
+    }
+    Ok(Batch {
+        records,
+        more: true,
+    })
+}
+
+/// Follow new OTEL log records on an engine, sending each one that passes the
+/// filter as a `log` event.
+pub fn stream_logs(bridge: III, filter: LogFilter, cursor: Cursor) -> EventStream {
+    let filter = Arc::new(filter);
+    poll(bridge, cursor, "log", move |bridge, cursor| {
+        fetch(bridge, filter.clone(), cursor)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(timestamp: u64, severity: &str, body: &str) -> Value {
+        json!({
+            "timestamp_unix_nano": timestamp,
+            "severity_text": severity,
+            "service_name": "api",
+            "trace_id": "t1",
+            "body": body,
+        })
+    }
+
+    #[test]
+    fn queries_start_at_the_cursor_millisecond() {
+        let filter = LogFilter {
+            severity_min: Some(13),
+            trace_id: Some("t1".to_string()),
+            ..Default::default()
+        };
+        let cursor = Cursor::parse("1700000000123456789:2").unwrap();
+        assert_eq!(
+            filter.query(cursor, PAGE_SIZE),
+            json!({
+                "start_time": 1_700_000_000_123u64,
+                "trace_id": "t1",
+                "severity_min": 13,
+                "offset": PAGE_SIZE,
+                "limit": PAGE_SIZE,
+            })
+        );
+    }
+
+    #[test]
+    fn resuming_skips_logs_sent_before_the_cursor() {
+        let ms = 1_700_000_000_123_000_000u64;
+        let fetched = vec![
+            (ms + 1, log(ms + 1, "info", "earlier in the millisecond")),
+            (ms + 5, log(ms + 5, "info", "sent")),
+            (ms + 5, log(ms + 5, "info", "not sent yet")),
+            (ms + 9, log(ms + 9, "info", "new")),
+        ];
+        let mut cursor = Cursor::parse(&format!("{}:1", ms + 5)).unwrap();
+        let bodies: Vec<_> = cursor
+            .advance(fetched)
+            .into_iter()
+            .map(|(_, log)| body(&log))
+            .collect();
+        assert_eq!(bodies, ["not sent yet", "new"]);
+    }
+
+    #[test]
+    fn filter_matches_severity_service_and_text() {
+        let filter = LogFilter {
+            severity_min: severity_number("warn"),
+            service: Some("api".to_string()),
+            text: Some("TIMEOUT".to_string()),
+            ..Default::default()
+        };
+        assert!(filter.matches(&log(1, "ERROR", "upstream timeout")));
+        assert!(!filter.matches(&log(1, "info", "upstream timeout")));
+        assert!(!filter.matches(&log(1, "error", "refused")));
+
+        let mut numbered = log(1, "", "timeout");
+        numbered["severity_number"] = json!(21);
+        assert!(filter.matches(&numbered));
+        assert_eq!(severity_number("17"), Some(17));
+        assert_eq!(severity_number("loud"), None);
+    }
+}
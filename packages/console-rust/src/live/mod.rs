@@ -0,0 +1,390 @@
+mod logs;
+mod traces;
+
+pub use logs::{severity_number, stream_logs, LogFilter};
+pub use traces::{stream_traces, TraceFilter};
+
+use axum::response::sse::{Event, KeepAlive, KeepAliveStream, Sse};
+use iii_sdk::{IIIError, III};
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::fmt;
+use std::future::Future;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::bridge::ConsoleError;
+
+/// How often the engine is asked for new records.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Longest wait between retries while the engine keeps failing.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Events queued for a client before polling pauses until it reads them.
+const BUFFERED_EVENTS: usize = 256;
+
+/// Timeout for each engine query made by a stream.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A Server-Sent Events response fed by a poller task.
+pub type EventStream = Sse<KeepAliveStream<ReceiverStream<Result<Event, Infallible>>>>;
+
+/// Position in a stream: the timestamp of the last record sent, and how many
+/// records with exactly that timestamp were sent.
+///
+/// Sent as the SSE event ID, so a reconnecting client resumes through the
+/// `Last-Event-ID` header without gaps or repeats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub timestamp_nanos: u64,
+    pub sent_at_timestamp: usize,
+}
+
+impl Cursor {
+    pub fn from_nanos(timestamp_nanos: u64) -> Self {
+        Self {
+            timestamp_nanos,
+            sent_at_timestamp: 0,
+        }
+    }
+
+    pub fn from_millis(timestamp_millis: u64) -> Self {
+        Self::from_nanos(timestamp_millis.saturating_mul(1_000_000))
+    }
+
+    /// A cursor that only lets through records from now on.
+    pub fn now() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Self::from_nanos(nanos)
+    }
+
+    /// Where a stream starts: after the last event a reconnecting client
+    /// received, at `since` (in milliseconds), or now. A malformed event ID
+    /// is ignored.
+    pub fn resume(last_event_id: Option<&str>, since: Option<u64>) -> Self {
+        last_event_id
+            .and_then(Self::parse)
+            .or_else(|| since.map(Self::from_millis))
+            .unwrap_or_else(Self::now)
+    }
+
+    /// Parse an event ID written by [`Cursor`]'s `Display`, `<nanos>:<count>`.
+    pub fn parse(id: &str) -> Option<Self> {
+        let (nanos, count) = id.trim().split_once(':').unwrap_or((id.trim(), "0"));
+        Some(Self {
+            timestamp_nanos: nanos.parse().ok()?,
+            sent_at_timestamp: count.parse().ok()?,
+        })
+    }
+
+    /// The cursor's timestamp in milliseconds, rounded down, as engine queries take it.
+    pub fn millis(&self) -> u64 {
+        self.timestamp_nanos / 1_000_000
+    }
+
+    /// Keep the records of a batch that come after the cursor, moving it past them.
+    ///
+    /// Records must be sorted by timestamp.
+    fn advance<T>(&mut self, records: Vec<(u64, T)>) -> Vec<(Cursor, T)> {
+        let mut skipped_at_cursor = 0;
+        let mut out = Vec::new();
+        for (timestamp, record) in records {
+            if timestamp < self.timestamp_nanos {
+                continue;
+            }
+            if timestamp == self.timestamp_nanos {
+                if skipped_at_cursor < self.sent_at_timestamp {
+                    skipped_at_cursor += 1;
+                    continue;
+                }
+                self.sent_at_timestamp += 1;
+            } else {
+                *self = Self {
+                    timestamp_nanos: timestamp,
+                    sent_at_timestamp: 1,
+                };
+                skipped_at_cursor = 1;
+            }
+            out.push((*self, record));
+        }
+        out
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.timestamp_nanos, self.sent_at_timestamp)
+    }
+}
+
+/// Records fetched by one poll.
+pub struct Batch {
+    /// `(timestamp in nanoseconds, record)` pairs, in any order
+    pub records: Vec<(u64, Value)>,
+    /// Whether the engine has more records than one poll fetches
+    pub more: bool,
+}
+
+type Sender = mpsc::Sender<Result<Event, Infallible>>;
+
+/// A bounded channel feeding an SSE response.
+fn channel() -> (Sender, EventStream) {
+    let (tx, rx) = mpsc::channel(BUFFERED_EVENTS);
+    let stream = Sse::new(ReceiverStream::new(rx)).keep_alive(KeepAlive::default());
+    (tx, stream)
+}
+
+/// An `engine_error` event carrying the console error envelope.
+fn error_event(stream: &str, err: IIIError) -> Event {
+    let error = ConsoleError::from(err);
+    tracing::debug!(stream, "Live stream query failed: {}", error.message);
+    Event::default()
+        .event("engine_error")
+        .data(json!({ "error": error }).to_string())
+}
+
+/// Wait before the next poll, returning false once the client has disconnected.
+async fn wait(tx: &Sender, delay: Duration) -> bool {
+    tokio::select! {
+        _ = tokio::time::sleep(delay) => true,
+        _ = tx.closed() => false,
+    }
+}
+
+/// What one poll of a stream produced.
+struct Polled {
+    events: Vec<Event>,
+    /// Whether to poll again right away rather than after the interval
+    again: bool,
+}
+
+/// Feed an SSE response from a poller task. `poll_once` takes the stream's
+/// state and hands it back with the result of the poll.
+///
+/// When the client stops reading, the buffer fills and polling pauses rather
+/// than dropping events; it stops once the client disconnects. Engine failures
+/// are sent as `engine_error` events carrying the console error envelope, and
+/// polling backs off until the engine answers again.
+fn run<S, F, Fut>(stream: &'static str, state: S, mut poll_once: F) -> EventStream
+where
+    S: Send + 'static,
+    F: FnMut(S) -> Fut + Send + 'static,
+    Fut: Future<Output = (S, Result<Polled, IIIError>)> + Send,
+{
+    let (tx, events) = channel();
+    tokio::spawn(async move {
+        let mut state = state;
+        let mut backoff = POLL_INTERVAL;
+        loop {
+            let (next, result) = poll_once(state).await;
+            state = next;
+            let delay = match result {
+                Ok(polled) => {
+                    for event in polled.events {
+                        if tx.send(Ok(event)).await.is_err() {
+                            return;
+                        }
+                    }
+                    backoff = POLL_INTERVAL;
+                    if polled.again {
+                        Duration::ZERO
+                    } else {
+                        POLL_INTERVAL
+                    }
+                }
+                Err(err) => {
+                    if tx.send(Ok(error_event(stream, err))).await.is_err() {
+                        return;
+                    }
+                    let delay = backoff;
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                    delay
+                }
+            };
+
+            if !wait(&tx, delay).await {
+                return;
+            }
+        }
+    });
+
+    events
+}
+
+/// Sort a batch by timestamp, then by content, dropping repeated records.
+///
+/// Pages fetched by offset can hold a record twice when new ones arrive between
+/// queries. Ordering the records that share a timestamp also keeps the
+/// cursor's count of them consistent from one poll to the next.
+fn in_order(mut records: Vec<(u64, Value)>) -> Vec<(u64, Value)> {
+    records.sort_by_cached_key(|(timestamp, record)| (*timestamp, record.to_string()));
+    records.dedup();
+    records
+}
+
+/// Stream records fetched by `fetch` as SSE events named `event`.
+///
+/// The engine is polled every second, or right away while it reports more
+/// new records, with the backpressure and error handling of [`run`].
+pub fn poll<F, Fut>(bridge: III, cursor: Cursor, event: &'static str, fetch: F) -> EventStream
+where
+    F: FnMut(III, Cursor) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<Batch, IIIError>> + Send,
+{
+    run(
+        event,
+        (bridge, cursor, fetch),
+        move |(bridge, mut cursor, mut fetch)| async move {
+            let result = fetch(bridge.clone(), cursor).await.map(|batch| {
+                let records = cursor.advance(in_order(batch.records));
+                Polled {
+                    again: batch.more && !records.is_empty(),
+                    events: records
+                        .into_iter()
+                        .map(|(position, record)| {
+                            Event::default()
+                                .event(event)
+                                .id(position.to_string())
+                                .data(record.to_string())
+                        })
+                        .collect(),
+                }
+            });
+            ((bridge, cursor, fetch), result)
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::response::IntoResponse;
+    use futures_util::StreamExt;
+
+    fn records(pairs: &[(u64, &str)]) -> Vec<(u64, Value)> {
+        pairs
+            .iter()
+            .map(|(t, msg)| (*t, json!({ "msg": msg })))
+            .collect()
+    }
+
+    fn messages<T>(sent: &[(T, Value)]) -> Vec<&str> {
+        sent.iter()
+            .map(|(_, r)| r["msg"].as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn cursor_round_trips_through_its_event_id() {
+        let cursor = Cursor {
+            timestamp_nanos: 1_700_000_000_123_456_789,
+            sent_at_timestamp: 3,
+        };
+        assert_eq!(cursor.to_string(), "1700000000123456789:3");
+        assert_eq!(Cursor::parse(&cursor.to_string()), Some(cursor));
+        assert_eq!(Cursor::parse(" 42 "), Some(Cursor::from_nanos(42)));
+        assert_eq!(Cursor::from_millis(5).millis(), 5);
+        assert_eq!(Cursor::from_nanos(5_999_999).millis(), 5);
+    }
+
+    #[test]
+    fn resume_prefers_the_last_event_id() {
+        let sent = Cursor {
+            timestamp_nanos: 7_000_000,
+            sent_at_timestamp: 2,
+        };
+        assert_eq!(Cursor::resume(Some("7000000:2"), Some(1)), sent);
+        assert_eq!(Cursor::resume(None, Some(3)), Cursor::from_millis(3));
+
+        let before = Cursor::now();
+        assert!(Cursor::resume(None, None).timestamp_nanos >= before.timestamp_nanos);
+    }
+
+    #[test]
+    fn malformed_event_ids_are_ignored() {
+        for id in ["", "abc", "12:x", "x:1", "1:2:3", "-5", "12:-1"] {
+            assert_eq!(Cursor::parse(id), None, "{id:?}");
+            assert_eq!(Cursor::resume(Some(id), Some(9)), Cursor::from_millis(9));
+        }
+    }
+
+    #[test]
+    fn advance_skips_records_already_sent_at_the_cursor() {
+        let mut cursor = Cursor {
+            timestamp_nanos: 20,
+            sent_at_timestamp: 1,
+        };
+        let sent = cursor.advance(records(&[(10, "old"), (20, "a"), (20, "b"), (30, "c")]));
+        assert_eq!(messages(&sent), ["b", "c"]);
+        assert_eq!(
+            sent[0].0,
+            Cursor {
+                timestamp_nanos: 20,
+                sent_at_timestamp: 2
+            }
+        );
+        assert_eq!(
+            cursor,
+            Cursor {
+                timestamp_nanos: 30,
+                sent_at_timestamp: 1
+            }
+        );
+
+        // The next poll returns the same records again, and nothing new
+        let again = cursor.advance(records(&[(20, "a"), (20, "b"), (30, "c")]));
+        assert!(again.is_empty());
+    }
+
+    #[test]
+    fn resuming_from_a_sent_event_id_repeats_nothing() {
+        let batch = in_order(records(&[(20, "b"), (20, "a"), (20, "c"), (30, "d")]));
+        let mut first = Cursor::from_nanos(20);
+        let sent = first.advance(batch.clone());
+        let resumed_at = sent[1].0.to_string();
+
+        let mut resumed = Cursor::parse(&resumed_at).unwrap();
+        assert_eq!(messages(&resumed.advance(batch)), ["c", "d"]);
+    }
+
+    #[test]
+    fn repeated_records_are_sent_once() {
+        let batch = records(&[(20, "a"), (20, "b"), (30, "c"), (20, "a"), (20, "b")]);
+        let mut cursor = Cursor::from_nanos(0);
+        assert_eq!(messages(&cursor.advance(in_order(batch))), ["a", "b", "c"]);
+        assert_eq!(
+            cursor,
+            Cursor {
+                timestamp_nanos: 30,
+                sent_at_timestamp: 1
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn run_sends_events_and_engine_errors() {
+        let stream = run("test", 0, |polls: usize| async move {
+            let result = match polls {
+                0 => Ok(Polled {
+                    events: vec![Event::default().event("record").id("1:1").data("{}")],
+                    again: true,
+                }),
+                _ => Err(IIIError::Timeout),
+            };
+            (polls + 1, result)
+        });
+        let mut body = stream.into_response().into_body().into_data_stream();
+
+        let first = body.next().await.unwrap().unwrap();
+        assert_eq!(&first[..], b"event: record\nid: 1:1\ndata: {}\n\n");
+        let second = body.next().await.unwrap().unwrap();
+        let second = String::from_utf8(second.to_vec()).unwrap();
+        assert!(second.starts_with("event: engine_error\n"));
+        assert!(second.contains("\"code\":\"timeout\""));
+    }
+}
@@ -0,0 +1,446 @@
+use axum::response::sse::Event;
+use iii_sdk::{IIIError, III};
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+use super::{run, Cursor, EventStream, Polled, QUERY_TIMEOUT};
+
+/// Spans fetched per engine query.
+const PAGE_SIZE: usize = 500;
+
+/// Engine queries made per poll, newest spans first.
+const MAX_PAGES: usize = 10;
+
+/// How far back each poll looks for spans, by span start time. Traces that
+/// started earlier are not replayed, whatever `since` or `Last-Event-ID` asks.
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// How long a trace must go without new spans, once its root span is
+/// recorded, before it counts as completed.
+const SETTLE: Duration = Duration::from_secs(2);
+
+/// How long a trace whose root span never shows up is tracked.
+const ORPHAN_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Finished trace IDs remembered so late spans do not reopen them.
+const MAX_FINISHED: usize = 10_000;
+
+/// Span attributes that name the function a span ran.
+const FUNCTION_ATTRIBUTES: &[&str] = &[
+    "function_id",
+    "iii.function.id",
+    "faas.name",
+    "code.function",
+];
+
+/// Server-side filters of a live trace stream, applied to root spans.
+#[derive(Debug, Default, Clone)]
+pub struct TraceFilter {
+    pub service: Option<String>,
+    pub function_id: Option<String>,
+    pub min_duration_ms: Option<u64>,
+}
+
+impl TraceFilter {
+    fn matches(&self, root: &Value) -> bool {
+        self.service
+            .as_ref()
+            .is_none_or(|service| service_name(root) == Some(service.as_str()))
+            && self.function_id.as_ref().is_none_or(|function_id| {
+                function_of(root).as_deref() == Some(function_id.as_str())
+            })
+            && self
+                .min_duration_ms
+                .is_none_or(|min| duration_nanos(root) / 1_000_000 >= min)
+    }
+}
+
+fn str_field<'a>(span: &'a Value, key: &str) -> Option<&'a str> {
+    span.get(key)
+        .and_then(|v| v.as_str())
+        .filter(|v| !v.is_empty())
+}
+
+fn nanos_field(span: &Value, key: &str) -> u64 {
+    span.get(key).and_then(|v| v.as_u64()).unwrap_or(0)
+}
+
+fn duration_nanos(span: &Value) -> u64 {
+    nanos_field(span, "end_time_unix_nano")
+        .saturating_sub(nanos_field(span, "start_time_unix_nano"))
+}
+
+fn is_root(span: &Value) -> bool {
+    str_field(span, "parent_span_id").is_none()
+}
+
+fn is_error(span: &Value) -> bool {
+    str_field(span, "status").is_some_and(|s| s.eq_ignore_ascii_case("error"))
+}
+
+fn service_name(span: &Value) -> Option<&str> {
+    str_field(span, "service_name").or_else(|| {
+        span.get("resource")
+            .and_then(|r| r.get("service.name"))
+            .and_then(|v| v.as_str())
+    })
+}
+
+/// A span attribute, whether attributes are `[key, value]` pairs or an object.
+fn attribute<'a>(span: &'a Value, key: &str) -> Option<&'a Value> {
+    match span.get("attributes")? {
+        Value::Array(pairs) => pairs
+            .iter()
+            .find_map(|pair| match pair.as_array()?.as_slice() {
+                [k, v] if k.as_str() == Some(key) => Some(v),
+                _ => None,
+            }),
+        Value::Object(attributes) => attributes.get(key),
+        _ => None,
+    }
+}
+
+/// The function a span ran, from its attributes.
+fn function_of(span: &Value) -> Option<String> {
+    FUNCTION_ATTRIBUTES
+        .iter()
+        .find_map(|key| attribute(span, key)?.as_str().map(String::from))
+}
+
+/// What is known about a trace that has not completed yet.
+struct PendingTrace {
+    span_ids: HashSet<String>,
+    root: Option<Value>,
+    error_count: usize,
+    services: BTreeSet<String>,
+    first_seen: Instant,
+    last_change: Instant,
+}
+
+impl PendingTrace {
+    fn new(now: Instant) -> Self {
+        Self {
+            span_ids: HashSet::new(),
+            root: None,
+            error_count: 0,
+            services: BTreeSet::new(),
+            first_seen: now,
+            last_change: now,
+        }
+    }
+
+    fn summary(&self, trace_id: &str, root: &Value) -> Value {
+        json!({
+            "trace_id": trace_id,
+            "root_span_id": str_field(root, "span_id"),
+            "name": str_field(root, "name"),
+            "service_name": service_name(root),
+            "function_id": function_of(root),
+            "status": str_field(root, "status"),
+            "start_time_unix_nano": nanos_field(root, "start_time_unix_nano"),
+            "end_time_unix_nano": nanos_field(root, "end_time_unix_nano"),
+            "duration_ms": duration_nanos(root) as f64 / 1_000_000.0,
+            "span_count": self.span_ids.len(),
+            "error_count": self.error_count,
+            "services": self.services,
+        })
+    }
+}
+
+/// Follows spans across polls and decides which events to send.
+struct Tracker {
+    filter: TraceFilter,
+    /// Traces whose root span ended at or before this are not reported
+    resume_after: u64,
+    pending: HashMap<String, PendingTrace>,
+    finished: HashSet<String>,
+    finished_order: VecDeque<String>,
+    /// Highest root end time of the traces already reported as completed
+    reported: u64,
+}
+
+impl Tracker {
+    fn new(filter: TraceFilter, resume_after: u64) -> Self {
+        Self {
+            filter,
+            resume_after,
+            pending: HashMap::new(),
+            finished: HashSet::new(),
+            finished_order: VecDeque::new(),
+            reported: resume_after,
+        }
+    }
+
+    fn finish(&mut self, trace_id: String) {
+        self.pending.remove(&trace_id);
+        if self.finished.insert(trace_id.clone()) {
+            self.finished_order.push_back(trace_id);
+        }
+        while self.finished_order.len() > MAX_FINISHED {
+            if let Some(old) = self.finished_order.pop_front() {
+                self.finished.remove(&old);
+            }
+        }
+    }
+
+    /// Record spans, returning `root_span` events for newly recorded root spans.
+    fn observe(&mut self, spans: Vec<Value>, now: Instant) -> Vec<Event> {
+        let mut events = Vec::new();
+        for span in spans {
+            let (Some(trace_id), Some(span_id)) =
+                (str_field(&span, "trace_id"), str_field(&span, "span_id"))
+            else {
+                continue;
+            };
+            if self.finished.contains(trace_id) {
+                continue;
+            }
+            let trace_id = trace_id.to_string();
+            let trace = self
+                .pending
+                .entry(trace_id.clone())
+                .or_insert_with(|| PendingTrace::new(now));
+            if !trace.span_ids.insert(span_id.to_string()) {
+                continue;
+            }
+            trace.last_change = now;
+            if is_error(&span) {
+                trace.error_count += 1;
+            }
+            if let Some(service) = service_name(&span) {
+                trace.services.insert(service.to_string());
+            }
+            if !is_root(&span) || trace.root.is_some() {
+                continue;
+            }
+
+            if nanos_field(&span, "end_time_unix_nano") <= self.resume_after
+                || !self.filter.matches(&span)
+            {
+                self.finish(trace_id);
+                continue;
+            }
+            let data = span.to_string();
+            if let Some(trace) = self.pending.get_mut(&trace_id) {
+                trace.root = Some(span);
+            }
+            events.push(
+                Event::default()
+                    .event("root_span")
+                    .id(self.position().to_string())
+                    .data(data),
+            );
+        }
+        events
+    }
+
+    /// `trace` events for traces that settled, and forget abandoned ones.
+    fn settle(&mut self, now: Instant) -> Vec<Event> {
+        let mut done: Vec<(String, Value, u64)> = self
+            .pending
+            .iter()
+            .filter(|(_, t)| now.duration_since(t.last_change) >= SETTLE)
+            .filter_map(|(id, t)| {
+                let root = t.root.as_ref()?;
+                Some((
+                    id.clone(),
+                    t.summary(id, root),
+                    nanos_field(root, "end_time_unix_nano"),
+                ))
+            })
+            .collect();
+        done.sort_by_key(|(_, _, end)| *end);
+
+        let abandoned: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, t)| t.root.is_none() && now.duration_since(t.first_seen) >= ORPHAN_TTL)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for trace_id in abandoned {
+            self.pending.remove(&trace_id);
+        }
+
+        let mut events = Vec::new();
+        for (trace_id, summary, end) in done {
+            self.finish(trace_id);
+            self.reported = self.reported.max(end);
+            events.push(
+                Event::default()
+                    .event("trace")
+                    .id(self.position().to_string())
+                    .data(summary.to_string()),
+            );
+        }
+        events
+    }
+
+    /// Where a reconnecting client resumes: just before the earliest root span
+    /// still waiting for its trace to complete, so that trace is not lost.
+    fn position(&self) -> u64 {
+        self.pending
+            .values()
+            .filter_map(|t| t.root.as_ref())
+            .map(|root| nanos_field(root, "end_time_unix_nano").saturating_sub(1))
+            .min()
+            .unwrap_or(self.reported)
+            .max(self.resume_after)
+    }
+}
+
+/// Fetch the spans that started within the window, newest first, stopping at
+/// the first page that holds nothing new.
+async fn fetch(bridge: &III, tracker: &Tracker) -> Result<Vec<Value>, IIIError> {
+    let window_start = Cursor::now()
+        .millis()
+        .saturating_sub(WINDOW.as_millis() as u64);
+    let mut spans = Vec::new();
+    for page in 0..MAX_PAGES {
+        let query = json!({
+            "start_time": window_start,
+            "sort_by": "start_time",
+            "sort_order": "desc",
+            "offset": page * PAGE_SIZE,
+            "limit": PAGE_SIZE
+        });
+        let data = bridge
+            .call_with_timeout("engine::traces::list", query, QUERY_TIMEOUT)
+            .await?;
+        let page_spans = match data.get("spans") {
+            Some(Value::Array(spans)) => spans.clone(),
+            _ => Vec::new(),
+        };
+        let fetched = page_spans.len();
+        let known = page_spans
+            .iter()
+            .filter(|span| {
+                let trace_id = str_field(span, "trace_id").unwrap_or_default();
+                tracker.finished.contains(trace_id)
+                    || tracker.pending.get(trace_id).is_some_and(|t| {
+                        str_field(span, "span_id").is_some_and(|id| t.span_ids.contains(id))
+                    })
+            })
+            .count();
+        spans.extend(page_spans);
+        if fetched < PAGE_SIZE || known == fetched {
+            break;
+        }
+    }
+    Ok(spans)
+}
+
+/// Follow an engine's traces, sending a `root_span` event when a root span is
+/// recorded and a `trace` event once the trace stops receiving spans.
+///
+/// Each poll only looks back [`WINDOW`], so a cursor older than that resumes
+/// with the traces that started within it.
+pub fn stream_traces(bridge: III, filter: TraceFilter, cursor: Cursor) -> EventStream {
+    let tracker = Tracker::new(filter, cursor.timestamp_nanos);
+    run(
+        "trace",
+        (bridge, tracker),
+        |(bridge, mut tracker)| async move {
+            let result = fetch(&bridge, &tracker).await.map(|spans| {
+                let now = Instant::now();
+                let mut events = tracker.observe(spans, now);
+                events.extend(tracker.settle(now));
+                Polled {
+                    events,
+                    again: false,
+                }
+            });
+            ((bridge, tracker), result)
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(trace_id: &str, span_id: &str, parent: Option<&str>, end: u64) -> Value {
+        json!({
+            "trace_id": trace_id,
+            "span_id": span_id,
+            "parent_span_id": parent,
+            "name": span_id,
+            "service_name": "api",
+            "start_time_unix_nano": end - 10,
+            "end_time_unix_nano": end,
+        })
+    }
+
+    #[test]
+    fn observe_reports_each_root_span_once() {
+        let mut tracker = Tracker::new(TraceFilter::default(), 0);
+        let now = Instant::now();
+        let spans = vec![
+            span("t1", "root", None, 100),
+            span("t1", "child", Some("root"), 90),
+        ];
+
+        assert_eq!(tracker.observe(spans.clone(), now).len(), 1);
+        assert!(tracker.observe(spans, now).is_empty());
+        assert_eq!(tracker.pending["t1"].span_ids.len(), 2);
+        assert_eq!(tracker.position(), 99);
+    }
+
+    #[test]
+    fn settle_waits_for_the_trace_to_go_quiet() {
+        let mut tracker = Tracker::new(TraceFilter::default(), 0);
+        let now = Instant::now();
+        tracker.observe(vec![span("t1", "root", None, 100)], now);
+
+        assert!(tracker.settle(now + SETTLE / 2).is_empty());
+        tracker.observe(vec![span("t1", "late", Some("root"), 95)], now + SETTLE / 2);
+        assert!(tracker.settle(now + SETTLE).is_empty());
+        assert_eq!(tracker.settle(now + SETTLE / 2 + SETTLE).len(), 1);
+
+        assert!(tracker.finished.contains("t1"));
+        assert_eq!(tracker.position(), 100);
+        // Spans of a finished trace do not reopen it
+        tracker.observe(
+            vec![span("t1", "later", Some("root"), 98)],
+            now + SETTLE * 3,
+        );
+        assert!(tracker.pending.is_empty());
+    }
+
+    #[test]
+    fn traces_ending_at_the_resume_point_are_not_replayed() {
+        let mut tracker = Tracker::new(TraceFilter::default(), 100);
+        let now = Instant::now();
+        let events = tracker.observe(
+            vec![span("t1", "root", None, 100), span("t2", "root", None, 101)],
+            now,
+        );
+        assert_eq!(events.len(), 1);
+        assert!(tracker.finished.contains("t1"));
+        assert!(tracker.pending["t2"].root.is_some());
+        assert_eq!(tracker.position(), 100);
+    }
+
+    #[test]
+    fn filtered_out_and_orphaned_traces_are_dropped() {
+        let filter = TraceFilter {
+            service: Some("billing".to_string()),
+            ..Default::default()
+        };
+        let mut tracker = Tracker::new(filter, 0);
+        let now = Instant::now();
+        let events = tracker.observe(
+            vec![
+                span("t1", "root", None, 100),
+                span("t2", "child", Some("gone"), 90),
+            ],
+            now,
+        );
+        assert!(events.is_empty());
+        assert!(tracker.finished.contains("t1"));
+
+        assert!(tracker.settle(now + ORPHAN_TTL).is_empty());
+        assert!(tracker.pending.is_empty());
+        assert!(!tracker.finished.contains("t2"));
+    }
+}
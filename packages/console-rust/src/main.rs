@@ -1,16 +1,33 @@
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{Context, Result};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tracing::info;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 use tracing_subscriber::EnvFilter;
 
+mod assets;
+mod auth;
 mod bridge;
+mod config;
+mod live;
+mod proxy;
 mod server;
+mod tls;
+mod util;
 
 #[derive(Parser, Debug)]
 #[command(name = "iii-console")]
 #[command(version)]
 #[command(about = "Developer console for the iii engine", long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Config file (default: ./iii-console.toml, then $XDG_CONFIG_HOME/iii-console/config.toml)
+    #[arg(long, env = "III_CONSOLE_CONFIG", global = true)]
+    config: Option<std::path::PathBuf>,
+
     /// Port to run the console server on
     #[arg(short, long, default_value = "3113")]
     port: u16,
@@ -19,6 +36,10 @@ struct Args {
     #[arg(long, default_value = "127.0.0.1")]
     host: String,
 
+    /// Path prefix to serve the console under, e.g. /iii behind an ingress
+    #[arg(long, env = "III_CONSOLE_BASE_PATH", default_value = "/")]
+    base_path: String,
+
     /// Host where the iii engine is running
     #[arg(long, default_value = "127.0.0.1")]
     engine_host: String,
@@ -35,6 +56,11 @@ struct Args {
     #[arg(long, default_value = "49134")]
     bridge_port: u16,
 
+    /// Let the browser connect to the engine ports directly instead of through
+    /// the console's /engine and /engine-ws proxy
+    #[arg(long, env = "III_CONSOLE_DIRECT_ENGINE")]
+    direct_engine: bool,
+
     /// Disable OpenTelemetry tracing, metrics, and logs export
     #[arg(long, env = "OTEL_DISABLED")]
     no_otel: bool,
@@ -46,6 +72,407 @@ struct Args {
     /// Enable the experimental flow visualization page
     #[arg(long, env = "III_ENABLE_FLOW")]
     enable_flow: bool,
+
+    /// Require this token to sign in to the console and call its bridge functions
+    #[arg(long, env = "III_CONSOLE_TOKEN", hide_env_values = true)]
+    auth_token: Option<String>,
+
+    /// TOML file defining console roles and the users allowed to sign in
+    #[arg(long, env = "III_CONSOLE_ROLES")]
+    roles_config: Option<std::path::PathBuf>,
+
+    /// PEM certificate chain to serve the console over HTTPS (reloaded on SIGHUP)
+    #[arg(long, env = "III_CONSOLE_TLS_CERT")]
+    tls_cert: Option<std::path::PathBuf>,
+
+    /// PEM private key matching --tls-cert
+    #[arg(long, env = "III_CONSOLE_TLS_KEY")]
+    tls_key: Option<std::path::PathBuf>,
+
+    /// Serve HTTPS with an ephemeral self-signed certificate (local use only)
+    #[arg(long, conflicts_with = "tls_cert")]
+    tls_self_signed: bool,
+
+    /// Append audit records to this JSONL file instead of the engine state
+    #[arg(long, env = "III_CONSOLE_AUDIT_LOG")]
+    audit_log: Option<std::path::PathBuf>,
+
+    /// Timeout in milliseconds for engine calls made by console functions
+    #[arg(long, env = "III_CONSOLE_CALL_TIMEOUT_MS", default_value_t = bridge::DEFAULT_TIMEOUT_MS)]
+    call_timeout_ms: u64,
+
+    /// Upper bound in milliseconds for per-request `timeout_ms` overrides
+    #[arg(long, env = "III_CONSOLE_MAX_CALL_TIMEOUT_MS", default_value_t = bridge::DEFAULT_MAX_TIMEOUT_MS)]
+    max_call_timeout_ms: u64,
+
+    /// Additional engine profiles, only configurable in the config file
+    #[arg(skip)]
+    engines: Vec<bridge::EngineProfile>,
+
+    /// Per-function call timeouts, only configurable in the config file
+    #[arg(skip)]
+    function_timeouts_ms: std::collections::HashMap<String, u64>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Inspect the console configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+    /// Export or import state groups through an engine's bridge
+    State {
+        #[command(subcommand)]
+        action: StateCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Print the merged configuration and where each value came from
+    Print,
+}
+
+#[derive(Subcommand, Debug)]
+enum StateCommand {
+    /// Write state groups to a file, or to stdout
+    Export {
+        /// State group to export; repeat for more groups
+        #[arg(short, long = "group", required = true)]
+        groups: Vec<String>,
+
+        /// Export format
+        #[arg(long, default_value = "ndjson", value_parser = ["ndjson", "json"])]
+        format: String,
+
+        /// File to write instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Engine profile to read from
+        #[arg(long)]
+        engine: Option<String>,
+
+        /// Console token or user token to export as; console groups need a role
+        /// allowed to call the function that owns them
+        #[arg(long, env = "III_CONSOLE_EXPORT_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+    },
+    /// Restore state groups from an export file, or from stdin with `-`
+    Import {
+        /// NDJSON or JSON export
+        file: PathBuf,
+
+        /// What to do with keys that already exist
+        #[arg(long, default_value = "fail", value_parser = ["overwrite", "skip-existing", "fail"])]
+        mode: String,
+
+        /// Engine profile to write to
+        #[arg(long)]
+        engine: Option<String>,
+    },
+}
+
+/// Layer the config file under the CLI and env values already parsed into `args`.
+///
+/// Returns a report of the merged settings and their sources.
+fn apply_config_file(args: &mut Args, matches: &clap::ArgMatches) -> Result<String> {
+    let loaded = config::load_file(args.config.as_deref())?;
+    let (path, file) = match loaded {
+        Some((path, file)) => (Some(path), file),
+        None => (None, config::FileConfig::default()),
+    };
+
+    let command = Args::command();
+    let mut r = config::Resolver::new(matches, &command, path.as_deref());
+
+    args.port = r.value("port", "port", args.port, file.port);
+    args.host = r.value("host", "host", args.host.clone(), file.host);
+    args.base_path = r.value(
+        "base_path",
+        "base_path",
+        args.base_path.clone(),
+        file.base_path,
+    );
+    args.engine_host = r.value(
+        "engine.host",
+        "engine_host",
+        args.engine_host.clone(),
+        file.engine.host,
+    );
+    args.engine_port = r.value(
+        "engine.port",
+        "engine_port",
+        args.engine_port,
+        file.engine.port,
+    );
+    args.ws_port = r.value(
+        "engine.ws_port",
+        "ws_port",
+        args.ws_port,
+        file.engine.ws_port,
+    );
+    args.bridge_port = r.value(
+        "engine.bridge_port",
+        "bridge_port",
+        args.bridge_port,
+        file.engine.bridge_port,
+    );
+    args.direct_engine = !r.value(
+        "engine.proxy",
+        "direct_engine",
+        !args.direct_engine,
+        file.engine.proxy,
+    );
+    args.no_otel = !r.value("otel.enabled", "no_otel", !args.no_otel, file.otel.enabled);
+    args.otel_service_name = r.value(
+        "otel.service_name",
+        "otel_service_name",
+        args.otel_service_name.clone(),
+        file.otel.service_name,
+    );
+    args.enable_flow = r.value(
+        "features.enable_flow",
+        "enable_flow",
+        args.enable_flow,
+        file.features.enable_flow,
+    );
+    args.auth_token = r.secret(
+        "auth.token",
+        "auth_token",
+        args.auth_token.take(),
+        file.auth.token,
+    );
+    args.roles_config = r.optional(
+        "auth.roles_config",
+        "roles_config",
+        args.roles_config.take(),
+        file.auth.roles_config,
+    );
+    args.tls_cert = r.optional("tls.cert", "tls_cert", args.tls_cert.take(), file.tls.cert);
+    args.tls_key = r.optional("tls.key", "tls_key", args.tls_key.take(), file.tls.key);
+    args.tls_self_signed = r.value(
+        "tls.self_signed",
+        "tls_self_signed",
+        args.tls_self_signed,
+        file.tls.self_signed,
+    );
+    args.audit_log = r.optional(
+        "audit.log",
+        "audit_log",
+        args.audit_log.take(),
+        file.audit.log,
+    );
+
+    args.call_timeout_ms = r.value(
+        "timeouts.default_ms",
+        "call_timeout_ms",
+        args.call_timeout_ms,
+        file.timeouts.default_ms,
+    );
+    args.max_call_timeout_ms = r.value(
+        "timeouts.max_ms",
+        "max_call_timeout_ms",
+        args.max_call_timeout_ms,
+        file.timeouts.max_ms,
+    );
+
+    config::validate_tls(&args.tls_cert, &args.tls_key, args.tls_self_signed)?;
+    args.base_path = config::normalize_base_path(&args.base_path)?;
+    if args.call_timeout_ms == 0 || args.max_call_timeout_ms == 0 {
+        anyhow::bail!("Call timeouts must be greater than zero");
+    }
+
+    let mut function_timeouts: Vec<_> = file.timeouts.functions.iter().collect();
+    function_timeouts.sort();
+    for (function_id, ms) in function_timeouts {
+        if *ms == 0 {
+            anyhow::bail!("Timeout for '{}' must be greater than zero", function_id);
+        }
+        r.file_only(
+            "timeouts.functions",
+            format!("{{ {:?} = {} }}", function_id, ms),
+        );
+    }
+    args.function_timeouts_ms = file.timeouts.functions;
+
+    let mut names = vec![bridge::DEFAULT_ENGINE.to_string()];
+    for profile in &file.engines {
+        if names.contains(&profile.name) {
+            anyhow::bail!("Duplicate engine profile name '{}'", profile.name);
+        }
+        names.push(profile.name.clone());
+        r.file_only(
+            "engines",
+            format!(
+                "{{ name = {:?}, host = {:?}, port = {}, ws_port = {}, bridge_port = {} }}",
+                profile.name, profile.host, profile.port, profile.ws_port, profile.bridge_port
+            ),
+        );
+    }
+    args.engines = file.engines;
+
+    Ok(r.report())
+}
+
+/// The default engine profile from the `--engine-*` options, followed by the
+/// profiles of the config file.
+/// Authentication configured by the token and roles file.
+fn auth_state(token: Option<String>, roles_config: Option<&Path>) -> Result<auth::AuthState> {
+    let roles = match roles_config {
+        Some(path) => auth::Roles::load(path)?,
+        None => auth::Roles::default(),
+    };
+    Ok(auth::AuthState::new(token, roles))
+}
+
+fn engine_profiles(args: &mut Args) -> Vec<bridge::EngineProfile> {
+    let default_profile = bridge::EngineProfile {
+        name: bridge::DEFAULT_ENGINE.to_string(),
+        host: args.engine_host.clone(),
+        port: args.engine_port,
+        ws_port: args.ws_port,
+        bridge_port: args.bridge_port,
+    };
+    std::iter::once(default_profile)
+        .chain(args.engines.drain(..))
+        .collect()
+}
+
+/// Run a `state` subcommand over its own bridge connection to one engine.
+async fn run_state_command(action: StateCommand, args: &mut Args) -> Result<()> {
+    let (StateCommand::Export { engine, .. } | StateCommand::Import { engine, .. }) = &action;
+    let name = engine.as_deref().unwrap_or(bridge::DEFAULT_ENGINE);
+    let profile = engine_profiles(args)
+        .into_iter()
+        .find(|profile| profile.name == name)
+        .with_context(|| format!("Unknown engine '{}'", name))?;
+    let timeout = Duration::from_millis(args.call_timeout_ms);
+
+    if let StateCommand::Export { groups, token, .. } = &action {
+        let (auth, principal) = export_principal(args, token.as_deref())?;
+        bridge::check_readable(&auth, &principal, groups.iter().map(String::as_str))
+            .map_err(|err| anyhow::anyhow!(err.message))?;
+    }
+
+    // Each command connects once its own input checks out
+    let iii = iii_sdk::III::new(&profile.bridge_url());
+    let result = match action {
+        StateCommand::Export {
+            groups,
+            format,
+            output,
+            ..
+        } => export_state(&iii, groups, &format, output, timeout).await,
+        StateCommand::Import { file, mode, .. } => import_state(&iii, &file, &mode, timeout).await,
+    };
+    iii.shutdown_async().await;
+    result
+}
+
+/// The principal a CLI export reads as. Without a token, an export from a
+/// console with authentication enabled gets no role, so it can only read
+/// groups outside the console's own.
+fn export_principal(
+    args: &Args,
+    token: Option<&str>,
+) -> Result<(auth::AuthState, auth::Principal)> {
+    let auth = auth_state(args.auth_token.clone(), args.roles_config.as_deref())?;
+    let principal = match token {
+        Some(token) => auth
+            .authenticate_credential(token)
+            .context("The export token is not accepted by the console")?,
+        None => auth
+            .authenticate_credential("")
+            .unwrap_or_else(|| auth::Principal {
+                name: "cli".to_string(),
+                role: String::new(),
+            }),
+    };
+    Ok((auth, principal))
+}
+
+fn engine_error(error: iii_sdk::IIIError) -> anyhow::Error {
+    anyhow::anyhow!(bridge::ConsoleError::from(error).message)
+}
+
+async fn export_state(
+    iii: &iii_sdk::III,
+    groups: Vec<String>,
+    format: &str,
+    output: Option<PathBuf>,
+    timeout: Duration,
+) -> Result<()> {
+    iii.connect().await?;
+    let mut exported = Vec::new();
+    for group in groups {
+        let records = bridge::export_group(iii, &group, timeout)
+            .await
+            .map_err(engine_error)
+            .with_context(|| format!("Failed to export '{}'", group))?;
+        info!("Exported {} items from '{}'", records.len(), group);
+        exported.push((group, records));
+    }
+
+    let text = if format == "json" {
+        format!(
+            "{}\n",
+            serde_json::to_string_pretty(&bridge::to_document(exported))?
+        )
+    } else {
+        let records: Vec<_> = exported.into_iter().flat_map(|(_, r)| r).collect();
+        bridge::to_ndjson(&records)
+    };
+    match output {
+        Some(path) => std::fs::write(&path, text)
+            .with_context(|| format!("Failed to write {}", path.display()))?,
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+async fn import_state(
+    iii: &iii_sdk::III,
+    file: &PathBuf,
+    mode: &str,
+    timeout: Duration,
+) -> Result<()> {
+    let text = if file.as_os_str() == "-" {
+        std::io::read_to_string(std::io::stdin()).context("Failed to read stdin")?
+    } else {
+        std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?
+    };
+    let records = bridge::parse_export_text(&text)
+        .map_err(|reason| anyhow::anyhow!("{} {}", file.display(), reason))?;
+    let mode = bridge::ConflictMode::parse(mode).context("Unknown import mode")?;
+
+    iii.connect().await?;
+
+    match bridge::import(iii, records, mode, timeout).await {
+        Ok(report) => {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            if report.failed > 0 {
+                anyhow::bail!("{} items failed to import", report.failed);
+            }
+            Ok(())
+        }
+        Err(bridge::ImportError::Engine(err)) => Err(engine_error(err).context("Import failed")),
+        Err(bridge::ImportError::Protected(groups)) => anyhow::bail!(
+            "Console groups cannot be imported: {}; nothing was imported",
+            groups.join(", ")
+        ),
+        Err(bridge::ImportError::Conflict(conflicts)) => {
+            for (group, key) in &conflicts {
+                eprintln!("{}/{} already exists", group, key);
+            }
+            anyhow::bail!(
+                "{} keys already exist; nothing was imported. Pass --mode overwrite or skip-existing",
+                conflicts.len()
+            )
+        }
+    }
 }
 
 async fn shutdown_signal() {
@@ -74,66 +501,145 @@ async fn shutdown_signal() {
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize tracing
+    let matches = Args::command().get_matches();
+
+    // Initialize tracing, on stderr for state commands since exports may go to stdout
+    let writer = if matches.subcommand_name() == Some("state") {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
     tracing_subscriber::fmt()
         .with_env_filter(
             EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
         )
+        .with_writer(writer)
         .init();
 
-    let args = Args::parse();
+    let mut args = Args::from_arg_matches(&matches)?;
+    let report = apply_config_file(&mut args, &matches)?;
+
+    match args.command.take() {
+        Some(Command::Config {
+            action: ConfigCommand::Print,
+        }) => {
+            print!("{}", report);
+            return Ok(());
+        }
+        Some(Command::State { action }) => return run_state_command(action, &mut args).await,
+        None => {}
+    }
 
     info!("Starting iii-console on {}:{}", args.host, args.port);
-    info!(
-        "Connecting to engine at {}:{} (WS: {})",
-        args.engine_host, args.engine_port, args.ws_port
-    );
 
-    // Initialize bridge connection to iii engine
-    let bridge_url = format!("ws://{}:{}", args.engine_host, args.bridge_port);
-    let bridge = iii_sdk::III::new(&bridge_url);
+    let profiles = engine_profiles(&mut args);
 
-    // Configure OpenTelemetry (enabled by default, use --no-otel to disable)
+    // Initialize one bridge connection per engine profile
+    let mut connections = Vec::new();
+    for profile in profiles {
+        info!(
+            "Connecting to engine '{}' at {}:{} (WS: {})",
+            profile.name, profile.host, profile.port, profile.ws_port
+        );
+        let bridge = iii_sdk::III::new(&profile.bridge_url());
+        connections.push((profile, bridge));
+    }
+    let engines = bridge::Engines::new(connections);
+    let (default_profile, default_bridge) = engines.bridges().next().expect("default engine");
+    let (default_bridge_url, default_bridge) =
+        (default_profile.bridge_url(), default_bridge.clone());
+
+    // Configure OpenTelemetry (enabled by default, use --no-otel to disable).
+    // Telemetry is exported through the default engine only.
     if !args.no_otel {
         info!(
             "OpenTelemetry enabled (service: {})",
             args.otel_service_name
         );
-        bridge.set_otel_config(iii_sdk::OtelConfig {
+        default_bridge.set_otel_config(iii_sdk::OtelConfig {
             enabled: Some(true),
             service_name: Some(args.otel_service_name),
             service_version: Some(env!("CARGO_PKG_VERSION").to_string()),
-            engine_ws_url: Some(bridge_url.clone()),
+            engine_ws_url: Some(default_bridge_url),
             ..Default::default()
         });
     } else {
         info!("OpenTelemetry disabled");
     }
 
-    // Register ALL functions and triggers BEFORE connecting
-    // This ensures they're queued for sending when connection establishes
-    bridge::register_functions(&bridge);
-
-    if let Err(e) = bridge::register_triggers(&bridge) {
-        tracing::warn!("Trigger registration failed: {}", e);
+    let auth = auth_state(args.auth_token, args.roles_config.as_deref())?;
+    if auth.enabled() {
+        info!("Console authentication enabled");
     }
 
-    // Now connect - SDK handles reconnection internally
-    // If OTEL is configured, the SDK initializes it during connect()
-    if let Err(e) = bridge.connect().await {
-        tracing::warn!(
-            "Initial bridge connection failed: {}. Will retry automatically.",
-            e
-        );
+    let audit = match args.audit_log {
+        Some(path) => {
+            info!("Writing audit records to {}", path.display());
+            bridge::AuditLog::file(path)
+        }
+        None => bridge::AuditLog::state(&default_bridge),
+    };
+
+    let services = bridge::Services {
+        auth: auth.clone(),
+        audit,
+        engines: engines.clone(),
+        timeouts: bridge::Timeouts::new(
+            args.call_timeout_ms,
+            args.max_call_timeout_ms,
+            args.function_timeouts_ms,
+        ),
+        jobs: bridge::Jobs::default(),
+        state_stats: bridge::StateStats::default(),
+        schemas: bridge::Schemas::default(),
+    };
+
+    for (profile, engine_bridge) in engines.bridges() {
+        // Register ALL functions and triggers BEFORE connecting
+        // This ensures they're queued for sending when connection establishes
+        bridge::register_functions(&profile.name, engine_bridge, &services);
+
+        if let Err(e) = bridge::register_triggers(engine_bridge) {
+            tracing::warn!("Trigger registration failed for '{}': {}", profile.name, e);
+        }
+
+        // Now connect - SDK handles reconnection internally
+        // If OTEL is configured, the SDK initializes it during connect()
+        if let Err(e) = engine_bridge.connect().await {
+            tracing::warn!(
+                "Initial bridge connection to '{}' failed: {}. Will retry automatically.",
+                profile.name,
+                e
+            );
+        }
     }
+    engines.spawn_health_checks();
+
+    let tls = match (args.tls_cert, args.tls_key) {
+        (Some(cert), Some(key)) => Some(tls::TlsConfig::Files { cert, key }),
+        _ if args.tls_self_signed => Some(tls::TlsConfig::SelfSigned),
+        _ => None,
+    };
+
+    let engine_proxy = if args.direct_engine {
+        info!("Frontend connects to the engine directly");
+        None
+    } else {
+        Some(proxy::EngineProxy::new(engines.clone()))
+    };
 
     let config = server::ServerConfig {
         port: args.port,
         host: args.host,
+        base_path: args.base_path,
         engine_host: args.engine_host,
         engine_port: args.engine_port,
         ws_port: args.ws_port,
         enable_flow: args.enable_flow,
+        auth,
+        tls,
+        engines: engines.clone(),
+        engine_proxy,
     };
 
     // Run server with graceful shutdown
@@ -143,7 +649,9 @@ async fn main() -> Result<()> {
         result = server => result,
         _ = shutdown_signal() => {
             tracing::info!("Shutdown signal received, cleaning up...");
-            bridge.shutdown_async().await;
+            for (_, engine_bridge) in engines.bridges() {
+                engine_bridge.shutdown_async().await;
+            }
             Ok(())
         }
     }
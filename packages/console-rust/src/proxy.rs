@@ -0,0 +1,241 @@
+use axum::body::Body;
+use axum::extract::ws::{self, WebSocket, WebSocketUpgrade};
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, Request, Uri};
+use axum::response::Response;
+use futures_util::{SinkExt, StreamExt};
+use hyper_util::client::legacy::{connect::HttpConnector, Client};
+use hyper_util::rt::TokioExecutor;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::{self, protocol::frame::coding::CloseCode};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::bridge::{ConsoleError, EngineProfile, Engines, ENGINE_HEADER};
+
+/// Path under which the engine REST API is served from the console origin.
+pub const ENGINE_PATH: &str = "/engine";
+
+/// Path of the engine stream WebSocket on the console origin.
+pub const ENGINE_WS_PATH: &str = "/engine-ws";
+
+/// Headers that describe a single connection and are never forwarded.
+const HOP_BY_HOP_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+type EngineSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Forwards engine REST and WebSocket traffic, so the browser only needs to
+/// reach the console.
+#[derive(Clone)]
+pub struct EngineProxy {
+    client: Client<HttpConnector, Body>,
+    engines: Engines,
+}
+
+impl EngineProxy {
+    pub fn new(engines: Engines) -> Self {
+        Self {
+            client: Client::builder(TokioExecutor::new()).build_http(),
+            engines,
+        }
+    }
+
+    fn profile(&self, name: Option<&str>) -> Result<&EngineProfile, ConsoleError> {
+        self.engines
+            .profile(name)
+            .map_err(|message| ConsoleError::not_found("engine_not_found", message))
+    }
+
+    /// Forward a request under `/engine` to the REST API of the engine chosen
+    /// by the `x-iii-engine` header, streaming the response back.
+    pub async fn forward(&self, request: Request<Body>) -> Result<Response, ConsoleError> {
+        let engine = request
+            .headers()
+            .get(ENGINE_HEADER)
+            .and_then(|v| v.to_str().ok());
+        let profile = self.profile(engine)?;
+
+        let path = engine_path(request.uri(), ENGINE_PATH);
+        let uri: Uri = format!("http://{}:{}{}", profile.host, profile.port, path)
+            .parse()
+            .map_err(|_| ConsoleError::new(400, "invalid_path", "Invalid engine path"))?;
+        let name = profile.name.clone();
+
+        let (mut parts, body) = request.into_parts();
+        let original_host = parts.headers.remove(header::HOST);
+        strip_hop_by_hop(&mut parts.headers);
+        parts.headers.remove(ENGINE_HEADER);
+        if let Some(host) = original_host {
+            parts.headers.insert("x-forwarded-host", host);
+        }
+        parts
+            .headers
+            .insert("x-forwarded-prefix", HeaderValue::from_static(ENGINE_PATH));
+        parts.uri = uri;
+
+        let response = self
+            .client
+            .request(Request::from_parts(parts, body))
+            .await
+            .map_err(|e| unreachable(&name, e))?;
+
+        let (mut parts, body) = response.into_parts();
+        strip_hop_by_hop(&mut parts.headers);
+        Ok(Response::from_parts(parts, Body::new(body)))
+    }
+
+    /// Open a WebSocket to the stream port of the engine chosen by the
+    /// `engine` query parameter, then upgrade the browser connection and pump
+    /// messages both ways until either side closes.
+    pub async fn upgrade(
+        &self,
+        upgrade: WebSocketUpgrade,
+        uri: &Uri,
+    ) -> Result<Response, ConsoleError> {
+        let engine = uri.query().and_then(|query| {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("engine="))
+        });
+        let profile = self.profile(engine)?;
+
+        let path = engine_path(&without_engine_param(uri), ENGINE_WS_PATH);
+        let url = format!("ws://{}:{}{}", profile.host, profile.ws_port, path);
+        let (engine_socket, _) = tokio_tungstenite::connect_async(url.as_str())
+            .await
+            .map_err(|e| unreachable(&profile.name, e))?;
+
+        Ok(upgrade.on_upgrade(move |socket| pump(socket, engine_socket)))
+    }
+}
+
+fn unreachable(engine: &str, error: impl std::fmt::Display) -> ConsoleError {
+    ConsoleError::new(
+        502,
+        "engine_unreachable",
+        format!("Engine '{}' is unreachable: {}", engine, error),
+    )
+    .retryable()
+}
+
+/// The path and query to request from the engine, with the console prefix removed.
+fn engine_path(uri: &Uri, prefix: &str) -> String {
+    let path = uri.path().strip_prefix(prefix).unwrap_or(uri.path());
+    let path = if path.starts_with('/') {
+        path.to_string()
+    } else {
+        format!("/{}", path)
+    };
+    match uri.query() {
+        Some(query) if !query.is_empty() => format!("{}?{}", path, query),
+        _ => path,
+    }
+}
+
+/// The URI without the console's own `engine` query parameter.
+fn without_engine_param(uri: &Uri) -> Uri {
+    let Some(query) = uri.query() else {
+        return uri.clone();
+    };
+    let query: Vec<&str> = query
+        .split('&')
+        .filter(|pair| !pair.starts_with("engine="))
+        .collect();
+    let path_and_query = if query.is_empty() {
+        uri.path().to_string()
+    } else {
+        format!("{}?{}", uri.path(), query.join("&"))
+    };
+    path_and_query.parse().unwrap_or_else(|_| uri.clone())
+}
+
+/// Remove hop-by-hop headers, including those named by `Connection`.
+fn strip_hop_by_hop(headers: &mut HeaderMap) {
+    let named: Vec<HeaderName> = headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect();
+    for name in named {
+        headers.remove(name);
+    }
+    for name in HOP_BY_HOP_HEADERS {
+        headers.remove(*name);
+    }
+}
+
+/// Relay messages between the browser and the engine until either side closes.
+///
+/// Pings and pongs are answered by each connection itself and not relayed.
+async fn pump(browser: WebSocket, engine: EngineSocket) {
+    let (mut browser_tx, mut browser_rx) = browser.split();
+    let (mut engine_tx, mut engine_rx) = engine.split();
+
+    let to_engine = async {
+        while let Some(Ok(message)) = browser_rx.next().await {
+            let Some(message) = to_engine_message(message) else {
+                continue;
+            };
+            if engine_tx.send(message).await.is_err() {
+                break;
+            }
+        }
+        let _ = engine_tx.close().await;
+    };
+    let to_browser = async {
+        while let Some(Ok(message)) = engine_rx.next().await {
+            let Some(message) = to_browser_message(message) else {
+                continue;
+            };
+            if browser_tx.send(message).await.is_err() {
+                break;
+            }
+        }
+        let _ = browser_tx.close().await;
+    };
+
+    tokio::select! {
+        _ = to_engine => {},
+        _ = to_browser => {},
+    }
+}
+
+fn to_engine_message(message: ws::Message) -> Option<tungstenite::Message> {
+    match message {
+        ws::Message::Text(text) => Some(tungstenite::Message::text(text.as_str())),
+        ws::Message::Binary(data) => Some(tungstenite::Message::Binary(data)),
+        ws::Message::Close(frame) => Some(tungstenite::Message::Close(frame.map(|frame| {
+            tungstenite::protocol::CloseFrame {
+                code: CloseCode::from(frame.code),
+                reason: frame.reason.as_str().into(),
+            }
+        }))),
+        ws::Message::Ping(_) | ws::Message::Pong(_) => None,
+    }
+}
+
+fn to_browser_message(message: tungstenite::Message) -> Option<ws::Message> {
+    match message {
+        tungstenite::Message::Text(text) => Some(ws::Message::Text(text.as_str().into())),
+        tungstenite::Message::Binary(data) => Some(ws::Message::Binary(data)),
+        tungstenite::Message::Close(frame) => {
+            Some(ws::Message::Close(frame.map(|frame| ws::CloseFrame {
+                code: frame.code.into(),
+                reason: frame.reason.as_str().into(),
+            })))
+        }
+        tungstenite::Message::Ping(_)
+        | tungstenite::Message::Pong(_)
+        | tungstenite::Message::Frame(_) => None,
+    }
+}
@@ -1,39 +1,87 @@
 use anyhow::Result;
 use axum::{
     body::Body,
-    extract::Path,
-    http::{header, HeaderValue, StatusCode},
-    response::{Html, IntoResponse, Response},
-    routing::get,
-    Json, Router,
+    extract::{ws::WebSocketUpgrade, Extension, Path, Query, Request},
+    http::{header, HeaderMap, HeaderValue, StatusCode, Uri},
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{any, get, post},
+    Form, Json, Router,
 };
-use rust_embed::Embed;
+use futures_util::{stream, StreamExt};
+use serde::Deserialize;
 use serde_json::json;
 use std::net::SocketAddr;
+use std::time::Duration;
 use tower_http::cors::CorsLayer;
 use tracing::info;
 
-#[derive(Embed)]
-#[folder = "assets/"]
-struct Assets;
+use crate::assets::{self, Assets};
+use crate::auth::{session_from_cookie, AuthState, Principal, SESSION_COOKIE, SESSION_TTL};
+use crate::bridge::{self, ConsoleError, Engines, ENGINE_HEADER};
+use crate::live::{self, Cursor, LogFilter, TraceFilter};
+use crate::proxy::{EngineProxy, ENGINE_PATH, ENGINE_WS_PATH};
+use crate::tls::{self, TlsConfig};
 
 /// Server configuration
 pub struct ServerConfig {
     pub port: u16,
     pub host: String,
+    /// Path prefix the console is served under, `/` or `/a/b`
+    pub base_path: String,
     pub engine_host: String,
     pub engine_port: u16,
     pub ws_port: u16,
     pub enable_flow: bool,
+    pub auth: AuthState,
+    pub tls: Option<TlsConfig>,
+    pub engines: Engines,
+    /// Forwards engine traffic through the console origin, unless the
+    /// frontend connects to the engine directly
+    pub engine_proxy: Option<EngineProxy>,
+}
+
+impl ServerConfig {
+    /// A console path, such as `/login`, under the base path
+    fn url(&self, path: &str) -> String {
+        match self.base_path.as_str() {
+            "/" => path.to_string(),
+            base => format!("{}{}", base, path),
+        }
+    }
+
+    /// Where the frontend reaches the engine REST API and stream WebSocket:
+    /// paths on the console origin when proxied, `null` when direct.
+    fn engine_urls(&self) -> (Option<String>, Option<String>) {
+        match self.engine_proxy {
+            Some(_) => (Some(self.url(ENGINE_PATH)), Some(self.url(ENGINE_WS_PATH))),
+            None => (None, None),
+        }
+    }
+
+    /// Attributes appended to the session cookie. The cookie is scoped to the
+    /// base path unless the browser calls the engine directly, whose
+    /// `/_console` paths lie outside it.
+    fn cookie_attributes(&self) -> String {
+        let path = match self.engine_proxy {
+            Some(_) => self.base_path.as_str(),
+            None => "/",
+        };
+        let secure = if self.tls.is_some() { "; Secure" } else { "" };
+        format!("Path={}; HttpOnly; SameSite=Lax{}", path, secure)
+    }
 }
 
 /// Generate index.html with runtime config injected
 fn get_index_html(config: &ServerConfig) -> String {
+    let (engine_url, engine_ws_url) = config.engine_urls();
     let runtime_config = json!({
-        "basePath": "/",
+        "basePath": config.base_path,
         "engineHost": config.engine_host,
         "enginePort": config.engine_port,
         "wsPort": config.ws_port,
+        "engineUrl": engine_url,
+        "engineWsUrl": engine_ws_url,
         "enableFlow": config.enable_flow,
     });
 
@@ -63,51 +111,504 @@ fn get_index_html(config: &ServerConfig) -> String {
         config_json
     );
 
-    index_content.replace("</head>", &format!("{}</head>", config_script))
+    rewrite_asset_urls(&index_content, &config.url(""))
+        .replace("</head>", &format!("{}</head>", config_script))
+}
+
+/// Point the root-relative (`/x`) and relative (`./x`) `src` and `href`
+/// URLs of index.html at the base path
+fn rewrite_asset_urls(html: &str, prefix: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find("=\"") {
+        let (head, tail) = rest.split_at(start + 2);
+        out.push_str(head);
+        rest = tail;
+        if !head.ends_with("src=\"") && !head.ends_with("href=\"") {
+            continue;
+        }
+        if let Some(relative) = tail.strip_prefix("./") {
+            out.push_str(prefix);
+            out.push('/');
+            rest = relative;
+        } else if tail.starts_with('/') && !tail.starts_with("//") {
+            out.push_str(prefix);
+        }
+    }
+    out.push_str(rest);
+    out
 }
 
 /// Serve the /api/config endpoint with runtime configuration
 async fn serve_config(
     axum::extract::State(config): axum::extract::State<std::sync::Arc<ServerConfig>>,
+    Extension(principal): Extension<Principal>,
 ) -> Json<serde_json::Value> {
+    let (engine_url, engine_ws_url) = config.engine_urls();
     Json(json!({
         "engineHost": config.engine_host,
         "enginePort": config.engine_port,
         "wsPort": config.ws_port,
+        "engineUrl": engine_url,
+        "engineWsUrl": engine_ws_url,
         "consolePort": config.port,
         "version": env!("CARGO_PKG_VERSION"),
-        "enableFlow": config.enable_flow
+        "enableFlow": config.enable_flow,
+        "authEnabled": config.auth.enabled(),
+        "user": principal.name,
+        "role": principal.role,
+        "allowedFunctions": config.auth.roles().allowlist(&principal.role),
+        "engines": config.engines.describe()
     }))
 }
 
+/// Serve the connection status of every configured engine
+async fn serve_engines(
+    axum::extract::State(config): axum::extract::State<std::sync::Arc<ServerConfig>>,
+) -> Json<serde_json::Value> {
+    Json(json!({ "engines": config.engines.describe() }))
+}
+
+/// Console function whose role permission also covers the live log stream
+const LOGS_FUNCTION: &str = "engine::console::otel_logs_list";
+
+/// Console function whose role permission also covers the live trace stream
+const TRACES_FUNCTION: &str = "engine::console::otel_traces_list";
+
+/// Console function whose role permission also covers the streamed state export
+const STATE_EXPORT_FUNCTION: &str = "engine::console::state_export";
+
+/// Timeout for listing each group of a streamed state export
+const STATE_EXPORT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Render a console error envelope as an HTTP response
+fn console_error_response(error: ConsoleError) -> Response {
+    let status = StatusCode::from_u16(error.status_code).unwrap_or(StatusCode::BAD_REQUEST);
+    (status, Json(json!({ "error": error }))).into_response()
+}
+
+#[derive(Deserialize)]
+struct LogStreamQuery {
+    engine: Option<String>,
+    /// Minimum severity, as a level name or OTEL severity number
+    severity: Option<String>,
+    service: Option<String>,
+    trace_id: Option<String>,
+    /// Substring of the log body
+    q: Option<String>,
+    /// Unix timestamp in milliseconds to replay records from
+    since: Option<u64>,
+}
+
+/// Stream new OTEL log records as Server-Sent Events
+async fn stream_logs(
+    axum::extract::State(config): axum::extract::State<std::sync::Arc<ServerConfig>>,
+    Extension(principal): Extension<Principal>,
+    headers: HeaderMap,
+    Query(query): Query<LogStreamQuery>,
+) -> Response {
+    let bridge = match stream_bridge(
+        &config,
+        &principal,
+        &headers,
+        query.engine.as_deref(),
+        LOGS_FUNCTION,
+    ) {
+        Ok(bridge) => bridge,
+        Err(error) => return console_error_response(error),
+    };
+
+    let non_empty = |value: Option<String>| value.filter(|v| !v.is_empty());
+    let severity_min = match non_empty(query.severity) {
+        Some(level) => match live::severity_number(&level) {
+            Some(number) => Some(number),
+            None => {
+                return console_error_response(ConsoleError::invalid_parameter(
+                    "severity",
+                    "must be a level name such as warn, or an OTEL severity number",
+                ))
+            }
+        },
+        None => None,
+    };
+    let filter = LogFilter {
+        severity_min,
+        service: non_empty(query.service),
+        trace_id: non_empty(query.trace_id),
+        text: non_empty(query.q),
+    };
+
+    live::stream_logs(bridge, filter, stream_cursor(&headers, query.since)).into_response()
+}
+
+#[derive(Deserialize)]
+struct TraceStreamQuery {
+    engine: Option<String>,
+    service: Option<String>,
+    function_id: Option<String>,
+    min_duration_ms: Option<u64>,
+    /// Unix timestamp in milliseconds to replay traces from
+    since: Option<u64>,
+}
+
+/// Stream new root spans and completed traces as Server-Sent Events
+async fn stream_traces(
+    axum::extract::State(config): axum::extract::State<std::sync::Arc<ServerConfig>>,
+    Extension(principal): Extension<Principal>,
+    headers: HeaderMap,
+    Query(query): Query<TraceStreamQuery>,
+) -> Response {
+    let bridge = match stream_bridge(
+        &config,
+        &principal,
+        &headers,
+        query.engine.as_deref(),
+        TRACES_FUNCTION,
+    ) {
+        Ok(bridge) => bridge,
+        Err(error) => return console_error_response(error),
+    };
+
+    let non_empty = |value: Option<String>| value.filter(|v| !v.is_empty());
+    let filter = TraceFilter {
+        service: non_empty(query.service),
+        function_id: non_empty(query.function_id),
+        min_duration_ms: query.min_duration_ms,
+    };
+
+    live::stream_traces(bridge, filter, stream_cursor(&headers, query.since)).into_response()
+}
+
+#[derive(Deserialize)]
+struct StateExportQuery {
+    engine: Option<String>,
+    /// Comma-separated state group IDs
+    #[serde(default)]
+    groups: String,
+}
+
+/// Stream state groups as an NDJSON download, one group at a time.
+///
+/// A group that fails to list aborts the response, so a partial export never
+/// looks complete.
+async fn export_states(
+    axum::extract::State(config): axum::extract::State<std::sync::Arc<ServerConfig>>,
+    Extension(principal): Extension<Principal>,
+    headers: HeaderMap,
+    Query(query): Query<StateExportQuery>,
+) -> Response {
+    let bridge = match stream_bridge(
+        &config,
+        &principal,
+        &headers,
+        query.engine.as_deref(),
+        STATE_EXPORT_FUNCTION,
+    ) {
+        Ok(bridge) => bridge,
+        Err(error) => return console_error_response(error),
+    };
+    let groups: Vec<String> = query
+        .groups
+        .split(',')
+        .map(str::trim)
+        .filter(|g| !g.is_empty())
+        .map(String::from)
+        .collect();
+    if groups.is_empty() {
+        return console_error_response(ConsoleError::invalid_parameter(
+            "groups",
+            "must name at least one group",
+        ));
+    }
+    if let Err(err) =
+        bridge::check_readable(&config.auth, &principal, groups.iter().map(String::as_str))
+    {
+        return console_error_response(err);
+    }
+
+    let lines = stream::iter(groups).then(move |group| {
+        let bridge = bridge.clone();
+        async move {
+            bridge::export_group(&bridge, &group, STATE_EXPORT_TIMEOUT)
+                .await
+                .map(|records| bridge::to_ndjson(&records))
+                .map_err(|err| std::io::Error::other(ConsoleError::from(err).message))
+        }
+    });
+    (
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"state-export.ndjson\"",
+            ),
+        ],
+        Body::from_stream(lines),
+    )
+        .into_response()
+}
+
+/// The engine a streamed response reads from, once the caller's role allows
+/// the console function the stream stands in for.
+fn stream_bridge(
+    config: &ServerConfig,
+    principal: &Principal,
+    headers: &HeaderMap,
+    engine: Option<&str>,
+    function_id: &str,
+) -> Result<iii_sdk::III, ConsoleError> {
+    if !config.auth.allows(principal, function_id) {
+        return Err(ConsoleError::new(
+            403,
+            "forbidden",
+            format!("Not allowed to call {}", function_id),
+        )
+        .with_details(json!({ "function_id": function_id })));
+    }
+
+    let engine = headers
+        .get(ENGINE_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .or(engine);
+    config
+        .engines
+        .resolve(engine)
+        .map(|(_, bridge)| bridge)
+        .map_err(|message| ConsoleError::not_found("engine_not_found", message))
+}
+
+/// Where a live stream starts, from the `Last-Event-ID` a reconnecting
+/// EventSource sends and the `since` query parameter.
+fn stream_cursor(headers: &HeaderMap, since: Option<u64>) -> Cursor {
+    let last_event_id = headers.get("last-event-id").and_then(|v| v.to_str().ok());
+    Cursor::resume(last_event_id, since)
+}
+
+/// Forward a request under `/engine` to the engine REST API
+async fn proxy_engine(
+    axum::extract::State(config): axum::extract::State<std::sync::Arc<ServerConfig>>,
+    request: Request,
+) -> Response {
+    let Some(proxy) = &config.engine_proxy else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    match proxy.forward(request).await {
+        Ok(response) => response,
+        Err(error) => console_error_response(error),
+    }
+}
+
+/// Connect a browser WebSocket on `/engine-ws` to the engine stream port
+async fn proxy_engine_ws(
+    axum::extract::State(config): axum::extract::State<std::sync::Arc<ServerConfig>>,
+    upgrade: WebSocketUpgrade,
+    uri: Uri,
+) -> Response {
+    let Some(proxy) = &config.engine_proxy else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    match proxy.upgrade(upgrade, &uri).await {
+        Ok(response) => response,
+        Err(error) => console_error_response(error),
+    }
+}
+
+/// Whether a path is answered with JSON rather than a page, so an
+/// unauthenticated request gets a 401 instead of a redirect
+fn is_api_path(path: &str) -> bool {
+    let under = |prefix: &str| {
+        path == prefix
+            || path
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.starts_with('/'))
+    };
+    path.starts_with("/api/") || under(ENGINE_PATH) || under(ENGINE_WS_PATH)
+}
+
+const LOGIN_HTML: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8" />
+    <meta name="viewport" content="width=device-width, initial-scale=1.0" />
+    <title>iii Console - Sign in</title>
+    <style>
+        body { margin: 0; min-height: 100vh; display: flex; align-items: center; justify-content: center; background: #0A0A0A; color: #F4F4F4; font-family: ui-monospace, monospace; }
+        form { display: flex; flex-direction: column; gap: 12px; width: 320px; }
+        input { padding: 8px 10px; background: #141414; border: 1px solid #2A2A2A; border-radius: 4px; color: #F4F4F4; font: inherit; }
+        button { padding: 8px 10px; background: #F3F724; border: 0; border-radius: 4px; color: #000; font: inherit; font-weight: 600; cursor: pointer; }
+        .error { color: #F87171; font-size: 12px; }
+        .hint { color: #9CA3AF; font-size: 12px; }
+    </style>
+</head>
+<body>
+    <form method="post" action="{{action}}">
+        <div>iii Console</div>
+        <div class="hint">Enter the console access token to continue.</div>
+        {{error}}
+        <input type="password" name="token" placeholder="Access token" autofocus required />
+        <button type="submit">Sign in</button>
+    </form>
+</body>
+</html>"#;
+
+#[derive(Deserialize)]
+struct LoginPageQuery {
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LoginForm {
+    token: String,
+}
+
+/// Serve the login page
+async fn serve_login(
+    axum::extract::State(config): axum::extract::State<std::sync::Arc<ServerConfig>>,
+    Query(query): Query<LoginPageQuery>,
+) -> Html<String> {
+    let error = if query.error.is_some() {
+        r#"<div class="error">Invalid access token</div>"#
+    } else {
+        ""
+    };
+    Html(
+        LOGIN_HTML
+            .replace("{{action}}", &config.url("/api/auth/login"))
+            .replace("{{error}}", error),
+    )
+}
+
+/// Exchange the access token for a session cookie
+async fn login(
+    axum::extract::State(config): axum::extract::State<std::sync::Arc<ServerConfig>>,
+    Form(form): Form<LoginForm>,
+) -> Response {
+    match config.auth.login(&form.token) {
+        Some(session_id) => {
+            let cookie = format!(
+                "{}={}; {}; Max-Age={}",
+                SESSION_COOKIE,
+                session_id,
+                config.cookie_attributes(),
+                SESSION_TTL.as_secs()
+            );
+            (
+                [(header::SET_COOKIE, cookie)],
+                Redirect::to(&config.url("/")),
+            )
+                .into_response()
+        }
+        None => {
+            tracing::warn!("Rejected console login attempt");
+            Redirect::to(&config.url("/login?error=1")).into_response()
+        }
+    }
+}
+
+/// Drop the current session and clear the session cookie
+async fn logout(
+    axum::extract::State(config): axum::extract::State<std::sync::Arc<ServerConfig>>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(session_id) = headers
+        .get(header::COOKIE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(session_from_cookie)
+    {
+        config.auth.logout(session_id);
+    }
+
+    let cookie = format!(
+        "{}=; {}; Max-Age=0",
+        SESSION_COOKIE,
+        config.cookie_attributes()
+    );
+    (
+        [(header::SET_COOKIE, cookie)],
+        Redirect::to(&config.url("/login")),
+    )
+        .into_response()
+}
+
+/// Reject unauthenticated requests: API calls get a 401, page loads go to the login page
+async fn require_auth(
+    axum::extract::State(config): axum::extract::State<std::sync::Arc<ServerConfig>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path();
+    if path == "/login" || path.starts_with("/api/auth/") {
+        return next.run(request).await;
+    }
+
+    let headers = request.headers();
+    let principal = config
+        .auth
+        .authenticate_headers(|name| headers.get(name).and_then(|v| v.to_str().ok()));
+
+    if let Some(principal) = principal {
+        request.extensions_mut().insert(principal);
+        next.run(request).await
+    } else if is_api_path(path) {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": {
+                    "code": "unauthorized",
+                    "message": "Authentication required",
+                    "details": null,
+                    "retryable": false,
+                    "trace_id": null
+                },
+                "authEnabled": true
+            })),
+        )
+            .into_response()
+    } else {
+        Redirect::to(&config.url("/login")).into_response()
+    }
+}
+
+/// index.html with runtime config, revalidated on every load so a new
+/// build is picked up right away
+fn index_response(config: &ServerConfig) -> Response {
+    (
+        [(header::CACHE_CONTROL, assets::NO_CACHE)],
+        Html(get_index_html(config)),
+    )
+        .into_response()
+}
+
 /// Serve the index.html with runtime config
 async fn serve_index(
     axum::extract::State(config): axum::extract::State<std::sync::Arc<ServerConfig>>,
-) -> Html<String> {
-    Html(get_index_html(&config))
+) -> Response {
+    index_response(&config)
 }
 
-/// Serve static files or fallback to index.html for SPA routing
+/// Serve static files, a 404 for missing ones, or index.html for SPA routes
 async fn serve_static_or_index(
     axum::extract::State(config): axum::extract::State<std::sync::Arc<ServerConfig>>,
     Path(path): Path<String>,
+    headers: HeaderMap,
 ) -> Response {
-    // Try to serve the static file first
-    if let Some(file) = Assets::get(&path) {
-        let mime = mime_guess::from_path(&path).first_or_octet_stream();
-        let body = Body::from(file.data.to_vec());
-
-        Response::builder()
-            .status(StatusCode::OK)
-            .header(
-                header::CONTENT_TYPE,
-                HeaderValue::from_str(mime.as_ref()).unwrap(),
-            )
-            .body(body)
-            .unwrap()
-    } else {
-        // Fallback to index.html for SPA routing
-        Html(get_index_html(&config)).into_response()
+    // index.html always gets the runtime config injected
+    if path == "index.html" {
+        return index_response(&config);
+    }
+    match assets::serve(&path, &headers) {
+        Some(response) => response,
+        None if path.starts_with("api/") => console_error_response(ConsoleError::not_found(
+            "not_found",
+            format!("No API route at /{}", path),
+        )),
+        None if assets::is_asset_path(&path) => (
+            StatusCode::NOT_FOUND,
+            [(header::CACHE_CONTROL, assets::NO_CACHE)],
+            "Not found",
+        )
+            .into_response(),
+        None => index_response(&config),
     }
 }
 
@@ -168,15 +669,64 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
             axum::http::Method::POST,
             axum::http::Method::OPTIONS,
         ])
-        .allow_headers([header::CONTENT_TYPE, header::ACCEPT]);
+        .allow_headers([header::CONTENT_TYPE, header::ACCEPT, header::AUTHORIZATION]);
+
+    let tls_config = config.tls.clone();
+    let tls_host = config.host.clone();
 
     // Build the router
-    let app = Router::new()
+    let mut app = Router::new()
         .route("/", get(serve_index))
+        .route("/login", get(serve_login))
+        .route("/api/auth/login", post(login))
+        .route("/api/auth/logout", post(logout))
         .route("/api/config", get(serve_config))
-        .route("/{*path}", get(serve_static_or_index))
-        .layer(cors)
-        .with_state(config);
+        .route("/api/engines", get(serve_engines))
+        .route("/api/logs/stream", get(stream_logs))
+        .route("/api/traces/stream", get(stream_traces))
+        .route("/api/states/export", get(export_states))
+        .route("/{*path}", get(serve_static_or_index));
+    if config.engine_proxy.is_some() {
+        app = app
+            .route(ENGINE_PATH, any(proxy_engine))
+            .route(&format!("{}/{{*path}}", ENGINE_PATH), any(proxy_engine))
+            .route(ENGINE_WS_PATH, get(proxy_engine_ws))
+            .route(
+                &format!("{}/{{*path}}", ENGINE_WS_PATH),
+                get(proxy_engine_ws),
+            );
+    }
+    let app = app
+        .layer(middleware::from_fn_with_state(config.clone(), require_auth))
+        .with_state(config.clone());
+
+    // Under a base path, every route is nested below it, and both the root and
+    // the base path with a trailing slash redirect to the base path
+    let app = match config.base_path.as_str() {
+        "/" => app,
+        base => {
+            let home = base.to_string();
+            let to_home = get(move || async move { Redirect::to(&home) });
+            Router::new()
+                .route("/", to_home.clone())
+                .route(&format!("{}/", base), to_home)
+                .nest(base, app)
+        }
+    };
+    let app = app.layer(cors);
+
+    if let Some(tls_config) = tls_config {
+        let rustls_config = tls::load(&tls_config, &tls_host).await?;
+        tls::spawn_reload_on_sighup(&tls_config, rustls_config.clone());
+
+        info!("Console available at https://{}", addr);
+
+        axum_server::bind_rustls(addr, rustls_config)
+            .serve(app.into_make_service())
+            .await?;
+
+        return Ok(());
+    }
 
     info!("Console available at http://{}", addr);
 
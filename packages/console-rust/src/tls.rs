@@ -0,0 +1,83 @@
+use anyhow::{Context, Result};
+use axum_server::tls_rustls::RustlsConfig;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+/// Where the console server gets its TLS certificate from
+#[derive(Debug, Clone)]
+pub enum TlsConfig {
+    /// PEM certificate chain and private key files, reloaded on SIGHUP
+    Files { cert: PathBuf, key: PathBuf },
+    /// An ephemeral self-signed certificate generated at startup
+    SelfSigned,
+}
+
+/// Build the rustls configuration for the console server
+pub async fn load(tls: &TlsConfig, host: &str) -> Result<RustlsConfig> {
+    // Several dependencies enable rustls; pin the provider so the choice is explicit.
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    match tls {
+        TlsConfig::Files { cert, key } => RustlsConfig::from_pem_file(cert, key)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to load TLS certificate {} and key {}",
+                    cert.display(),
+                    key.display()
+                )
+            }),
+        TlsConfig::SelfSigned => {
+            let mut names = vec!["localhost".to_string(), "127.0.0.1".to_string()];
+            if !names.iter().any(|n| n == host) && host != "0.0.0.0" {
+                names.push(host.to_string());
+            }
+
+            let certified = rcgen::generate_simple_self_signed(names)
+                .context("Failed to generate self-signed certificate")?;
+            warn!("Serving HTTPS with an ephemeral self-signed certificate; browsers will show a warning");
+
+            RustlsConfig::from_pem(
+                certified.cert.pem().into_bytes(),
+                certified.key_pair.serialize_pem().into_bytes(),
+            )
+            .await
+            .context("Failed to load self-signed certificate")
+        }
+    }
+}
+
+/// Reload the certificate files whenever the process receives SIGHUP
+#[cfg(unix)]
+pub fn spawn_reload_on_sighup(tls: &TlsConfig, rustls_config: RustlsConfig) {
+    let TlsConfig::Files { cert, key } = tls.clone() else {
+        return;
+    };
+
+    tokio::spawn(async move {
+        let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+        {
+            Ok(signal) => signal,
+            Err(e) => {
+                warn!(
+                    "Failed to install SIGHUP handler, TLS reload disabled: {}",
+                    e
+                );
+                return;
+            }
+        };
+
+        while hangup.recv().await.is_some() {
+            match rustls_config.reload_from_pem_file(&cert, &key).await {
+                Ok(()) => info!("Reloaded TLS certificate from {}", cert.display()),
+                Err(e) => warn!(
+                    "Failed to reload TLS certificate, keeping the old one: {}",
+                    e
+                ),
+            }
+        }
+    });
+}
+
+#[cfg(not(unix))]
+pub fn spawn_reload_on_sighup(_tls: &TlsConfig, _rustls_config: RustlsConfig) {}